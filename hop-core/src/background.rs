@@ -0,0 +1,64 @@
+use crate::utils::{append_private, ensure_dir_exists};
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Start `hop` again with `args` in the background, detached from the
+/// terminal, with its output appended to `log_path`
+pub fn spawn(args: &[String], log_path: &Path) -> Result<u32> {
+    if let Some(parent) = log_path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    let log = append_private(log_path)
+        .with_context(|| format!("Failed to open {}", log_path.display()))?;
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // Own process group, so Ctrl-C in the starting shell does not stop it,
+    // and terminate() can stop its ssh children along with it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let child = command.spawn()?;
+    Ok(child.id())
+}
+
+#[cfg(unix)]
+pub fn is_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+/// Stop a process started by `spawn`, together with its process group
+#[cfg(unix)]
+pub fn terminate(pid: u32) -> Result<()> {
+    // SAFETY: sends SIGTERM to a process group we started from a pid file of our own
+    if unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGTERM) } != 0
+        && unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0
+    {
+        return Err(anyhow::anyhow!("pid {}: {}", pid, std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+}
+
+/// Stop a process started by `spawn`, together with its children
+#[cfg(not(unix))]
+pub fn terminate(pid: u32) -> Result<()> {
+    let status = Command::new("taskkill").args(["/PID", &pid.to_string(), "/T", "/F"]).status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("taskkill failed for pid {}", pid));
+    }
+    Ok(())
+}
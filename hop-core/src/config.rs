@@ -0,0 +1,987 @@
+use crate::exit_codes::{config_error, config_parse_error, server_not_found};
+use crate::index;
+use crate::models::Server;
+use crate::ssh::DEFAULT_CONNECT_TIMEOUT;
+use crate::utils::{ensure_dir_exists, parse_bool, parse_count, parse_list, parse_minutes, permission_problem, print_warning, write_private};
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    servers: Vec<Server>,
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub settings: Settings,
+    /// Built on the first lookup and dropped by changes that move servers around
+    #[serde(skip)]
+    index: OnceLock<ServerIndex>,
+}
+
+/// Positions in `Config::servers` by name, tag and host, so lookups stay
+/// fast with thousands of servers
+///
+/// Every list is in configuration order; names normally map to one
+/// position, but a hand-edited file may repeat one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ServerIndex {
+    by_name: BTreeMap<String, Vec<usize>>,
+    by_lowercase: BTreeMap<String, Vec<usize>>,
+    by_tag: HashMap<String, Vec<usize>>,
+    by_ip: HashMap<String, Vec<usize>>,
+}
+
+impl ServerIndex {
+    fn build(servers: &[Server]) -> Self {
+        let mut index = ServerIndex::default();
+        for (position, server) in servers.iter().enumerate() {
+            index.insert(position, server);
+        }
+        index
+    }
+
+    fn insert(&mut self, position: usize, server: &Server) {
+        self.by_name.entry(server.name.clone()).or_default().push(position);
+        self.by_lowercase.entry(server.name.to_lowercase()).or_default().push(position);
+        for tag in &server.tags {
+            self.by_tag.entry(tag.clone()).or_default().push(position);
+        }
+        self.by_ip.entry(server.ip.clone()).or_default().push(position);
+    }
+
+    /// Positions of names starting with `prefix`, in configuration order
+    fn with_prefix(names: &BTreeMap<String, Vec<usize>>, prefix: &str) -> Vec<usize> {
+        let mut positions: Vec<usize> = names
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .flat_map(|(_, positions)| positions.iter().copied())
+            .collect();
+        positions.sort_unstable();
+        positions
+    }
+}
+
+/// Servers worked on at once by health checks and reports unless the `parallel` setting says otherwise
+pub const DEFAULT_PARALLEL: usize = 8;
+
+/// User preferences stored alongside the servers, changed with `hop config set`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Match server identifiers regardless of case (exact-case matches still win)
+    pub case_insensitive: bool,
+    /// Servers with any of these tags require typing their name before `hop connect`
+    pub confirm_connect_tags: Vec<String>,
+    /// Servers with any of these tags require typing their name before `hop exec`
+    pub confirm_exec_tags: Vec<String>,
+    /// URL that receives a JSON event after every add, edit, remove, lock or import
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Local command run after every add, edit, remove, lock or import, with the server as JSON on stdin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change_hook: Option<String>,
+    /// Proxy for outgoing HTTP requests, overriding HTTPS_PROXY/HTTP_PROXY
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file with extra CA certificates to trust, e.g. a corporate proxy's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// PEM client certificate for servers that require mutual TLS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+    /// PEM private key matching `client_cert`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+    /// Also send `hop exec` audit entries to syslog
+    pub audit_syslog: bool,
+    /// Extra glob patterns (e.g. `ghp_*`) hidden in verbose output and logs
+    pub redact_patterns: Vec<String>,
+    /// Refuse to read config or secrets files that others can access, instead of warning
+    pub strict_permissions: bool,
+    /// Policy file for `hop exec`, instead of /etc/hop/policy.json or the config directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_file: Option<String>,
+    /// Minutes a pooled master connection stays open without being used
+    pub pool_idle: u64,
+    /// Days of failed health checks after which `hop list --stale` flags a server
+    pub stale_days: u64,
+    /// Months without a connection after which `hop list --stale` flags a server
+    pub stale_months: u64,
+    /// Servers worked on at once by health checks and reports without `-j`
+    pub parallel: usize,
+    /// Seconds ssh waits for a server to answer before giving up
+    pub connect_timeout: u64,
+}
+
+impl Settings {
+    /// Keys accepted by `hop config get/set`
+    pub const KEYS: &'static [&'static str] = &["case-insensitive", "confirm-connect-tags", "confirm-exec-tags", "webhook-url", "change-hook", "proxy", "ca-bundle", "client-cert", "client-key", "audit-syslog", "redact-patterns", "strict-permissions", "policy-file", "pool-idle", "stale-days", "stale-months", "parallel", "connect-timeout"];
+
+    fn is_default(&self) -> bool {
+        *self == Settings::default()
+    }
+
+    pub fn get(&self, key: &str) -> Result<String> {
+        match key {
+            "case-insensitive" => Ok(self.case_insensitive.to_string()),
+            "confirm-connect-tags" => Ok(self.confirm_connect_tags.join(",")),
+            "confirm-exec-tags" => Ok(self.confirm_exec_tags.join(",")),
+            "webhook-url" => Ok(self.webhook_url.clone().unwrap_or_default()),
+            "change-hook" => Ok(self.change_hook.clone().unwrap_or_default()),
+            "proxy" => Ok(self.proxy.clone().unwrap_or_default()),
+            "ca-bundle" => Ok(self.ca_bundle.clone().unwrap_or_default()),
+            "client-cert" => Ok(self.client_cert.clone().unwrap_or_default()),
+            "client-key" => Ok(self.client_key.clone().unwrap_or_default()),
+            "audit-syslog" => Ok(self.audit_syslog.to_string()),
+            "redact-patterns" => Ok(self.redact_patterns.join(",")),
+            "strict-permissions" => Ok(self.strict_permissions.to_string()),
+            "policy-file" => Ok(self.policy_file.clone().unwrap_or_default()),
+            "pool-idle" => Ok(self.pool_idle.to_string()),
+            "stale-days" => Ok(self.stale_days.to_string()),
+            "stale-months" => Ok(self.stale_months.to_string()),
+            "parallel" => Ok(self.parallel.to_string()),
+            "connect-timeout" => Ok(self.connect_timeout.to_string()),
+            _ => Err(unknown_setting(key)),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "case-insensitive" => self.case_insensitive = parse_bool(value)?,
+            "confirm-connect-tags" => self.confirm_connect_tags = parse_list(value),
+            "confirm-exec-tags" => self.confirm_exec_tags = parse_list(value),
+            "webhook-url" => self.webhook_url = parse_url(value)?,
+            "change-hook" => self.change_hook = parse_optional(value),
+            "proxy" => self.proxy = parse_optional(value),
+            "ca-bundle" => self.ca_bundle = parse_optional(value),
+            "client-cert" => self.client_cert = parse_optional(value),
+            "client-key" => self.client_key = parse_optional(value),
+            "audit-syslog" => self.audit_syslog = parse_bool(value)?,
+            "redact-patterns" => self.redact_patterns = parse_list(value),
+            "strict-permissions" => self.strict_permissions = parse_bool(value)?,
+            "policy-file" => self.policy_file = parse_optional(value),
+            "pool-idle" => self.pool_idle = parse_minutes(value)?,
+            "stale-days" => self.stale_days = parse_count(value, "days")?,
+            "stale-months" => self.stale_months = parse_count(value, "months")?,
+            "parallel" => self.parallel = parse_count(value, "servers")? as usize,
+            "connect-timeout" => self.connect_timeout = parse_count(value, "seconds")?,
+            _ => return Err(unknown_setting(key)),
+        }
+        Ok(())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            case_insensitive: true,
+            confirm_connect_tags: Vec::new(),
+            confirm_exec_tags: Vec::new(),
+            webhook_url: None,
+            change_hook: None,
+            proxy: None,
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            audit_syslog: false,
+            redact_patterns: Vec::new(),
+            strict_permissions: false,
+            policy_file: None,
+            pool_idle: 30,
+            stale_days: 30,
+            stale_months: 6,
+            parallel: DEFAULT_PARALLEL,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+        }
+    }
+}
+
+/// Parse an optional value; an empty value clears the setting
+fn parse_optional(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Parse an optional http(s) URL; an empty value clears the setting
+fn parse_url(value: &str) -> Result<Option<String>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if !value.starts_with("http://") && !value.starts_with("https://") {
+        return Err(anyhow::anyhow!("Invalid URL '{}'. Expected http:// or https://", value));
+    }
+    Ok(Some(value.to_string()))
+}
+
+fn unknown_setting(key: &str) -> anyhow::Error {
+    anyhow::anyhow!("Unknown setting '{}'. Available settings: {}", key, Settings::KEYS.join(", "))
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config {
+            servers: Vec::new(),
+            settings: Settings::default(),
+            index: OnceLock::new(),
+        }
+    }
+
+    fn index(&self) -> &ServerIndex {
+        self.index.get_or_init(|| ServerIndex::build(&self.servers))
+    }
+
+    /// Indexes of the servers with this name, honouring the case-insensitive setting
+    ///
+    /// A `group/name` identifier only matches the server in that group;
+    /// `/name` matches an ungrouped one.
+    fn positions(&self, identifier: &str) -> Vec<usize> {
+        let (group, name) = match identifier.rsplit_once('/') {
+            Some((group, name)) => (Some(group), name),
+            None => (None, identifier),
+        };
+        let index = self.index();
+        let matching = |positions: Option<&Vec<usize>>| -> Vec<usize> {
+            positions
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|&position| group.is_none_or(|group| self.is_group(&self.servers[position], group)))
+                .collect()
+        };
+        let positions = matching(index.by_name.get(name));
+        if positions.is_empty() && self.settings.case_insensitive {
+            return matching(index.by_lowercase.get(&name.to_lowercase()));
+        }
+        positions
+    }
+
+    /// Index of the server with this name
+    ///
+    /// A plain name used in several groups means the ungrouped server, like
+    /// a path without a directory; without one it needs its group in front.
+    /// That makes `Server::qualified_name` a key for every server.
+    fn position(&self, identifier: &str) -> Option<usize> {
+        let positions = self.positions(identifier);
+        let first = *positions.first()?;
+        let group = &self.servers[first].group;
+        if positions.iter().all(|&position| self.servers[position].group == *group) {
+            return Some(first);
+        }
+        positions.into_iter().find(|&position| self.servers[position].group.is_none())
+    }
+
+    /// Whether `server`'s name is already taken in its group
+    pub fn contains(&self, server: &Server) -> bool {
+        !self.positions(&format!("{}/{}", server.group.as_deref().unwrap_or_default(), server.name)).is_empty()
+    }
+
+    /// Replace the server behind each identifier
+    ///
+    /// All of them are looked up first, since moving one server can change
+    /// which server a plain name means.
+    pub fn replace_servers(&mut self, replacements: Vec<(String, Server)>) {
+        let positions: Vec<Option<usize>> = replacements.iter().map(|(identifier, _)| self.position(identifier)).collect();
+        self.index.take();
+        for ((_, server), position) in replacements.into_iter().zip(positions) {
+            if let Some(position) = position {
+                self.servers[position] = server;
+            }
+        }
+    }
+
+    pub fn add_server(&mut self, server: Server) -> Result<()> {
+        // Names only have to be unique within a group
+        if self.contains(&server) {
+            return Err(anyhow::anyhow!("Server with name '{}' already exists", server.qualified_name()));
+        }
+        
+        // Appending keeps every other position, so the index can be extended in place
+        if let Some(index) = self.index.get_mut() {
+            index.insert(self.servers.len(), &server);
+        }
+        self.servers.push(server);
+        Ok(())
+    }
+
+    pub fn remove_server(&mut self, identifier: &str) -> Result<Server> {
+        let index = self.position(identifier)
+            .ok_or_else(|| server_not_found(identifier))?;
+        
+        self.index.take();
+        Ok(self.servers.remove(index))
+    }
+
+    /// Remove every server that isn't locked, keeping the settings
+    pub fn clear_unlocked_servers(&mut self) {
+        self.index.take();
+        self.servers.retain(|server| server.locked);
+    }
+
+    pub fn find_server(&self, identifier: &str) -> Option<&Server> {
+        self.position(identifier).map(|index| &self.servers[index])
+    }
+
+    /// Resolve a user-supplied identifier
+    ///
+    /// Tries an exact name, then a 1-based list number, then a name prefix.
+    /// A name or prefix shared by several servers yields
+    /// `Resolution::Ambiguous`; `group/name` picks one of them.
+    pub fn resolve_server(&self, identifier: &str) -> Resolution<'_> {
+        if let Some(server) = self.find_server(identifier) {
+            return Resolution::Found(server);
+        }
+        let exact = self.positions(identifier);
+        if exact.len() > 1 {
+            return Resolution::Ambiguous(exact.into_iter().map(|position| &self.servers[position]).collect());
+        }
+        
+        let by_number = identifier
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| self.servers.get(index));
+        if let Some(server) = by_number {
+            return Resolution::Found(server);
+        }
+        
+        // `group/prefix` only looks in that group
+        let (group, prefix) = match identifier.rsplit_once('/') {
+            Some((group, prefix)) => (Some(group), prefix),
+            None => (None, identifier),
+        };
+        // Exact-case prefixes are a subset of the lowercase ones
+        let positions = if self.settings.case_insensitive {
+            ServerIndex::with_prefix(&self.index().by_lowercase, &prefix.to_lowercase())
+        } else {
+            ServerIndex::with_prefix(&self.index().by_name, prefix)
+        };
+        let mut candidates: Vec<&Server> = positions
+            .into_iter()
+            .map(|position| &self.servers[position])
+            .filter(|server| group.is_none_or(|group| self.is_group(server, group)))
+            .collect();
+        
+        match candidates.len() {
+            0 => Resolution::NotFound,
+            1 => Resolution::Found(candidates.remove(0)),
+            _ => Resolution::Ambiguous(candidates),
+        }
+    }
+
+    /// Whether `server` is directly in `group`, honouring the case-insensitive setting
+    fn is_group(&self, server: &Server, group: &str) -> bool {
+        let group = group.trim_matches('/');
+        match &server.group {
+            Some(own) if self.settings.case_insensitive => own.eq_ignore_ascii_case(group),
+            Some(own) => own == group,
+            None => group.is_empty(),
+        }
+    }
+
+    /// Number of servers directly in each group
+    pub fn groups(&self) -> BTreeMap<&str, usize> {
+        let mut groups = BTreeMap::new();
+        for group in self.servers.iter().filter_map(|s| s.group.as_deref()) {
+            *groups.entry(group).or_insert(0) += 1;
+        }
+        groups
+    }
+
+    /// Move the servers in `from` and its subgroups under `to`, keeping
+    /// their subgroups; locked servers stay where they are
+    ///
+    /// Returns the old and new qualified names of the servers moved. Nothing
+    /// moves when a name is already taken in its new group.
+    pub fn rename_group(&mut self, from: &str, to: &str) -> Result<Vec<(String, String)>> {
+        let moving: Vec<(usize, Server)> = self
+            .servers
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.in_group(from) && !s.locked)
+            .map(|(position, server)| {
+                let subgroup = server.group.as_deref().map(|own| own[from.len()..].to_string()).unwrap_or_default();
+                let mut moved = server.clone();
+                moved.group = Some(format!("{}{}", to, subgroup));
+                (position, moved)
+            })
+            .collect();
+        // A server that moves on frees its old name
+        for (_, moved) in &moving {
+            let taken = self
+                .positions(&moved.qualified_name())
+                .into_iter()
+                .any(|position| !moving.iter().any(|(other, _)| *other == position));
+            if taken {
+                return Err(anyhow::anyhow!("Server with name '{}' already exists", moved.qualified_name()));
+            }
+        }
+        
+        self.index.take();
+        let mut renamed = Vec::new();
+        for (position, moved) in moving {
+            renamed.push((self.servers[position].qualified_name(), moved.qualified_name()));
+            self.servers[position] = moved;
+        }
+        Ok(renamed)
+    }
+
+    /// The caller may rename or retag the server, so the index is rebuilt on the next lookup
+    pub fn find_server_mut(&mut self, identifier: &str) -> Option<&mut Server> {
+        let position = self.position(identifier)?;
+        self.index.take();
+        Some(&mut self.servers[position])
+    }
+
+    /// Check whether connecting to this server needs a typed confirmation
+    pub fn requires_connect_confirmation(&self, server: &Server) -> bool {
+        server.confirm_before_connect
+            || server.tags.iter().any(|tag| self.settings.confirm_connect_tags.contains(tag))
+    }
+
+    /// Check whether running commands on this server needs a typed confirmation
+    pub fn requires_exec_confirmation(&self, server: &Server) -> bool {
+        server.confirm_before_exec
+            || server.tags.iter().any(|tag| self.settings.confirm_exec_tags.contains(tag))
+    }
+
+    /// Find all servers matching a filter
+    pub fn filter_servers(&self, filter: &ServerFilter) -> Vec<&Server> {
+        let Some(tag) = filter.tags.first() else {
+            return self.servers.iter().collect();
+        };
+        // Start from the servers carrying the first tag, then check the rest
+        self.index()
+            .by_tag
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .map(|&position| &self.servers[position])
+            .filter(|s| filter.matches(s))
+            .collect()
+    }
+
+    /// Servers whose host is `ip`
+    pub fn servers_with_ip(&self, ip: &str) -> Vec<&Server> {
+        self.index()
+            .by_ip
+            .get(ip)
+            .into_iter()
+            .flatten()
+            .map(|&position| &self.servers[position])
+            .collect()
+    }
+
+    pub fn list_servers(&self) -> &[Server] {
+        &self.servers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+}
+
+/// Outcome of resolving a user-supplied server identifier
+#[derive(Debug)]
+pub enum Resolution<'a> {
+    Found(&'a Server),
+    Ambiguous(Vec<&'a Server>),
+    NotFound,
+}
+
+/// Criteria for selecting several servers at once (bulk operations)
+#[derive(Debug, Clone, Default)]
+pub struct ServerFilter {
+    /// Servers must carry every one of these tags
+    pub tags: Vec<String>,
+}
+
+impl ServerFilter {
+    pub fn new(tags: Vec<String>) -> Self {
+        ServerFilter { tags }
+    }
+
+    pub fn matches(&self, server: &Server) -> bool {
+        self.tags.iter().all(|tag| server.has_tag(tag))
+    }
+}
+
+impl std::fmt::Display for ServerFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tag {}", self.tags.join(" + "))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct ConfigManager {
+    config_path: PathBuf,
+}
+
+/// Set by `--no-cache`
+static NO_CACHE: AtomicBool = AtomicBool::new(false);
+
+/// Neither read nor write the on-disk config cache
+pub fn set_no_cache(no_cache: bool) {
+    NO_CACHE.store(no_cache, Ordering::Relaxed);
+}
+
+/// Modification time and size of `servers.json` when a cache was written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    /// Since the Unix epoch
+    modified: Duration,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+        Some(FileStamp { modified, len: metadata.len() })
+    }
+
+    /// Whether the file was last written long enough ago that another write
+    /// within the same clock tick can't go unnoticed
+    fn is_settled(&self) -> bool {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .is_ok_and(|now| now.saturating_sub(self.modified) >= Duration::from_secs(1))
+    }
+}
+
+/// The parsed config and its server index, reused by later runs while
+/// `servers.json` keeps the stamp it had
+#[derive(Serialize, Deserialize)]
+struct ConfigCache {
+    stamp: FileStamp,
+    config: Config,
+    index: ServerIndex,
+}
+
+impl ConfigManager {
+    pub fn new() -> Result<Self> {
+        let config_path = get_config_path()?;
+        Ok(ConfigManager { config_path })
+    }
+
+    pub fn load(&self) -> Result<Config> {
+        if !self.config_path.exists() {
+            return Ok(Config::new());
+        }
+
+        let stamp = if NO_CACHE.load(Ordering::Relaxed) { None } else { FileStamp::of(&self.config_path) };
+        if let Some(config) = stamp.as_ref().and_then(|stamp| self.read_cache(stamp)) {
+            check_permissions(&self.config_path, config.settings.strict_permissions)?;
+            return Ok(config);
+        }
+
+        let contents = fs::read_to_string(&self.config_path)
+            .map_err(|e| config_error(format!("Failed to read config file {}", self.config_path.display()), e))?;
+
+        if contents.trim().is_empty() {
+            return Ok(Config::new());
+        }
+
+        let config: Config = serde_json::from_str(&contents)
+            .map_err(|e| config_parse_error(format!("Failed to parse config file {}", self.config_path.display()), e))?;
+
+        check_permissions(&self.config_path, config.settings.strict_permissions)?;
+        if let Some(stamp) = stamp.filter(FileStamp::is_settled) {
+            // A missing cache only costs the next run a parse
+            if let Err(e) = self.write_cache(stamp, &config) {
+                debug!("Failed to write the config cache: {:#}", e);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Next to `servers.json`, since it holds the same data
+    fn cache_path(&self) -> PathBuf {
+        self.config_path.with_file_name("servers.cache.json")
+    }
+
+    /// The cached config, if it was written for the file as it is now
+    fn read_cache(&self, stamp: &FileStamp) -> Option<Config> {
+        let contents = fs::read(self.cache_path()).ok()?;
+        let cache: ConfigCache = serde_json::from_slice(&contents).ok()?;
+        if cache.stamp != *stamp {
+            debug!("Config cache is stale, reading {}", self.config_path.display());
+            return None;
+        }
+        let mut config = cache.config;
+        config.index = OnceLock::from(cache.index);
+        Some(config)
+    }
+
+    fn write_cache(&self, stamp: FileStamp, config: &Config) -> Result<()> {
+        let cache = ConfigCache { stamp, config: config.clone(), index: config.index().clone() };
+        let contents = serde_json::to_string(&cache)?;
+        write_private(&self.cache_path(), &contents)?;
+        Ok(())
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        // Ensure the parent directory exists
+        if let Some(parent) = self.config_path.parent() {
+            ensure_dir_exists(parent)
+                .map_err(|e| config_error("Failed to create config directory", e))?;
+        }
+
+        let contents = serde_json::to_string_pretty(config)
+            .map_err(|e| config_error("Failed to serialize config", e))?;
+
+        write_private(&self.config_path, &contents)
+            .map_err(|e| config_error(format!("Failed to write config file {}", self.config_path.display()), e))?;
+
+        // The stamp changed anyway, but don't leave an outdated copy around
+        let _ = fs::remove_file(self.cache_path());
+
+        // Completion and pickers rebuild a stale index themselves, so this is best effort
+        if let Err(e) = index::write_index(&self.config_path, config) {
+            debug!("Failed to update the server index: {:#}", e);
+        }
+        Ok(())
+    }
+
+    pub fn get_config_path(&self) -> &Path {
+        &self.config_path
+    }
+}
+
+impl Default for ConfigManager {
+    fn default() -> Self {
+        Self::new().expect("Failed to create ConfigManager")
+    }
+}
+
+/// Warn about (or, when `strict`, refuse) a file other users could read or change
+///
+/// The warning is printed once per run even though the config is loaded
+/// several times.
+pub fn check_permissions(path: &Path, strict: bool) -> Result<()> {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+
+    let Some(problem) = permission_problem(path) else {
+        return Ok(());
+    };
+    if strict {
+        return Err(config_error("Refusing to use a file other users can access", problem));
+    }
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        print_warning(&format!("{}. Set strict-permissions to refuse such files.", problem));
+    }
+    Ok(())
+}
+
+fn get_config_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| config_error("Could not find config directory", "no home directory"))?;
+
+    let hop_dir = config_dir.join("hop");
+    let config_path = hop_dir.join("servers.json");
+
+    Ok(config_path)
+}
+
+/// Load configuration from file
+pub fn load_config() -> Result<Config> {
+    let manager = ConfigManager::new()?;
+    manager.load()
+}
+
+/// Save configuration to file
+pub fn save_config(config: &Config) -> Result<()> {
+    let manager = ConfigManager::new()?;
+    manager.save(config)
+}
+
+/// Get the path to the configuration file
+pub fn get_config_file_path() -> Result<PathBuf> {
+    let manager = ConfigManager::new()?;
+    Ok(manager.get_config_path().to_path_buf())
+}
+
+/// Initialize configuration directory and file if they don't exist
+pub fn init_config() -> Result<()> {
+    let manager = ConfigManager::new()?;
+    
+    if !manager.config_path.exists() {
+        let config = Config::new();
+        manager.save(&config)?;
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_add_server() {
+        let mut config = Config::new();
+        let server = Server::new("test".to_string(), "user".to_string(), "192.168.1.1".to_string());
+        
+        assert!(config.add_server(server).is_ok());
+        assert_eq!(config.servers.len(), 1);
+    }
+
+    #[test]
+    fn test_config_add_duplicate_server() {
+        let mut config = Config::new();
+        let server1 = Server::new("test".to_string(), "user".to_string(), "192.168.1.1".to_string());
+        let server2 = Server::new("test".to_string(), "user".to_string(), "192.168.1.2".to_string());
+        
+        assert!(config.add_server(server1).is_ok());
+        assert!(config.add_server(server2).is_err());
+    }
+
+    #[test]
+    fn test_config_find_server() {
+        let mut config = Config::new();
+        let server = Server::new("test".to_string(), "user".to_string(), "192.168.1.1".to_string());
+        
+        config.add_server(server).unwrap();
+        
+        assert!(config.find_server("test").is_some());
+        assert!(config.find_server("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_config_groups() {
+        let mut config = Config::new();
+        let mut grouped = |name: &str, group: &str| {
+            let mut server = Server::new(name.to_string(), "user".to_string(), "192.168.1.1".to_string());
+            server.group = Some(group.to_string()).filter(|g| !g.is_empty());
+            config.add_server(server).unwrap();
+        };
+        grouped("web", "clients/acme");
+        grouped("web2", "clients/acme/eu");
+        grouped("web3", "staging");
+        grouped("db", "");
+        grouped("app", "staging");
+        grouped("app", "prod");
+
+        assert!(config.find_server("clients/acme/web").is_some());
+        assert!(config.find_server("Clients/ACME/web").is_some());
+        assert!(config.find_server("staging/web").is_none());
+        assert!(config.find_server("/db").is_some());
+        assert!(matches!(config.resolve_server("web"), Resolution::Found(s) if s.name == "web"));
+        assert!(matches!(config.resolve_server("w"), Resolution::Ambiguous(c) if c.len() == 3));
+        assert!(matches!(config.resolve_server("staging/w"), Resolution::Found(s) if s.name == "web3"));
+        assert!(matches!(config.resolve_server("clients/w"), Resolution::NotFound));
+
+        // Names only have to be unique within a group
+        assert!(matches!(config.resolve_server("staging/app"), Resolution::Found(s) if s.group.as_deref() == Some("staging")));
+        assert!(matches!(config.resolve_server("prod/app"), Resolution::Found(s) if s.group.as_deref() == Some("prod")));
+        assert!(matches!(config.resolve_server("app"), Resolution::Ambiguous(c) if c.len() == 2));
+        assert!(config.find_server("app").is_none());
+        let mut duplicate = Server::new("app".to_string(), "user".to_string(), "192.168.1.2".to_string());
+        duplicate.group = Some("prod".to_string());
+        assert!(config.add_server(duplicate.clone()).is_err());
+        duplicate.group = None;
+        config.add_server(duplicate).unwrap();
+        assert!(matches!(config.resolve_server("app"), Resolution::Found(s) if s.group.is_none()));
+        config.remove_server("app").unwrap();
+
+        assert_eq!(
+            config.groups().into_iter().collect::<Vec<_>>(),
+            [("clients/acme", 1), ("clients/acme/eu", 1), ("prod", 1), ("staging", 2)]
+        );
+
+        assert!(config.rename_group("prod", "staging").is_err());
+        assert_eq!(config.servers[5].group.as_deref(), Some("prod"));
+
+        config.servers[0].locked = true;
+        let renamed = config.rename_group("clients", "customers").unwrap();
+        assert_eq!(renamed, [("clients/acme/eu/web2".to_string(), "customers/acme/eu/web2".to_string())]);
+        assert_eq!(config.servers[1].group.as_deref(), Some("customers/acme/eu"));
+        assert_eq!(config.servers[0].group.as_deref(), Some("clients/acme"));
+    }
+
+    #[test]
+    fn test_config_resolve_server_by_number() {
+        let mut config = Config::new();
+        config.add_server(Server::new("web".to_string(), "user".to_string(), "192.168.1.1".to_string())).unwrap();
+        config.add_server(Server::new("2".to_string(), "user".to_string(), "192.168.1.2".to_string())).unwrap();
+
+        assert!(matches!(config.resolve_server("1"), Resolution::Found(s) if s.name == "web"));
+        // Exact names win over list numbers
+        assert!(matches!(config.resolve_server("2"), Resolution::Found(s) if s.name == "2"));
+        assert!(matches!(config.resolve_server("0"), Resolution::NotFound));
+        assert!(matches!(config.resolve_server("3"), Resolution::NotFound));
+    }
+
+    #[test]
+    fn test_config_resolve_server_by_prefix() {
+        let mut config = Config::new();
+        config.add_server(Server::new("prod-db".to_string(), "user".to_string(), "192.168.1.1".to_string())).unwrap();
+        config.add_server(Server::new("prod-web".to_string(), "user".to_string(), "192.168.1.2".to_string())).unwrap();
+        config.add_server(Server::new("staging".to_string(), "user".to_string(), "192.168.1.3".to_string())).unwrap();
+
+        assert!(matches!(config.resolve_server("prod-d"), Resolution::Found(s) if s.name == "prod-db"));
+        assert!(matches!(config.resolve_server("st"), Resolution::Found(s) if s.name == "staging"));
+        assert!(matches!(config.resolve_server("prod"), Resolution::Ambiguous(c) if c.len() == 2));
+        assert!(matches!(config.resolve_server("dev"), Resolution::NotFound));
+    }
+
+    #[test]
+    fn test_config_case_insensitive_matching() {
+        let mut config = Config::new();
+        config.add_server(Server::new("WebServer".to_string(), "user".to_string(), "192.168.1.1".to_string())).unwrap();
+
+        assert!(config.find_server("webserver").is_some());
+        assert!(matches!(config.resolve_server("WEB"), Resolution::Found(_)));
+        assert!(config.add_server(Server::new("webserver".to_string(), "user".to_string(), "192.168.1.2".to_string())).is_err());
+
+        config.settings.set("case-insensitive", "false").unwrap();
+        assert!(config.find_server("webserver").is_none());
+        assert!(config.find_server("WebServer").is_some());
+        assert!(matches!(config.resolve_server("web"), Resolution::NotFound));
+    }
+
+    #[test]
+    fn test_config_confirmation_guards() {
+        let mut config = Config::new();
+        let mut prod = Server::new("prod-db".to_string(), "user".to_string(), "192.168.1.1".to_string());
+        prod.tags = vec!["prod".to_string()];
+        let mut dev = Server::new("dev".to_string(), "user".to_string(), "192.168.1.2".to_string());
+        dev.confirm_before_connect = true;
+
+        config.settings.set("confirm-exec-tags", "prod, pci").unwrap();
+        assert!(config.requires_exec_confirmation(&prod));
+        assert!(!config.requires_connect_confirmation(&prod));
+        assert!(config.requires_connect_confirmation(&dev));
+        assert!(!config.requires_exec_confirmation(&dev));
+    }
+
+    #[test]
+    fn test_settings_get_set() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.get("case-insensitive").unwrap(), "true");
+        settings.set("case-insensitive", "off").unwrap();
+        assert_eq!(settings.get("case-insensitive").unwrap(), "false");
+        assert!(settings.set("case-insensitive", "maybe").is_err());
+        assert!(settings.set("nope", "true").is_err());
+
+        settings.set("webhook-url", "https://hooks.example.com/hop").unwrap();
+        assert_eq!(settings.webhook_url.as_deref(), Some("https://hooks.example.com/hop"));
+        assert!(settings.set("webhook-url", "hooks.example.com").is_err());
+        settings.set("webhook-url", "").unwrap();
+        assert_eq!(settings.webhook_url, None);
+    }
+
+    #[test]
+    fn test_config_filter_servers() {
+        let mut config = Config::new();
+        let mut web = Server::new("web".to_string(), "user".to_string(), "192.168.1.1".to_string());
+        web.tags = vec!["prod".to_string(), "legacy".to_string()];
+        let mut db = Server::new("db".to_string(), "user".to_string(), "192.168.1.2".to_string());
+        db.tags = vec!["prod".to_string()];
+        config.add_server(web).unwrap();
+        config.add_server(db).unwrap();
+
+        assert_eq!(config.filter_servers(&ServerFilter::new(vec!["prod".to_string()])).len(), 2);
+        let legacy = config.filter_servers(&ServerFilter::new(vec!["prod".to_string(), "legacy".to_string()]));
+        assert_eq!(legacy.len(), 1);
+        assert_eq!(legacy[0].name, "web");
+    }
+
+    #[test]
+    fn test_config_remove_server() {
+        let mut config = Config::new();
+        let server = Server::new("test".to_string(), "user".to_string(), "192.168.1.1".to_string());
+        
+        config.add_server(server).unwrap();
+        assert_eq!(config.servers.len(), 1);
+        
+        assert!(config.remove_server("test").is_ok());
+        assert_eq!(config.servers.len(), 0);
+        
+        assert!(config.remove_server("nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_config_index_stays_current() {
+        let mut config = Config::new();
+        for (name, ip) in [("web-1", "10.0.0.1"), ("web-2", "10.0.0.2"), ("db", "10.0.0.1")] {
+            config.add_server(Server::new(name.to_string(), "user".to_string(), ip.to_string())).unwrap();
+        }
+        assert_eq!(config.servers_with_ip("10.0.0.1").len(), 2);
+
+        // Removing shifts later servers down
+        config.remove_server("web-1").unwrap();
+        assert_eq!(config.find_server("db").unwrap().ip, "10.0.0.1");
+        assert_eq!(config.servers_with_ip("10.0.0.1").len(), 1);
+
+        // Renaming and retagging through a mutable reference
+        let server = config.find_server_mut("web-2").unwrap();
+        server.name = "web".to_string();
+        server.tags = vec!["prod".to_string()];
+        assert!(config.find_server("web-2").is_none());
+        assert!(matches!(config.resolve_server("we"), Resolution::Found(s) if s.name == "web"));
+        assert_eq!(config.filter_servers(&ServerFilter::new(vec!["prod".to_string()]))[0].name, "web");
+
+        config.add_server(Server::new("web-3".to_string(), "user".to_string(), "10.0.0.3".to_string())).unwrap();
+        assert!(matches!(config.resolve_server("web-"), Resolution::Found(s) if s.name == "web-3"));
+        config.find_server_mut("web-3").unwrap().locked = true;
+        config.clear_unlocked_servers();
+        assert!(config.find_server("web").is_none());
+        assert!(config.find_server("web-3").is_some());
+    }
+
+    #[test]
+    fn test_config_load_reads_current_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager { config_path: dir.path().join("servers.json") };
+        let mut config = Config::new();
+        config.add_server(Server::new("web".to_string(), "user".to_string(), "10.0.0.1".to_string())).unwrap();
+        manager.save(&config).unwrap();
+        assert!(manager.load().unwrap().find_server("web").is_some());
+
+        // Another process rewrites the file
+        fs::write(&manager.config_path, r#"{"servers": [{"name": "db", "user": "user", "ip": "10.0.0.2"}]}"#).unwrap();
+        let loaded = manager.load().unwrap();
+        assert!(loaded.find_server("web").is_none());
+        assert!(loaded.find_server("db").is_some());
+    }
+
+    #[test]
+    fn test_config_cache_follows_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager { config_path: dir.path().join("servers.json") };
+        let mut config = Config::new();
+        config.add_server(Server::new("web".to_string(), "user".to_string(), "10.0.0.1".to_string())).unwrap();
+        manager.save(&config).unwrap();
+        let age = |path: &Path| {
+            let old = SystemTime::now() - Duration::from_secs(60);
+            fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+        };
+
+        // A file written just now may still change within the same tick
+        manager.load().unwrap();
+        assert!(!manager.cache_path().exists());
+
+        age(&manager.config_path);
+        manager.load().unwrap();
+        let cached = fs::read_to_string(manager.cache_path()).unwrap();
+        fs::write(manager.cache_path(), cached.replace("\"web\"", "\"cached\"")).unwrap();
+        assert!(manager.load().unwrap().find_server("cached").is_some());
+
+        // Another process rewrites the file
+        fs::write(&manager.config_path, r#"{"servers": [{"name": "db", "user": "user", "ip": "10.0.0.2"}]}"#).unwrap();
+        age(&manager.config_path);
+        let loaded = manager.load().unwrap();
+        assert!(loaded.find_server("cached").is_none());
+        assert!(loaded.find_server("db").is_some());
+    }
+} 
\ No newline at end of file
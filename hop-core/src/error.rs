@@ -0,0 +1,118 @@
+use crate::exit_codes::{ErrorKind, AUTH_ERROR, CONFIG_ERROR, GENERAL_ERROR, SERVER_NOT_FOUND, SSH_FAILURE, USAGE_ERROR};
+use std::fmt;
+
+/// Failures that callers may want to tell apart; each one maps to an exit code
+///
+/// Functions still return `anyhow::Error`; use [`HopError::find`] to get the
+/// variant back out of one, even after context has been added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HopError {
+    /// No server matches the identifier
+    ServerNotFound(String),
+    /// The configuration file could not be read or written
+    ConfigError(String),
+    /// The configuration file is not valid JSON
+    InvalidConfig(String),
+    /// A required program (ssh, scp, sshpass, ...) is not installed
+    NotInstalled(String),
+    /// The server rejected our credentials
+    AuthError(String),
+    /// The server's host key doesn't match the one on record
+    HostKeyMismatch(String),
+    /// ssh failed; `code` is the remote command's exit status when it got that far
+    SshFailure { code: Option<i32>, message: String },
+    /// scp could not copy a file
+    TransferError(String),
+    /// Invalid command line usage
+    UsageError(String),
+    /// Any other failure, e.g. a command that failed on some servers
+    Failed(String),
+}
+
+impl HopError {
+    /// Find the `HopError` behind an error, looking through added context
+    pub fn find(error: &anyhow::Error) -> Option<&HopError> {
+        error.chain().find_map(|cause| cause.downcast_ref::<HopError>())
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HopError::ServerNotFound(_) => SERVER_NOT_FOUND,
+            HopError::ConfigError(_) | HopError::InvalidConfig(_) => CONFIG_ERROR,
+            HopError::AuthError(_) => AUTH_ERROR,
+            HopError::NotInstalled(_) | HopError::HostKeyMismatch(_) | HopError::TransferError(_) => SSH_FAILURE,
+            HopError::SshFailure { code, .. } => code.unwrap_or(SSH_FAILURE),
+            HopError::UsageError(_) => USAGE_ERROR,
+            HopError::Failed(_) => GENERAL_ERROR,
+        }
+    }
+
+    /// The known failure with a stable identifier and hint, if this is one
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            HopError::ServerNotFound(_) => Some(ErrorKind::ServerNotFound),
+            HopError::NotInstalled(program) if program == "sshpass" => Some(ErrorKind::SshpassNotInstalled),
+            HopError::NotInstalled(_) => Some(ErrorKind::SshNotInstalled),
+            HopError::AuthError(_) => Some(ErrorKind::PermissionDenied),
+            HopError::HostKeyMismatch(_) => Some(ErrorKind::HostKeyMismatch),
+            HopError::InvalidConfig(_) => Some(ErrorKind::ConfigParse),
+            _ => None,
+        }
+    }
+
+    /// Whether the server could not be reached at all, as opposed to a
+    /// command that ran and failed
+    pub fn is_unreachable(&self) -> bool {
+        matches!(
+            self,
+            HopError::SshFailure { code: None, .. } | HopError::HostKeyMismatch(_) | HopError::NotInstalled(_)
+        )
+    }
+}
+
+impl fmt::Display for HopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HopError::ServerNotFound(identifier) => write!(f, "Server '{}' not found", identifier),
+            HopError::NotInstalled(program) => write!(f, "'{}' was not found", program),
+            HopError::SshFailure { message, .. } => write!(f, "{}", message),
+            HopError::ConfigError(message)
+            | HopError::InvalidConfig(message)
+            | HopError::AuthError(message)
+            | HopError::HostKeyMismatch(message)
+            | HopError::TransferError(message)
+            | HopError::UsageError(message)
+            | HopError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for HopError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_find_through_context() {
+        let error = Err::<(), _>(HopError::ServerNotFound("web".to_string()))
+            .context("while connecting")
+            .unwrap_err();
+        assert_eq!(HopError::find(&error), Some(&HopError::ServerNotFound("web".to_string())));
+        assert_eq!(HopError::find(&anyhow::anyhow!("boom")), None);
+    }
+
+    #[test]
+    fn test_exit_codes_and_kinds() {
+        let remote = HopError::SshFailure { code: Some(7), message: "x".to_string() };
+        assert_eq!((remote.exit_code(), remote.kind(), remote.is_unreachable()), (7, None, false));
+
+        let lost = HopError::SshFailure { code: None, message: "x".to_string() };
+        assert_eq!((lost.exit_code(), lost.is_unreachable()), (SSH_FAILURE, true));
+
+        assert_eq!(HopError::NotInstalled("sshpass".to_string()).kind(), Some(ErrorKind::SshpassNotInstalled));
+        assert_eq!(HopError::UsageError("x".to_string()).exit_code(), USAGE_ERROR);
+        assert_eq!(HopError::TransferError("x".to_string()).exit_code(), SSH_FAILURE);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::error::HopError;
+use std::fmt;
+use std::io;
+
+/// Generic failure (invalid input, I/O errors, ...)
+pub const GENERAL_ERROR: i32 = 1;
+/// The requested server does not exist in the configuration
+pub const SERVER_NOT_FOUND: i32 = 2;
+/// ssh/scp could not be run or the connection failed
+pub const SSH_FAILURE: i32 = 3;
+/// The configuration file could not be read, parsed or written
+pub const CONFIG_ERROR: i32 = 4;
+/// The server rejected our credentials
+pub const AUTH_ERROR: i32 = 5;
+/// Invalid command line usage (EX_USAGE)
+pub const USAGE_ERROR: i32 = 64;
+
+/// Common failures that come with a stable identifier and a suggested fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    ServerNotFound,
+    SshNotInstalled,
+    PermissionDenied,
+    HostKeyMismatch,
+    ConfigParse,
+    SshpassNotInstalled,
+}
+
+impl ErrorKind {
+    /// Identifier printed next to the error; never renumber or reuse these
+    pub fn id(self) -> &'static str {
+        match self {
+            ErrorKind::ServerNotFound => "E001",
+            ErrorKind::SshNotInstalled => "E002",
+            ErrorKind::PermissionDenied => "E003",
+            ErrorKind::HostKeyMismatch => "E004",
+            ErrorKind::ConfigParse => "E005",
+            ErrorKind::SshpassNotInstalled => "E006",
+        }
+    }
+
+    /// One-line suggestion for fixing the problem
+    pub fn hint(self) -> &'static str {
+        match self {
+            ErrorKind::ServerNotFound => "run `hop list` to see the configured servers",
+            ErrorKind::SshNotInstalled => "install the OpenSSH client and make sure `ssh` and `scp` are on your PATH",
+            ErrorKind::PermissionDenied => "check the user name and that your key is loaded (`ssh-add -l`)",
+            ErrorKind::HostKeyMismatch => "if the server was reinstalled, remove the old key with `ssh-keygen -R <host>`",
+            ErrorKind::ConfigParse => "fix the JSON at the reported line, or move the file aside to start with an empty config",
+            ErrorKind::SshpassNotInstalled => "install sshpass, or remove the stored password with `hop secret remove <server> password`",
+        }
+    }
+}
+
+/// Build the error returned when a server lookup fails
+pub fn server_not_found(identifier: &str) -> anyhow::Error {
+    HopError::ServerNotFound(identifier.to_string()).into()
+}
+
+/// Build a configuration error, keeping the underlying cause in the message
+pub fn config_error(message: impl fmt::Display, cause: impl fmt::Display) -> anyhow::Error {
+    HopError::ConfigError(format!("{}: {}", message, cause)).into()
+}
+
+/// Build the error returned when the config file is not valid JSON
+pub fn config_parse_error(message: impl fmt::Display, cause: impl fmt::Display) -> anyhow::Error {
+    HopError::InvalidConfig(format!("{}: {}", message, cause)).into()
+}
+
+/// Build the error returned when ssh or scp cannot be started
+pub fn spawn_failure(program: &str, error: io::Error) -> anyhow::Error {
+    if error.kind() == io::ErrorKind::NotFound {
+        return HopError::NotInstalled(program.to_string()).into();
+    }
+    HopError::SshFailure { code: None, message: format!("Failed to execute {}: {}", program, error) }.into()
+}
+
+/// Classify a failed ssh invocation from its stderr output
+///
+/// ssh itself exits with 255 on connection or authentication problems; any
+/// other code comes from the remote command and is passed through unchanged.
+pub fn ssh_failure(code: Option<i32>, stderr: &str, message: impl Into<String>) -> anyhow::Error {
+    let message = message.into();
+    let error = match code {
+        Some(255) | None if stderr.contains("Permission denied") => HopError::AuthError(message),
+        Some(255) | None if stderr.contains("Host key verification failed")
+            || stderr.contains("REMOTE HOST IDENTIFICATION HAS CHANGED") =>
+        {
+            HopError::HostKeyMismatch(message)
+        }
+        Some(255) | None => HopError::SshFailure { code: None, message },
+        Some(code) => HopError::SshFailure { code: Some(code), message },
+    };
+    error.into()
+}
+
+/// Find the exit code for an error, defaulting to GENERAL_ERROR
+pub fn exit_code_for(error: &anyhow::Error) -> i32 {
+    HopError::find(error)
+        .map(HopError::exit_code)
+        .unwrap_or(GENERAL_ERROR)
+}
+
+/// Find the known failure behind an error, if any
+pub fn error_kind_for(error: &anyhow::Error) -> Option<ErrorKind> {
+    HopError::find(error).and_then(HopError::kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_for() {
+        assert_eq!(exit_code_for(&server_not_found("web")), SERVER_NOT_FOUND);
+        assert_eq!(exit_code_for(&anyhow::anyhow!("boom")), GENERAL_ERROR);
+
+        let wrapped = Err::<(), _>(server_not_found("web")).context("while connecting").unwrap_err();
+        assert_eq!(exit_code_for(&wrapped), SERVER_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_ssh_failure_classification() {
+        assert_eq!(exit_code_for(&ssh_failure(Some(255), "Permission denied (publickey).", "x")), AUTH_ERROR);
+        assert_eq!(exit_code_for(&ssh_failure(Some(255), "Connection refused", "x")), SSH_FAILURE);
+        assert_eq!(exit_code_for(&ssh_failure(Some(7), "", "x")), 7);
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let denied = ssh_failure(Some(255), "Permission denied (publickey).", "x");
+        assert_eq!(error_kind_for(&denied), Some(ErrorKind::PermissionDenied));
+
+        let host_key = ssh_failure(None, "Host key verification failed.", "x");
+        assert_eq!(error_kind_for(&host_key), Some(ErrorKind::HostKeyMismatch));
+        assert_eq!(exit_code_for(&host_key), SSH_FAILURE);
+
+        let missing = spawn_failure("ssh", io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(error_kind_for(&missing), Some(ErrorKind::SshNotInstalled));
+        assert_eq!(exit_code_for(&missing), SSH_FAILURE);
+
+        assert_eq!(error_kind_for(&ssh_failure(Some(7), "Permission denied", "x")), None);
+        assert_eq!(error_kind_for(&anyhow::anyhow!("boom")), None);
+    }
+}
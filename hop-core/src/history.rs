@@ -0,0 +1,159 @@
+use crate::index;
+use crate::utils::{append_private, ensure_dir_exists, now_unix};
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// One recorded use of a server
+///
+/// A session writes two entries: one when it starts, and one with
+/// `duration_secs` set when it ends. Sessions that hop could not see end
+/// (e.g. a closed terminal) only have the first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub server: String,
+    /// Unix timestamp (seconds) when the session started
+    pub timestamp: u64,
+    /// How long the session lasted, on the entry written when it ended
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+}
+
+/// Time spent on one server on one day
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DayTotal {
+    pub sessions: usize,
+    pub seconds: u64,
+}
+
+/// Aggregated usage of a single server
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Usage {
+    pub last_used: u64,
+    pub count: usize,
+}
+
+/// Get the path to the history file (one JSON entry per line)
+pub fn get_history_file_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("history.jsonl"))
+}
+
+/// Append a connection to the history file, returning its start time
+pub fn record_connect(server: &str) -> Result<u64> {
+    let timestamp = now_unix();
+    append(&HistoryEntry { server: server.to_string(), timestamp, duration_secs: None })?;
+    if let Err(e) = index::touch(server, timestamp) {
+        debug!("Failed to update the server index: {:#}", e);
+    }
+    Ok(timestamp)
+}
+
+/// Append the end of a session started at `started_at`
+pub fn record_session_end(server: &str, started_at: u64, duration_secs: u64) -> Result<()> {
+    append(&HistoryEntry { server: server.to_string(), timestamp: started_at, duration_secs: Some(duration_secs) })
+}
+
+fn append(entry: &HistoryEntry) -> Result<()> {
+    let path = get_history_file_path()?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+
+    let mut file = append_private(&path)
+        .with_context(|| format!("Failed to open history file: {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write history file: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Load all history entries, skipping lines that cannot be parsed
+pub fn load_history() -> Result<Vec<HistoryEntry>> {
+    let path = get_history_file_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+
+    Ok(parse_history(&contents))
+}
+
+fn parse_history(contents: &str) -> Vec<HistoryEntry> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Summarize history per server name
+pub fn usage_by_server(entries: &[HistoryEntry]) -> HashMap<String, Usage> {
+    let mut usage: HashMap<String, Usage> = HashMap::new();
+    for entry in entries.iter().filter(|e| e.duration_secs.is_none()) {
+        let stats = usage.entry(entry.server.clone()).or_default();
+        stats.count += 1;
+        stats.last_used = stats.last_used.max(entry.timestamp);
+    }
+    usage
+}
+
+/// Total session time per (day, server) for sessions that started at or
+/// after `since`; `day_of` maps a start time to its day label
+pub fn time_by_day(entries: &[HistoryEntry], since: u64, day_of: impl Fn(u64) -> String) -> BTreeMap<(String, String), DayTotal> {
+    let mut totals: BTreeMap<(String, String), DayTotal> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| e.timestamp >= since) {
+        let Some(seconds) = entry.duration_secs else {
+            continue;
+        };
+        let total = totals.entry((day_of(entry.timestamp), entry.server.clone())).or_default();
+        total.sessions += 1;
+        total.seconds += seconds;
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_by_server() {
+        let entries = parse_history(
+            "{\"server\":\"web\",\"timestamp\":100}\n\
+             not json\n\
+             {\"server\":\"db\",\"timestamp\":150}\n\
+             {\"server\":\"web\",\"timestamp\":300}\n",
+        );
+        assert_eq!(entries.len(), 3);
+
+        let usage = usage_by_server(&entries);
+        assert_eq!(usage["web"], Usage { last_used: 300, count: 2 });
+        assert_eq!(usage["db"], Usage { last_used: 150, count: 1 });
+    }
+
+    #[test]
+    fn test_time_by_day() {
+        let entries = parse_history(
+            "{\"server\":\"web\",\"timestamp\":100}\n\
+             {\"server\":\"web\",\"timestamp\":100,\"duration_secs\":600}\n\
+             {\"server\":\"web\",\"timestamp\":90000,\"duration_secs\":60}\n\
+             {\"server\":\"web\",\"timestamp\":90500,\"duration_secs\":30}\n\
+             {\"server\":\"db\",\"timestamp\":50,\"duration_secs\":5}\n",
+        );
+        // Completion entries do not count as extra uses
+        assert_eq!(usage_by_server(&entries)["web"].count, 1);
+
+        let totals = time_by_day(&entries, 60, |t| (t / 86_400).to_string());
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[&("0".to_string(), "web".to_string())], DayTotal { sessions: 1, seconds: 600 });
+        assert_eq!(totals[&("1".to_string(), "web".to_string())], DayTotal { sessions: 2, seconds: 90 });
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::{get_config_file_path, load_config, Config};
+use crate::history;
+use crate::utils::{ensure_dir_exists, write_private};
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What completion and pickers need to know about a server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Unix timestamp of the last connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<u64>,
+}
+
+/// Get the path to the server index, next to `servers.json`
+pub fn get_index_file_path() -> Result<PathBuf> {
+    Ok(index_path_for(&get_config_file_path()?))
+}
+
+fn index_path_for(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("index.json")
+}
+
+/// Rewrite the index for `config`, keeping the last-used times it already has
+///
+/// Called whenever the config is saved. Without an index yet, the times are
+/// taken from the connection history once.
+pub fn write_index(config_path: &Path, config: &Config) -> Result<()> {
+    let path = index_path_for(config_path);
+    let last_used: HashMap<String, u64> = match read(&path) {
+        Some(entries) => entries.into_iter().filter_map(|e| Some((e.name, e.last_used?))).collect(),
+        None => history::load_history()
+            .map(|entries| history::usage_by_server(&entries))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, usage)| (name, usage.last_used))
+            .collect(),
+    };
+    let entries: Vec<IndexEntry> = config
+        .list_servers()
+        .iter()
+        .map(|server| IndexEntry {
+            name: server.qualified_name(),
+            tags: server.tags.clone(),
+            last_used: last_used.get(&server.qualified_name()).copied(),
+        })
+        .collect();
+    write(&path, &entries)
+}
+
+/// Record a connection to `server` in the index
+pub fn touch(server: &str, timestamp: u64) -> Result<()> {
+    let path = get_index_file_path()?;
+    let Some(mut entries) = read(&path) else {
+        // Built with the history, which already has this connection
+        return Ok(());
+    };
+    if let Some(entry) = entries.iter_mut().find(|e| e.name == server) {
+        entry.last_used = Some(timestamp);
+        write(&path, &entries)?;
+    }
+    Ok(())
+}
+
+/// Load the index, rebuilding it when it is missing or older than the config
+///
+/// The config is only read in that case; normally this is a single small
+/// file read.
+pub fn load_index() -> Result<Vec<IndexEntry>> {
+    let config_path = get_config_file_path()?;
+    let path = index_path_for(&config_path);
+    if !is_stale(&path, &config_path) {
+        if let Some(entries) = read(&path) {
+            return Ok(entries);
+        }
+    }
+
+    debug!("Rebuilding {}", path.display());
+    let config = load_config()?;
+    if config_path.exists() {
+        // Start from the history, since the config may have been edited by hand
+        let _ = fs::remove_file(&path);
+        write_index(&config_path, &config)?;
+        if let Some(entries) = read(&path) {
+            return Ok(entries);
+        }
+    }
+    Ok(config
+        .list_servers()
+        .iter()
+        .map(|server| IndexEntry { name: server.qualified_name(), tags: server.tags.clone(), last_used: None })
+        .collect())
+}
+
+/// Entries sorted by most recent connection; never-used servers come last, by name
+pub fn by_recent_use(mut entries: Vec<IndexEntry>) -> Vec<IndexEntry> {
+    entries.sort_by(|a, b| b.last_used.cmp(&a.last_used).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+fn is_stale(index: &Path, config: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(index), modified(config)) {
+        (Some(index), Some(config)) => index < config,
+        (None, _) => true,
+        (Some(_), None) => false,
+    }
+}
+
+/// The index at `path`, or `None` when it is missing or unreadable
+fn read(path: &Path) -> Option<Vec<IndexEntry>> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write(path: &Path, entries: &[IndexEntry]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    write_private(path, &serde_json::to_string(entries)?)
+        .with_context(|| format!("Failed to write server index: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Server;
+
+    fn entry(name: &str, last_used: Option<u64>) -> IndexEntry {
+        IndexEntry { name: name.to_string(), tags: Vec::new(), last_used }
+    }
+
+    #[test]
+    fn test_write_keeps_last_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("servers.json");
+        write(&index_path_for(&config_path), &[entry("web", Some(100)), entry("gone", Some(50))]).unwrap();
+
+        let mut config = Config::new();
+        let mut web = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        web.tags = vec!["prod".to_string()];
+        config.add_server(web).unwrap();
+        config.add_server(Server::new("db".to_string(), "root".to_string(), "10.0.0.2".to_string())).unwrap();
+        write_index(&config_path, &config).unwrap();
+
+        let entries = read(&index_path_for(&config_path)).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], IndexEntry { name: "web".to_string(), tags: vec!["prod".to_string()], last_used: Some(100) });
+        assert_eq!(entries[1], entry("db", None));
+    }
+
+    #[test]
+    fn test_by_recent_use() {
+        let sorted = by_recent_use(vec![entry("b", None), entry("old", Some(1)), entry("a", None), entry("new", Some(9))]);
+        let names: Vec<&str> = sorted.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["new", "old", "a", "b"]);
+    }
+}
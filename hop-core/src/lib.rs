@@ -0,0 +1,56 @@
+//! Server inventory, configuration and SSH connection logic behind the `hop` CLI.
+//!
+//! Other tools can read and change the same `servers.json` that `hop` uses,
+//! and connect through the same ssh invocation, without shelling out to the
+//! binary.
+//!
+//! ```no_run
+//! use hop_core::config::load_config;
+//! use hop_core::ssh::SshClient;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let config = load_config()?;
+//!     for server in config.list_servers() {
+//!         println!("{}", server);
+//!     }
+//!
+//!     if let Some(server) = config.find_server("web") {
+//!         print!("{}", SshClient::new().capture(server, "uptime")?);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Status messages are printed the same way the CLI prints them; call
+//! [`utils::set_quiet`] to silence the informational ones.
+//!
+//! Functions return `anyhow::Error`. Use [`error::HopError::find`] to match on
+//! the failure (server not found, authentication, ssh exit status, ...)
+//! instead of its message.
+
+/// Detached `hop` processes and their logs
+pub mod background;
+/// The server list and settings in `servers.json`
+pub mod config;
+/// Typed errors for failures callers may want to tell apart
+pub mod error;
+/// Process exit codes and the stable identifiers of common failures
+pub mod exit_codes;
+/// Connection history in `history.jsonl`
+pub mod history;
+/// Server names, tags and last use for completion and pickers, in `index.json`
+pub mod index;
+/// The `Server` entry and its per-server options
+pub mod models;
+/// Home directory, OpenSSH and path differences between Unix, Windows and WSL
+pub mod platform;
+/// Shared ssh master connections
+pub mod pool;
+/// Masking secrets in logged commands
+pub mod redact;
+/// Forwarding Ctrl-C and other signals to ssh, and cleaning up after them
+pub mod signals;
+/// Running ssh and scp against a `Server`
+pub mod ssh;
+/// Output, prompts, parsing and private file helpers
+pub mod utils;
@@ -0,0 +1,639 @@
+use crate::platform::expand_home;
+use crate::utils::{host_for_uri, is_valid_server_name, normalize_host, parse_bool, parse_duration, parse_list, parse_minutes};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Fields that can be changed with `hop edit --set key=value`
+pub const SETTABLE_FIELDS: &[&str] = &["user", "ip", "port", "tags", "group", "description", "notes", "confirm_before_connect", "confirm_before_exec", "identity_file", "identity_agent", "vault_role", "vault_mount", "vault_otp", "bitwarden_item", "bitwarden_passphrase", "pool_idle", "pre_connect", "pre_connect_timeout", "pre_connect_on_failure", "post_disconnect", "post_disconnect_timeout", "post_disconnect_on_failure"];
+
+/// Port sshd listens on unless configured otherwise
+pub const DEFAULT_SSH_PORT: u16 = 22;
+
+/// How long a pre_connect/post_disconnect command may run unless configured otherwise
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `identity_agent` value that stands for the 1Password SSH agent socket
+pub const ONEPASSWORD_AGENT: &str = "1password";
+/// `identity_agent` value that stands for gpg-agent's SSH socket
+pub const GPG_AGENT: &str = "gpg";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Server {
+    pub name: String,
+    pub user: String,
+    pub ip: String,
+    /// SSH port; DEFAULT_SSH_PORT when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Group path such as "staging" or "clients/acme"; ungrouped when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// One-line summary of what the server is for, shown by `hop list -v` and `hop show`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Free-form notes shown in the banner before connecting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Require typing the server name before an interactive session
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub confirm_before_connect: bool,
+    /// Require typing the server name before running remote commands
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub confirm_before_exec: bool,
+    /// Locked entries cannot be edited or removed until unlocked with `hop lock --unlock`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub locked: bool,
+    /// Private key offered to this server, and the only one ssh tries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    /// Agent socket used for this server; "1password" and "gpg" pick those agents' sockets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_agent: Option<String>,
+    /// Vault SSH role to get a signed certificate (or OTP) from before connecting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_role: Option<String>,
+    /// Mount path of Vault's SSH secrets engine; "ssh" when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_mount: Option<String>,
+    /// Ask Vault for a one-time password instead of a signed certificate
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub vault_otp: bool,
+    /// Bitwarden item whose password is fetched with rbw/bw at connect time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitwarden_item: Option<String>,
+    /// The Bitwarden password unlocks the SSH key rather than the account
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bitwarden_passphrase: bool,
+    /// Minutes a pooled master connection stays open unused; the pool-idle setting when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_idle: Option<u64>,
+    /// Host keys confirmed with `hop add --scan`, as `<type> <base64>`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub host_keys: Vec<String>,
+    /// Unix timestamp (seconds) when the server was added or imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<u64>,
+    /// Local shell command run before connecting, e.g. to bring up a VPN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_connect: Option<String>,
+    /// Seconds pre_connect may run; DEFAULT_HOOK_TIMEOUT when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_connect_timeout: Option<u64>,
+    /// What a failing pre_connect does; aborts the connection when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_connect_on_failure: Option<OnFailure>,
+    /// Local shell command run after the session ends, e.g. to unmount a share
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_disconnect: Option<String>,
+    /// Seconds post_disconnect may run; DEFAULT_HOOK_TIMEOUT when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_disconnect_timeout: Option<u64>,
+    /// What a failing post_disconnect does; warns when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_disconnect_on_failure: Option<OnFailure>,
+}
+
+/// What to do when a pre_connect/post_disconnect command fails or times out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnFailure {
+    /// Stop with an error
+    Abort,
+    /// Print a warning and carry on
+    Warn,
+    /// Carry on silently
+    Ignore,
+}
+
+impl OnFailure {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "abort" => Ok(OnFailure::Abort),
+            "warn" => Ok(OnFailure::Warn),
+            "ignore" => Ok(OnFailure::Ignore),
+            _ => Err(anyhow::anyhow!("Invalid value '{}'. Expected abort, warn or ignore", value)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OnFailure::Abort => "abort",
+            OnFailure::Warn => "warn",
+            OnFailure::Ignore => "ignore",
+        }
+    }
+}
+
+/// A local command run around a session, with its limits filled in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalHook<'a> {
+    pub command: &'a str,
+    pub timeout: Duration,
+    pub on_failure: OnFailure,
+}
+
+/// Ask gpgconf where gpg-agent listens for SSH clients
+fn gpg_agent_socket() -> Option<String> {
+    let output = std::process::Command::new("gpgconf")
+        .args(["--list-dirs", "agent-ssh-socket"])
+        .output()
+        .ok()?;
+    let socket = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !socket.is_empty()).then_some(socket)
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// The error for a host that is neither an IP address nor a hostname
+pub fn invalid_host(host: &str) -> anyhow::Error {
+    anyhow::anyhow!("Invalid host '{}'. Expected an IPv4 or IPv6 address or a hostname.", host.trim())
+}
+
+/// Validate a group path like "clients/acme"; an empty value means no group
+pub fn normalize_group(value: &str) -> Result<Option<String>> {
+    let value = value.trim().trim_matches('/');
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let parts: Vec<&str> = value.split('/').map(str::trim).collect();
+    if !parts.iter().all(|part| is_valid_server_name(part)) {
+        return Err(anyhow::anyhow!(
+            "Invalid group '{}'. Use names of letters, digits, hyphens and underscores separated by '/'.",
+            value
+        ));
+    }
+    Ok(Some(parts.join("/")))
+}
+
+/// An empty value resets the port to the default
+fn parse_port(value: &str) -> Result<Option<u16>> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    match value.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Ok(Some(port)),
+        _ => Err(anyhow::anyhow!("Invalid port '{}'. Expected a number from 1 to 65535.", value.trim())),
+    }
+}
+
+/// An empty value resets a hook timeout to the default
+fn parse_timeout(value: &str) -> Result<Option<u64>> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(parse_duration(value)?.as_secs()))
+}
+
+/// An empty value resets a hook's failure handling to the default
+fn parse_on_failure(value: &str) -> Result<Option<OnFailure>> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    OnFailure::parse(value).map(Some)
+}
+
+impl Server {
+    pub fn new(name: String, user: String, ip: String) -> Self {
+        Server {
+            name,
+            user,
+            ip,
+            port: None,
+            tags: Vec::new(),
+            group: None,
+            description: None,
+            notes: None,
+            confirm_before_connect: false,
+            confirm_before_exec: false,
+            locked: false,
+            identity_file: None,
+            identity_agent: None,
+            vault_role: None,
+            vault_mount: None,
+            vault_otp: false,
+            bitwarden_item: None,
+            bitwarden_passphrase: false,
+            pool_idle: None,
+            host_keys: Vec::new(),
+            added_at: None,
+            pre_connect: None,
+            pre_connect_timeout: None,
+            pre_connect_on_failure: None,
+            post_disconnect: None,
+            post_disconnect_timeout: None,
+            post_disconnect_on_failure: None,
+        }
+    }
+
+    /// The private key to use, with a leading `~/` resolved
+    pub fn identity_file_path(&self) -> Option<PathBuf> {
+        self.identity_file.as_deref().map(expand_home)
+    }
+
+    /// The agent socket to use, with "1password", "gpg" and a leading `~/` resolved
+    pub fn identity_agent_path(&self) -> Option<String> {
+        let agent = self.identity_agent.as_deref()?;
+        if agent == GPG_AGENT {
+            return gpg_agent_socket();
+        }
+        if agent != ONEPASSWORD_AGENT {
+            return Some(expand_home(agent).to_string_lossy().into_owned());
+        }
+        if cfg!(windows) {
+            return Some(r"\\.\pipe\openssh-ssh-agent".to_string());
+        }
+        let socket = if cfg!(target_os = "macos") {
+            "Library/Group Containers/2BUA8C4S2C.com.1password/t/agent.sock"
+        } else {
+            ".1password/agent.sock"
+        };
+        let home = dirs::home_dir()?;
+        Some(home.join(socket).to_string_lossy().into_owned())
+    }
+
+    /// Fail if this entry is locked against modification
+    pub fn ensure_unlocked(&self) -> Result<()> {
+        if self.locked {
+            return Err(anyhow::anyhow!(
+                "Server '{}' is locked. Run 'hop lock {} --unlock' first.",
+                self.name, self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// The command to run before connecting, if any
+    pub fn pre_connect_hook(&self) -> Option<LocalHook<'_>> {
+        Some(LocalHook {
+            command: self.pre_connect.as_deref()?,
+            timeout: self.pre_connect_timeout.map_or(DEFAULT_HOOK_TIMEOUT, Duration::from_secs),
+            on_failure: self.pre_connect_on_failure.unwrap_or(OnFailure::Abort),
+        })
+    }
+
+    /// The command to run after the session ends, if any
+    pub fn post_disconnect_hook(&self) -> Option<LocalHook<'_>> {
+        Some(LocalHook {
+            command: self.post_disconnect.as_deref()?,
+            timeout: self.post_disconnect_timeout.map_or(DEFAULT_HOOK_TIMEOUT, Duration::from_secs),
+            on_failure: self.post_disconnect_on_failure.unwrap_or(OnFailure::Warn),
+        })
+    }
+
+    /// The port sshd listens on
+    pub fn ssh_port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_SSH_PORT)
+    }
+
+    /// The port, when it isn't the default one
+    fn custom_port(&self) -> Option<u16> {
+        self.port.filter(|&port| port != DEFAULT_SSH_PORT)
+    }
+
+    /// The host as ssh records it in known_hosts: `[host]:port` off port 22
+    pub fn known_hosts_name(&self) -> String {
+        match self.custom_port() {
+            Some(port) => format!("[{}]:{}", self.ip, port),
+            None => self.ip.clone(),
+        }
+    }
+
+    /// The pre_connect/post_disconnect commands that are set
+    pub fn local_hook_commands(&self) -> Vec<String> {
+        self.pre_connect.iter().chain(&self.post_disconnect).cloned().collect()
+    }
+
+    /// Remove pre_connect/post_disconnect and their limits, returning the commands
+    ///
+    /// They run on the local machine, so records from elsewhere (imports,
+    /// QR payloads) should not carry them.
+    pub fn strip_local_hooks(&mut self) -> Vec<String> {
+        let commands = self.pre_connect.take().into_iter().chain(self.post_disconnect.take()).collect();
+        self.pre_connect_timeout = None;
+        self.pre_connect_on_failure = None;
+        self.post_disconnect_timeout = None;
+        self.post_disconnect_on_failure = None;
+        commands
+    }
+
+    /// Check if this server carries the given tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// The name with its group in front, e.g. "clients/acme/web"
+    ///
+    /// Names are only unique within a group, so history, secrets and other
+    /// per-server state are keyed by this.
+    pub fn qualified_name(&self) -> String {
+        match &self.group {
+            Some(group) => format!("{}/{}", group, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Whether the server is in `group` or one of its subgroups
+    pub fn in_group(&self, group: &str) -> bool {
+        self.group.as_deref().is_some_and(|own| {
+            own == group || own.strip_prefix(group).is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    /// Add a tag unless the server already carries it; returns whether it was added
+    pub fn add_tag(&mut self, tag: &str) -> Result<bool> {
+        let tag = tag.trim();
+        if tag.is_empty() || tag.contains(',') {
+            return Err(anyhow::anyhow!("Invalid tag '{}'. Tags cannot be empty or contain commas.", tag));
+        }
+        if self.has_tag(tag) {
+            return Ok(false);
+        }
+        self.tags.push(tag.to_string());
+        Ok(true)
+    }
+
+    /// Drop a tag; returns whether the server carried it
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|t| t != tag.trim());
+        self.tags.len() != before
+    }
+
+    /// Get the display value of a settable field
+    pub fn field(&self, key: &str) -> Option<String> {
+        match key {
+            "user" => Some(self.user.clone()),
+            "ip" => Some(self.ip.clone()),
+            "port" => Some(self.port.map(|p| p.to_string()).unwrap_or_default()),
+            "tags" => Some(self.tags.join(",")),
+            "group" => Some(self.group.clone().unwrap_or_default()),
+            "description" => Some(self.description.clone().unwrap_or_default()),
+            "notes" => Some(self.notes.clone().unwrap_or_default()),
+            "confirm_before_connect" => Some(self.confirm_before_connect.to_string()),
+            "confirm_before_exec" => Some(self.confirm_before_exec.to_string()),
+            "identity_file" => Some(self.identity_file.clone().unwrap_or_default()),
+            "identity_agent" => Some(self.identity_agent.clone().unwrap_or_default()),
+            "vault_role" => Some(self.vault_role.clone().unwrap_or_default()),
+            "vault_mount" => Some(self.vault_mount.clone().unwrap_or_default()),
+            "vault_otp" => Some(self.vault_otp.to_string()),
+            "bitwarden_item" => Some(self.bitwarden_item.clone().unwrap_or_default()),
+            "bitwarden_passphrase" => Some(self.bitwarden_passphrase.to_string()),
+            "pool_idle" => Some(self.pool_idle.map(|m| m.to_string()).unwrap_or_default()),
+            "pre_connect" => Some(self.pre_connect.clone().unwrap_or_default()),
+            "pre_connect_timeout" => Some(self.pre_connect_timeout.map(|s| format!("{}s", s)).unwrap_or_default()),
+            "pre_connect_on_failure" => Some(self.pre_connect_on_failure.map(OnFailure::as_str).unwrap_or_default().to_string()),
+            "post_disconnect" => Some(self.post_disconnect.clone().unwrap_or_default()),
+            "post_disconnect_timeout" => Some(self.post_disconnect_timeout.map(|s| format!("{}s", s)).unwrap_or_default()),
+            "post_disconnect_on_failure" => Some(self.post_disconnect_on_failure.map(OnFailure::as_str).unwrap_or_default().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Set a field by name, validating the new value
+    pub fn set_field(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "user" => {
+                if value.is_empty() {
+                    return Err(anyhow::anyhow!("User cannot be empty."));
+                }
+                self.user = value.to_string();
+            }
+            "ip" => self.ip = normalize_host(value).ok_or_else(|| invalid_host(value))?,
+            "port" => self.port = parse_port(value)?,
+            "tags" => self.tags = parse_list(value),
+            "group" => self.group = normalize_group(value)?,
+            "description" => self.description = Some(value.trim().to_string()).filter(|d| !d.is_empty()),
+            "notes" => self.notes = Some(value.to_string()).filter(|n| !n.is_empty()),
+            "confirm_before_connect" => self.confirm_before_connect = parse_bool(value)?,
+            "confirm_before_exec" => self.confirm_before_exec = parse_bool(value)?,
+            "identity_file" => self.identity_file = Some(value.to_string()).filter(|f| !f.is_empty()),
+            "identity_agent" => self.identity_agent = Some(value.to_string()).filter(|a| !a.is_empty()),
+            "vault_role" => self.vault_role = Some(value.to_string()).filter(|r| !r.is_empty()),
+            "vault_mount" => self.vault_mount = Some(value.trim_matches('/').to_string()).filter(|m| !m.is_empty()),
+            "vault_otp" => self.vault_otp = parse_bool(value)?,
+            "bitwarden_item" => self.bitwarden_item = Some(value.to_string()).filter(|i| !i.is_empty()),
+            "bitwarden_passphrase" => self.bitwarden_passphrase = parse_bool(value)?,
+            "pool_idle" if value.trim().is_empty() => self.pool_idle = None,
+            "pool_idle" => self.pool_idle = Some(parse_minutes(value)?),
+            "pre_connect" => self.pre_connect = Some(value.to_string()).filter(|c| !c.trim().is_empty()),
+            "pre_connect_timeout" => self.pre_connect_timeout = parse_timeout(value)?,
+            "pre_connect_on_failure" => self.pre_connect_on_failure = parse_on_failure(value)?,
+            "post_disconnect" => self.post_disconnect = Some(value.to_string()).filter(|c| !c.trim().is_empty()),
+            "post_disconnect_timeout" => self.post_disconnect_timeout = parse_timeout(value)?,
+            "post_disconnect_on_failure" => self.post_disconnect_on_failure = parse_on_failure(value)?,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown field '{}'. Settable fields: {}",
+                    key,
+                    SETTABLE_FIELDS.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse an ad-hoc `user@host` target that is not in the configuration
+    ///
+    /// The host doubles as the server name until the entry is saved.
+    pub fn from_target(target: &str) -> Option<Self> {
+        let (user, host) = target.split_once('@')?;
+        let host = normalize_host(host).filter(|_| !user.is_empty())?;
+        Some(Server::new(host.clone(), user.to_string(), host))
+    }
+
+    /// Returns the identifier for this server (name)
+    pub fn identifier(&self) -> &str {
+        &self.name
+    }
+
+    /// Generate the SSH command for this server
+    pub fn ssh_command(&self) -> String {
+        match self.custom_port() {
+            Some(port) => format!("ssh -p {} {}@{}", port, self.user, self.ip),
+            None => format!("ssh {}@{}", self.user, self.ip),
+        }
+    }
+
+    /// Generate an `ssh://` URI understood by most mobile SSH clients
+    pub fn ssh_uri(&self) -> String {
+        let port = self.custom_port().map(|port| format!(":{}", port)).unwrap_or_default();
+        format!("ssh://{}@{}{}", self.user, host_for_uri(&self.ip), port)
+    }
+}
+
+impl fmt::Display for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.custom_port() {
+            Some(port) => write!(f, "{} -> {}@{}:{}", self.name, self.user, host_for_uri(&self.ip), port),
+            None => write!(f, "{} -> {}@{}", self.name, self.user, self.ip),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_creation() {
+        let server = Server::new("test-server".to_string(), "root".to_string(), "192.168.1.10".to_string());
+        assert_eq!(server.name, "test-server");
+        assert_eq!(server.user, "root");
+        assert_eq!(server.ip, "192.168.1.10");
+    }
+
+    #[test]
+    fn test_set_field() {
+        let mut server = Server::new("test-server".to_string(), "root".to_string(), "192.168.1.10".to_string());
+        server.set_field("user", "deploy").unwrap();
+        server.set_field("tags", "legacy, web,").unwrap();
+        assert_eq!(server.user, "deploy");
+        assert_eq!(server.tags, vec!["legacy", "web"]);
+        assert!(server.has_tag("web"));
+        assert_eq!(server.field("tags").unwrap(), "legacy,web");
+        server.set_field("description", " Billing API, EU ").unwrap();
+        assert_eq!(server.description.as_deref(), Some("Billing API, EU"));
+        server.set_field("description", "").unwrap();
+        assert_eq!(server.description, None);
+
+        assert!(server.add_tag("prod").unwrap());
+        assert!(!server.add_tag(" web ").unwrap());
+        assert!(server.add_tag("a,b").is_err());
+        assert!(server.remove_tag("legacy"));
+        assert!(!server.remove_tag("legacy"));
+        assert_eq!(server.tags, vec!["web", "prod"]);
+
+        assert!(server.set_field("ip", "not a host").is_err());
+        assert!(server.set_field("name", "other").is_err());
+        assert_eq!(server.ip, "192.168.1.10");
+    }
+
+    #[test]
+    fn test_strip_local_hooks() {
+        let mut server = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        assert!(server.strip_local_hooks().is_empty());
+
+        server.set_field("pre_connect", "wg-quick up office").unwrap();
+        server.set_field("pre_connect_timeout", "1m").unwrap();
+        server.set_field("post_disconnect", "wg-quick down office").unwrap();
+        assert_eq!(server.strip_local_hooks(), ["wg-quick up office", "wg-quick down office"]);
+        assert_eq!(server.pre_connect_hook(), None);
+        assert_eq!(server.pre_connect_timeout, None);
+        assert_eq!(server.post_disconnect_hook(), None);
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut server = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        assert_eq!(server.qualified_name(), "web");
+
+        server.set_field("group", "/clients/ acme/").unwrap();
+        assert_eq!(server.group.as_deref(), Some("clients/acme"));
+        assert_eq!(server.qualified_name(), "clients/acme/web");
+        assert!(server.in_group("clients"));
+        assert!(server.in_group("clients/acme"));
+        assert!(!server.in_group("client"));
+        assert!(!server.in_group("clients/acme/eu"));
+
+        assert!(server.set_field("group", "clients//acme").is_err());
+        assert!(server.set_field("group", "clients/a b").is_err());
+        server.set_field("group", "").unwrap();
+        assert_eq!(server.group, None);
+    }
+
+    #[test]
+    fn test_from_target() {
+        let server = Server::from_target("deploy@203.0.113.7").unwrap();
+        assert_eq!(server.user, "deploy");
+        assert_eq!(server.ip, "203.0.113.7");
+
+        assert!(Server::from_target("deploy").is_none());
+        assert!(Server::from_target("@203.0.113.7").is_none());
+        assert!(Server::from_target("deploy@not an ip").is_none());
+
+        let server = Server::from_target("deploy@[2001:db8::7]").unwrap();
+        assert_eq!(server.ip, "2001:db8::7");
+        assert_eq!(server.ssh_uri(), "ssh://deploy@[2001:db8::7]");
+    }
+
+    #[test]
+    fn test_identity_agent_path() {
+        let mut server = Server::new("web".to_string(), "deploy".to_string(), "10.0.0.1".to_string());
+        assert_eq!(server.identity_agent_path(), None);
+
+        server.set_field("identity_agent", "/run/agent.sock").unwrap();
+        assert_eq!(server.identity_agent_path().as_deref(), Some("/run/agent.sock"));
+
+        server.set_field("identity_agent", "~/agent.sock").unwrap();
+        assert!(!server.identity_agent_path().unwrap().starts_with('~'));
+
+        server.set_field("identity_agent", "1password").unwrap();
+        assert!(server.identity_agent_path().unwrap().contains("1password") || cfg!(windows));
+    }
+
+    #[test]
+    fn test_local_hooks() {
+        let mut server = Server::new("web".to_string(), "deploy".to_string(), "10.0.0.1".to_string());
+        assert_eq!(server.pre_connect_hook(), None);
+
+        server.set_field("pre_connect", "wg-quick up office").unwrap();
+        server.set_field("post_disconnect", "wg-quick down office").unwrap();
+        server.set_field("post_disconnect_timeout", "2m").unwrap();
+        server.set_field("post_disconnect_on_failure", "Ignore").unwrap();
+        assert!(server.set_field("pre_connect_on_failure", "retry").is_err());
+
+        let pre = server.pre_connect_hook().unwrap();
+        assert_eq!((pre.command, pre.timeout, pre.on_failure), ("wg-quick up office", DEFAULT_HOOK_TIMEOUT, OnFailure::Abort));
+        let post = server.post_disconnect_hook().unwrap();
+        assert_eq!((post.timeout, post.on_failure), (Duration::from_secs(120), OnFailure::Ignore));
+        assert_eq!(server.field("post_disconnect_timeout").unwrap(), "120s");
+
+        server.set_field("post_disconnect_timeout", "").unwrap();
+        assert_eq!(server.post_disconnect_hook().unwrap().timeout, DEFAULT_HOOK_TIMEOUT);
+    }
+
+    #[test]
+    fn test_ssh_uri() {
+        let server = Server::new("web".to_string(), "deploy".to_string(), "10.0.0.1".to_string());
+        assert_eq!(server.ssh_uri(), "ssh://deploy@10.0.0.1");
+
+        let mut server = Server::new("v6".to_string(), "deploy".to_string(), "2001:db8::7".to_string());
+        server.port = Some(2222);
+        assert_eq!(server.ssh_uri(), "ssh://deploy@[2001:db8::7]:2222");
+        assert_eq!(server.to_string(), "v6 -> deploy@[2001:db8::7]:2222");
+    }
+
+    #[test]
+    fn test_ensure_unlocked() {
+        let mut server = Server::new("test-server".to_string(), "root".to_string(), "192.168.1.10".to_string());
+        assert!(server.ensure_unlocked().is_ok());
+        server.locked = true;
+        assert!(server.ensure_unlocked().is_err());
+    }
+
+    #[test]
+    fn test_ssh_command() {
+        let server = Server::new("test-server".to_string(), "root".to_string(), "192.168.1.10".to_string());
+        assert_eq!(server.ssh_command(), "ssh root@192.168.1.10");
+        assert_eq!(server.known_hosts_name(), "192.168.1.10");
+
+        let mut server = server;
+        server.set_field("port", "2222").unwrap();
+        assert_eq!(server.ssh_port(), 2222);
+        assert_eq!(server.ssh_command(), "ssh -p 2222 root@192.168.1.10");
+        assert_eq!(server.known_hosts_name(), "[192.168.1.10]:2222");
+
+        assert!(server.set_field("port", "0").is_err());
+        assert!(server.set_field("port", "70000").is_err());
+        server.set_field("port", "").unwrap();
+        assert_eq!(server.port, None);
+        assert_eq!(server.ssh_port(), DEFAULT_SSH_PORT);
+    }
+} 
\ No newline at end of file
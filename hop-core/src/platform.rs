@@ -0,0 +1,115 @@
+use anyhow::Result;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The file ssh can use to discard known hosts
+pub const NULL_DEVICE: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+/// Expand a leading `~` to the home directory
+///
+/// Windows OpenSSH doesn't expand `~` in every option, so paths are expanded
+/// before they are handed to ssh. Both `~/` and `~\` are accepted.
+pub fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) if rest.starts_with(['/', '\\']) => &rest[1..],
+        _ => return PathBuf::from(path),
+    };
+    match dirs::home_dir() {
+        Some(home) if rest.is_empty() => home,
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// The user's `~/.ssh/known_hosts`
+pub fn known_hosts_path() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home.join(".ssh").join("known_hosts"))
+}
+
+/// Whether hop runs inside the Windows Subsystem for Linux
+pub fn is_wsl() -> bool {
+    static WSL: OnceLock<bool> = OnceLock::new();
+    *WSL.get_or_init(|| {
+        cfg!(target_os = "linux")
+            && std::fs::read_to_string("/proc/sys/kernel/osrelease")
+                .is_ok_and(|release| release.to_lowercase().contains("microsoft"))
+    })
+}
+
+/// The OpenSSH program (`ssh`, `scp`, ...) to run
+///
+/// Normally the one on PATH. On Windows the built-in OpenSSH client in
+/// `System32\OpenSSH` is used when PATH doesn't have one, and inside WSL
+/// without a Linux client the Windows `ssh.exe` is used through interop.
+pub fn openssh_program(name: &str) -> PathBuf {
+    let path = env::var_os("PATH").unwrap_or_default();
+    let system_root = if cfg!(windows) { env::var_os("SystemRoot") } else { None };
+    find_openssh(name, &path, system_root, is_wsl())
+}
+
+fn find_openssh(name: &str, path: &OsString, system_root: Option<OsString>, wsl: bool) -> PathBuf {
+    let on_path = |file: &str| env::split_paths(path).map(|dir| dir.join(file)).find(|candidate| candidate.is_file());
+
+    let file = format!("{}{}", name, env::consts::EXE_SUFFIX);
+    if on_path(&file).is_some() {
+        return PathBuf::from(name);
+    }
+    if let Some(root) = system_root {
+        let builtin = Path::new(&root).join("System32").join("OpenSSH").join(&file);
+        if builtin.is_file() {
+            return builtin;
+        }
+    }
+    if wsl {
+        if let Some(windows) = on_path(&format!("{}.exe", name)) {
+            return windows;
+        }
+    }
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~/.ssh/known_hosts"), home.join(".ssh/known_hosts"));
+        assert_eq!(expand_home("~\\.ssh"), home.join(".ssh"));
+        assert_eq!(expand_home("~alice/.ssh"), PathBuf::from("~alice/.ssh"));
+        assert_eq!(expand_home("/etc/ssh"), PathBuf::from("/etc/ssh"));
+    }
+
+    #[test]
+    fn test_find_openssh() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        let root = dir.path().join("Windows");
+        fs::create_dir_all(&bin).unwrap();
+        fs::create_dir_all(root.join("System32").join("OpenSSH")).unwrap();
+        let path = env::join_paths([&bin]).unwrap();
+
+        assert_eq!(find_openssh("ssh", &path, None, false), PathBuf::from("ssh"));
+
+        let builtin = root.join("System32").join("OpenSSH").join(format!("ssh{}", env::consts::EXE_SUFFIX));
+        fs::write(&builtin, "").unwrap();
+        assert_eq!(find_openssh("ssh", &path, Some(root.clone().into()), false), builtin);
+
+        fs::write(bin.join("ssh.exe"), "").unwrap();
+        if env::consts::EXE_SUFFIX.is_empty() {
+            assert_eq!(find_openssh("ssh", &path, None, true), bin.join("ssh.exe"));
+            assert_eq!(find_openssh("ssh", &path, None, false), PathBuf::from("ssh"));
+        }
+
+        fs::write(bin.join(format!("ssh{}", env::consts::EXE_SUFFIX)), "").unwrap();
+        assert_eq!(find_openssh("ssh", &path, Some(root.into()), true), PathBuf::from("ssh"));
+    }
+}
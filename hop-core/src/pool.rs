@@ -0,0 +1,116 @@
+use crate::background;
+use crate::config::Settings;
+use crate::history::Usage;
+use crate::models::Server;
+use crate::utils::{ensure_dir_exists, server_file_name, write_private};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+fn pool_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("pool"))
+}
+
+/// Get the path of the ControlMaster socket for a server, by qualified name
+pub fn socket_path(server: &str) -> Result<PathBuf> {
+    Ok(pool_dir()?.join(format!("{}.sock", server_file_name(server))))
+}
+
+/// The master socket for a server, if the pool has opened one
+pub fn existing_socket(server: &str) -> Option<PathBuf> {
+    socket_path(server).ok().filter(|path| path.exists())
+}
+
+/// Qualified names of servers with a socket in the pool directory
+pub fn socket_servers() -> Result<Vec<String>> {
+    let dir = pool_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "sock") {
+            names.extend(path.file_stem().and_then(|stem| stem.to_str()).map(|stem| stem.replace('+', "/")));
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Get the path to the file holding the pool daemon's process id
+pub fn get_pid_file_path() -> Result<PathBuf> {
+    Ok(pool_dir()?.join("pool.pid"))
+}
+
+/// Get the path to the pool daemon's output
+pub fn get_log_file_path() -> Result<PathBuf> {
+    Ok(pool_dir()?.join("pool.log"))
+}
+
+/// Process id of the running pool daemon, if any
+pub fn running_pid() -> Result<Option<u32>> {
+    let path = get_pid_file_path()?;
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    Ok(contents.trim().parse().ok().filter(|&pid| background::is_alive(pid)))
+}
+
+/// Start `hop` again with `args` as the background pool daemon
+pub fn spawn_background(args: &[String]) -> Result<u32> {
+    let pid = background::spawn(args, &get_log_file_path()?).context("Failed to start the pool")?;
+    ensure_dir_exists(&pool_dir()?)?;
+    write_private(&get_pid_file_path()?, &pid.to_string())?;
+    Ok(pid)
+}
+
+/// Stop the pool daemon; returns whether one was running
+///
+/// Master connections are closed separately, since ssh runs them in their own session.
+pub fn stop() -> Result<bool> {
+    let Some(pid) = running_pid()? else {
+        return Ok(false);
+    };
+    background::terminate(pid).context("Failed to stop the pool")?;
+    let _ = fs::remove_file(get_pid_file_path()?);
+    Ok(true)
+}
+
+/// Idle timeout for a server's master connection, in minutes
+pub fn idle_minutes(server: &Server, settings: &Settings) -> u64 {
+    server.pool_idle.unwrap_or(settings.pool_idle)
+}
+
+/// The `size` most-used servers, most used first
+pub fn most_used(usage: &HashMap<String, Usage>, size: usize) -> Vec<String> {
+    let mut ranked: Vec<(&String, &Usage)> = usage.iter().collect();
+    ranked.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(b.1.last_used.cmp(&a.1.last_used)).then(a.0.cmp(b.0)));
+    ranked.into_iter().take(size).map(|(name, _)| name.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_used() {
+        let usage = HashMap::from([
+            ("web".to_string(), Usage { last_used: 100, count: 5 }),
+            ("db".to_string(), Usage { last_used: 300, count: 5 }),
+            ("old".to_string(), Usage { last_used: 50, count: 1 }),
+        ]);
+        assert_eq!(most_used(&usage, 2), vec!["db", "web"]);
+        assert_eq!(most_used(&usage, 10).len(), 3);
+
+        let mut server = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        let settings = Settings::default();
+        assert_eq!(idle_minutes(&server, &settings), 30);
+        server.pool_idle = Some(5);
+        assert_eq!(idle_minutes(&server, &settings), 5);
+    }
+}
@@ -0,0 +1,221 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often `sleep` checks for a signal
+const SLEEP_STEP: Duration = Duration::from_millis(100);
+
+/// Most child processes tracked at once; more are still run, just not tracked
+#[cfg(unix)]
+const MAX_CHILDREN: usize = 64;
+
+/// Exit status for a process stopped by `signal`, as shells report it
+pub fn exit_code(signal: i32) -> i32 {
+    128 + signal
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::MAX_CHILDREN;
+    use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+    use std::sync::OnceLock;
+
+    /// The last SIGINT, SIGTERM or SIGHUP received, or 0
+    pub static RECEIVED: AtomicI32 = AtomicI32::new(0);
+    /// Child pids that signals are forwarded to; 0 marks a free slot
+    pub static CHILDREN: [AtomicI32; MAX_CHILDREN] = [const { AtomicI32::new(0) }; MAX_CHILDREN];
+    /// Live `Deferred` guards
+    pub static DEFERRED: AtomicUsize = AtomicUsize::new(0);
+    /// Terminal settings of stdin when hop started
+    static TERMINAL: OnceLock<libc::termios> = OnceLock::new();
+
+    pub const SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+    pub fn install() {
+        // SAFETY: termios is plain data, and tcgetattr only writes to it
+        let mut terminal: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut terminal) } == 0 {
+            let _ = TERMINAL.set(terminal);
+        }
+        for signal in SIGNALS {
+            // SAFETY: the handler only uses async-signal-safe calls and atomics
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = handle as *const () as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                libc::sigaction(signal, &action, std::ptr::null_mut());
+            }
+        }
+    }
+
+    pub fn restore_terminal() {
+        if let Some(terminal) = TERMINAL.get() {
+            // SAFETY: tcsetattr is async-signal-safe and `terminal` came from tcgetattr
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, terminal) };
+        }
+    }
+
+    /// Forward the signal to tracked children and let hop clean up after them;
+    /// with nothing to wait for, restore the terminal and die of the signal
+    extern "C" fn handle(signal: libc::c_int) {
+        RECEIVED.store(signal, Ordering::SeqCst);
+        let mut forwarded = false;
+        for slot in &CHILDREN {
+            let pid = slot.load(Ordering::SeqCst);
+            if pid > 0 {
+                // SAFETY: kill is async-signal-safe
+                unsafe { libc::kill(pid, signal) };
+                forwarded = true;
+            }
+        }
+        if !forwarded && DEFERRED.load(Ordering::SeqCst) == 0 {
+            restore_terminal();
+            // SAFETY: signal and raise are async-signal-safe; the raised
+            // signal is delivered once this handler returns
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+        }
+    }
+
+    pub fn track(pid: u32) -> Option<usize> {
+        let pid = i32::try_from(pid).ok()?;
+        CHILDREN.iter().position(|slot| slot.compare_exchange(0, pid, Ordering::SeqCst, Ordering::SeqCst).is_ok())
+    }
+
+    pub fn untrack(slot: usize) {
+        CHILDREN[slot].store(0, Ordering::SeqCst);
+    }
+}
+
+/// Handle SIGINT, SIGTERM and SIGHUP instead of dying on the spot
+///
+/// While a tracked child runs, the signal is forwarded to it and hop keeps
+/// going, so it can clean up once the child exits; check [`interrupted`] to
+/// stop afterwards. With no child and no [`Deferred`] guard, the terminal is
+/// restored and hop exits as it would have without a handler.
+#[cfg(unix)]
+pub fn install() {
+    imp::install();
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// The signal hop received, if any
+#[cfg(unix)]
+pub fn interrupted() -> Option<i32> {
+    Some(imp::RECEIVED.load(std::sync::atomic::Ordering::SeqCst)).filter(|&signal| signal != 0)
+}
+
+#[cfg(not(unix))]
+pub fn interrupted() -> Option<i32> {
+    None
+}
+
+/// Put the terminal back the way it was when hop started, e.g. with echo on
+#[cfg(unix)]
+pub fn restore_terminal() {
+    imp::restore_terminal();
+}
+
+#[cfg(not(unix))]
+pub fn restore_terminal() {}
+
+/// Forwards signals to a child process until dropped
+pub struct TrackedChild {
+    #[cfg(unix)]
+    slot: Option<usize>,
+}
+
+/// Forward signals to `child` until the returned guard is dropped
+pub fn track(child: &Child) -> TrackedChild {
+    #[cfg(unix)]
+    {
+        TrackedChild { slot: imp::track(child.id()) }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = child;
+        TrackedChild {}
+    }
+}
+
+impl Drop for TrackedChild {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(slot) = self.slot {
+            imp::untrack(slot);
+        }
+    }
+}
+
+/// Keeps hop alive on a signal, for code that polls [`interrupted`] and
+/// cleans up by itself, e.g. a loop that restarts ssh
+pub struct Deferred(());
+
+pub fn defer() -> Deferred {
+    #[cfg(unix)]
+    imp::DEFERRED.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    Deferred(())
+}
+
+impl Drop for Deferred {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        imp::DEFERRED.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Sleep for `duration`, waking up early when hop receives a signal
+///
+/// Returns whether the whole duration passed.
+pub fn sleep(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while interrupted().is_none() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return true;
+        }
+        thread::sleep(left.min(SLEEP_STEP));
+    }
+    false
+}
+
+/// Like `Command::status`, with signals forwarded to the command
+pub fn status(command: &mut Command) -> io::Result<ExitStatus> {
+    let mut child = command.spawn()?;
+    let _tracked = track(&child);
+    child.wait()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracked_children_free_their_slot() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let tracked = track(&child);
+        let slot = tracked.slot.unwrap();
+        assert_eq!(imp::CHILDREN[slot].load(std::sync::atomic::Ordering::SeqCst), child.id() as i32);
+        drop(tracked);
+        assert_eq!(imp::CHILDREN[slot].load(std::sync::atomic::Ordering::SeqCst), 0);
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_status() {
+        assert!(status(&mut Command::new("true")).unwrap().success());
+        assert!(!status(&mut Command::new("false")).unwrap().success());
+        assert!(sleep(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(exit_code(libc::SIGINT), 130);
+    }
+}
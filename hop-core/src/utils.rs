@@ -0,0 +1,703 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Suppress info and success messages (set by the global `--quiet` flag)
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Check whether info and success messages are suppressed
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Answer yes to every confirmation prompt (set by `--yes` / `HOP_ASSUME_YES`)
+pub fn set_assume_yes(assume_yes: bool) {
+    ASSUME_YES.store(assume_yes, Ordering::Relaxed);
+}
+
+/// Check whether confirmation prompts are answered automatically
+pub fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+}
+
+/// Check whether stdin is an interactive terminal
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Print an error message in red
+pub fn print_error(message: &str) {
+    eprintln!("{}: {}", "Error".red().bold(), message);
+}
+
+/// Print a suggestion for fixing the preceding error
+pub fn print_hint(message: &str) {
+    eprintln!("{}: {}", "Hint".cyan().bold(), message);
+}
+
+/// Prefix colours for output from several servers, assigned in order
+const PREFIX_COLORS: [Color; 6] = [Color::Cyan, Color::Green, Color::Yellow, Color::Magenta, Color::Blue, Color::Red];
+
+/// The `[server]` prefix for lines from the `index`-th of several servers,
+/// padded so that names up to `width` characters line up
+pub fn server_prefix(name: &str, index: usize, width: usize) -> ColoredString {
+    format!("{:<width$}", format!("[{}]", name), width = width + 2)
+        .color(PREFIX_COLORS[index % PREFIX_COLORS.len()])
+        .bold()
+}
+
+/// Print a success message in green
+pub fn print_success(message: &str) {
+    if is_quiet() {
+        return;
+    }
+    println!("{}: {}", "Success".green().bold(), message);
+}
+
+/// Print an info message in blue
+pub fn print_info(message: &str) {
+    if is_quiet() {
+        return;
+    }
+    println!("{}: {}", "Info".blue().bold(), message);
+}
+
+/// Print a warning message in yellow
+pub fn print_warning(message: &str) {
+    println!("{}: {}", "Warning".yellow().bold(), message);
+}
+
+/// Put `text` on the system clipboard, returning whether a clipboard tool accepted it
+pub fn copy_to_clipboard(text: &str) -> bool {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+    };
+    
+    tools.iter().any(|(program, args)| {
+        let child = std::process::Command::new(program)
+            .args(*args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            return false;
+        };
+        let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
+
+/// Identify the local user as `user@host`
+pub fn local_actor() -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty());
+    match host {
+        Some(host) => format!("{}@{}", user, host),
+        None => user,
+    }
+}
+
+/// Current time as a Unix timestamp in seconds
+pub fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format the time elapsed since a Unix timestamp, e.g. "3h ago"
+pub fn format_time_ago(timestamp: u64) -> String {
+    let elapsed = now_unix().saturating_sub(timestamp);
+    match elapsed {
+        0..=59 => "just now".to_string(),
+        60..=3_599 => format!("{}m ago", elapsed / 60),
+        3_600..=86_399 => format!("{}h ago", elapsed / 3_600),
+        86_400..=2_591_999 => format!("{}d ago", elapsed / 86_400),
+        2_592_000..=31_535_999 => format!("{}mo ago", elapsed / 2_592_000),
+        _ => format!("{}y ago", elapsed / 31_536_000),
+    }
+}
+
+/// Format a duration in seconds compactly, e.g. "45s", "3m 12s", "1h 05m"
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3_599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3_600, seconds % 3_600 / 60),
+    }
+}
+
+/// Format a Unix timestamp as a UTC calendar date (YYYY-MM-DD)
+pub fn format_utc_date(timestamp: u64) -> String {
+    // Civil-from-days conversion (Howard Hinnant's algorithm)
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Format a Unix timestamp as a UTC date and time (YYYY-MM-DD HH:MM:SS)
+pub fn format_utc_time(timestamp: u64) -> String {
+    let seconds = timestamp % 86_400;
+    format!("{} {:02}:{:02}:{:02}", format_utc_date(timestamp), seconds / 3_600, seconds % 3_600 / 60, seconds % 60)
+}
+
+/// Calendar fields of a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalendarTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    /// 0 = Sunday
+    pub weekday: u32,
+}
+
+impl CalendarTime {
+    /// The date as YYYY-MM-DD
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Calendar fields of a Unix timestamp in the local time zone
+#[cfg(unix)]
+pub fn local_time(timestamp: u64) -> CalendarTime {
+    let time = timestamp as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm we pass; tm is plain data
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return utc_time(timestamp);
+    }
+    CalendarTime {
+        year: tm.tm_year + 1900,
+        month: tm.tm_mon as u32 + 1,
+        day: tm.tm_mday as u32,
+        hour: tm.tm_hour as u32,
+        minute: tm.tm_min as u32,
+        weekday: tm.tm_wday as u32,
+    }
+}
+
+/// Calendar fields of a Unix timestamp (UTC on this platform)
+#[cfg(not(unix))]
+pub fn local_time(timestamp: u64) -> CalendarTime {
+    utc_time(timestamp)
+}
+
+/// Calendar fields of a Unix timestamp in UTC
+pub fn utc_time(timestamp: u64) -> CalendarTime {
+    let days = timestamp / 86_400;
+    let seconds = timestamp % 86_400;
+    let date = format_utc_date(timestamp);
+    let mut parts = date.split('-').map(|part| part.parse::<u32>().unwrap_or(1));
+    CalendarTime {
+        year: parts.next().unwrap_or(1970) as i32,
+        month: parts.next().unwrap_or(1),
+        day: parts.next().unwrap_or(1),
+        hour: (seconds / 3_600) as u32,
+        minute: (seconds % 3_600 / 60) as u32,
+        // 1970-01-01 was a Thursday
+        weekday: ((days + 4) % 7) as u32,
+    }
+}
+
+/// Quote a string for a POSIX shell, e.g. a remote command run by ssh
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Ensure a directory exists, creating it if necessary
+pub fn ensure_dir_exists(path: &Path) -> Result<()> {
+    if !path.exists() {
+        fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Match `text` against a glob with `*` (any run) and `?` (one character)
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Write a file that only the current user can read
+///
+/// New files are created with mode 0600, and an existing file's mode is
+/// tightened to 0600 as well.
+#[cfg(unix)]
+pub fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    fs::write(path, contents)
+}
+
+/// Open a file for appending, creating it readable by the current user only
+pub fn append_private(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+/// Describe why a file is not private to the current user, if it is not
+///
+/// A file is private when it is owned by the current user and has no group or
+/// other permission bits. Always `None` on platforms without Unix modes.
+#[cfg(unix)]
+pub fn permission_problem(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path).ok()?;
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    if metadata.uid() != uid {
+        return Some(format!("{} is owned by uid {}, not by you (uid {})", path.display(), metadata.uid(), uid));
+    }
+    let mode = metadata.mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Some(format!("{} has mode {:o}; it should be 600 (chmod 600 {})", path.display(), mode, path.display()));
+    }
+    None
+}
+
+#[cfg(not(unix))]
+pub fn permission_problem(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Prompt the user for a line of text
+///
+/// Returns `None` when the answer is empty or stdin is not a terminal.
+pub fn prompt_input(message: &str) -> Result<Option<String>> {
+    if !is_interactive() {
+        return Ok(None);
+    }
+    
+    print!("{}: ", message);
+    io::stdout().flush().context("Failed to flush stdout")?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).context("Failed to read input")?;
+    let input = input.trim();
+    
+    Ok(if input.is_empty() { None } else { Some(input.to_string()) })
+}
+
+/// Prompt for a secret without echoing it
+///
+/// Fails instead of blocking when stdin is not a terminal.
+pub fn prompt_secret(message: &str) -> Result<String> {
+    if !is_interactive() {
+        return Err(anyhow::anyhow!("{}: stdin is not a terminal", message));
+    }
+    
+    rpassword::prompt_password(format!("{}: ", message)).context("Failed to read input")
+}
+
+/// Ask the user to type `expected` back before a risky action
+///
+/// Returns `true` without prompting when `--yes` is in effect.
+pub fn confirm_by_typing(message: &str, expected: &str) -> Result<bool> {
+    if assume_yes() {
+        return Ok(true);
+    }
+    
+    if !is_interactive() {
+        return Err(anyhow::anyhow!(
+            "{} Confirmation required but stdin is not a terminal; pass --yes to proceed.",
+            message
+        ));
+    }
+    
+    println!("{}", message);
+    let answer = prompt_input(&format!("Type '{}' to continue", expected))?;
+    Ok(answer.as_deref() == Some(expected))
+}
+
+/// Show a numbered list and let the user pick an entry
+///
+/// Returns `None` when the user enters nothing. Fails when stdin is not a
+/// terminal, since there is nobody to answer.
+pub fn choose_from(message: &str, items: &[String]) -> Result<Option<usize>> {
+    if !is_interactive() {
+        return Err(anyhow::anyhow!(
+            "{}\n{}",
+            message,
+            items.iter().map(|item| format!("  {}", item)).collect::<Vec<_>>().join("\n")
+        ));
+    }
+    
+    println!("{}", message);
+    for (index, item) in items.iter().enumerate() {
+        println!("  {}. {}", index + 1, item);
+    }
+    
+    loop {
+        print!("Select [1-{}] (empty to cancel): ", items.len());
+        io::stdout().flush().context("Failed to flush stdout")?;
+        
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).context("Failed to read selection")?;
+        let input = input.trim();
+        
+        if input.is_empty() {
+            return Ok(None);
+        }
+        
+        match input.parse::<usize>() {
+            Ok(choice) if (1..=items.len()).contains(&choice) => return Ok(Some(choice - 1)),
+            _ => print_warning(&format!("'{}' is not a valid choice.", input)),
+        }
+    }
+}
+
+/// Open a file in the user's editor ($VISUAL, then $EDITOR) and wait for it to exit
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() });
+    
+    // Allow editors configured with arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next()
+        .ok_or_else(|| anyhow::anyhow!("$EDITOR is empty"))?;
+    
+    let status = crate::signals::status(std::process::Command::new(program).args(parts).arg(path))
+        .with_context(|| format!("Failed to launch editor '{}'", editor))?;
+    
+    if !status.success() {
+        return Err(anyhow::anyhow!("Editor '{}' exited with {}", editor, status));
+    }
+    
+    Ok(())
+}
+
+/// Parse a boolean setting value such as true/false, yes/no or on/off
+pub fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(anyhow::anyhow!("Expected a boolean (true/false), got '{}'", value)),
+    }
+}
+
+/// Parse a whole number of minutes, e.g. an idle timeout
+pub fn parse_minutes(value: &str) -> Result<u64> {
+    parse_count(value, "minutes")
+}
+
+/// Parse a positive whole number of `unit`s (days, months, ...)
+pub fn parse_count(value: &str, unit: &str) -> Result<u64> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| anyhow::anyhow!("Expected a number of {}, got '{}'", unit, value))
+}
+
+/// Parse a duration such as `30s`, `5m` or `1h`; a bare number means seconds
+pub fn parse_duration(value: &str) -> Result<std::time::Duration> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len()));
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        _ => 0,
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .filter(|_| seconds > 0)
+        .map(|number| std::time::Duration::from_secs(number * seconds))
+        .ok_or_else(|| anyhow::anyhow!("Invalid duration '{}'. Expected e.g. 30s, 5m or 1h", value))
+}
+
+/// Parse a comma-separated list, dropping empty entries
+pub fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Validate a host: an IPv4 address, an IPv6 address (optionally in
+/// brackets or with a `%zone`), or an RFC 1123 hostname
+pub fn is_valid_host(host: &str) -> bool {
+    normalize_host(host).is_some()
+}
+
+/// The host as it is stored: trimmed, with the brackets around IPv6 removed
+pub fn normalize_host(host: &str) -> Option<String> {
+    let host = host.trim();
+    if let Some(inner) = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+        return is_ipv6(inner).then(|| inner.to_string());
+    }
+    (host.parse::<Ipv4Addr>().is_ok() || is_ipv6(host) || is_hostname(host)).then(|| host.to_string())
+}
+
+fn is_ipv6(host: &str) -> bool {
+    let (address, zone) = match host.split_once('%') {
+        Some((address, zone)) => (address, Some(zone)),
+        None => (host, None),
+    };
+    address.parse::<Ipv6Addr>().is_ok()
+        && zone.is_none_or(|zone| !zone.is_empty() && zone.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)))
+}
+
+/// RFC 1123 hostname; a numeric last label is rejected so `256.1.1.1` isn't a name
+fn is_hostname(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() || host.len() > 253 {
+        return false;
+    }
+    let valid_labels = host.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    valid_labels && !host.rsplit('.').next().unwrap_or_default().chars().all(|c| c.is_ascii_digit())
+}
+
+/// The host as written in URIs and scp remote specs, where IPv6 needs brackets
+pub fn host_for_uri(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// Look up the addresses a host resolves to
+pub fn resolve_host(host: &str) -> Result<Vec<IpAddr>> {
+    let addresses: Vec<IpAddr> = (host, 0)
+        .to_socket_addrs()
+        .with_context(|| format!("Cannot resolve {}", host))?
+        .map(|address| address.ip())
+        .collect();
+    if addresses.is_empty() {
+        return Err(anyhow::anyhow!("Cannot resolve {}", host));
+    }
+    Ok(addresses)
+}
+
+/// A `group/name` server key made safe to use as a file name, e.g. "staging+web"
+///
+/// Neither names nor groups may contain '+', so this can't collide.
+pub fn server_file_name(name: &str) -> String {
+    name.replace('/', "+")
+}
+
+/// Validate server name (alphanumeric, hyphens, underscores)
+pub fn is_valid_server_name(name: &str) -> bool {
+    !name.is_empty() && 
+    name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Prompt user for confirmation
+///
+/// Returns `true` without prompting when `--yes` is in effect, and fails
+/// instead of blocking when stdin is not a terminal.
+pub fn confirm_action(message: &str) -> Result<bool> {
+    if assume_yes() {
+        return Ok(true);
+    }
+    
+    if !is_interactive() {
+        return Err(anyhow::anyhow!(
+            "{} Confirmation required but stdin is not a terminal; pass --yes (or set HOP_ASSUME_YES=1) to proceed.",
+            message
+        ));
+    }
+    
+    print!("{} [y/N]: ", message);
+    io::stdout().flush().context("Failed to flush stdout")?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).context("Failed to read confirmation")?;
+    
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        use std::time::Duration;
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn test_server_prefix() {
+        assert_eq!(&*server_prefix("db", 0, 5), "[db]   ");
+        assert_eq!(&*server_prefix("web-1", 7, 5), "[web-1]");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("servers.json");
+        fs::write(&path, "[]").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(permission_problem(&path).unwrap().contains("mode 644"));
+
+        write_private(&path, "{}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        assert_eq!(permission_problem(&path), None);
+    }
+
+    #[test]
+    fn test_is_valid_host() {
+        assert!(is_valid_host("192.168.1.1"));
+        assert!(is_valid_host("255.255.255.255"));
+        assert!(!is_valid_host("256.1.1.1"));
+        assert!(!is_valid_host("192.168.1"));
+        assert!(!is_valid_host("192.168.1.1.1"));
+
+        assert!(is_valid_host("db-1.internal.example.com"));
+        assert!(is_valid_host("localhost."));
+        assert!(!is_valid_host("-db.example.com"));
+        assert!(!is_valid_host("db_1.example.com"));
+        assert!(!is_valid_host("two words"));
+        assert!(!is_valid_host(&format!("{}.com", "a".repeat(64))));
+
+        assert!(is_valid_host("2001:db8::1"));
+        assert!(is_valid_host("fe80::1%eth0"));
+        assert!(!is_valid_host("fe80::1%"));
+        assert!(!is_valid_host("[10.0.0.1]"));
+        assert_eq!(normalize_host(" [::1] ").as_deref(), Some("::1"));
+    }
+
+    #[test]
+    fn test_host_for_uri() {
+        assert_eq!(host_for_uri("2001:db8::1"), "[2001:db8::1]");
+        assert_eq!(host_for_uri("db.example.com"), "db.example.com");
+    }
+
+    #[test]
+    fn test_format_time_ago() {
+        let now = now_unix();
+        assert_eq!(format_time_ago(now), "just now");
+        assert_eq!(format_time_ago(now - 120), "2m ago");
+        assert_eq!(format_time_ago(now - 3 * 3_600), "3h ago");
+        assert_eq!(format_time_ago(now - 2 * 86_400), "2d ago");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(192), "3m 12s");
+        assert_eq!(format_duration(3_900), "1h 05m");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/var/www"), "'/var/www'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_format_utc_date() {
+        assert_eq!(format_utc_date(0), "1970-01-01");
+        assert_eq!(format_utc_date(951_782_400), "2000-02-29");
+        assert_eq!(format_utc_date(1_792_108_800), "2026-10-16");
+        assert_eq!(format_utc_time(1_792_108_800 + 3_723), "2026-10-16 01:02:03");
+    }
+
+    #[test]
+    fn test_utc_time() {
+        // 2024-03-15 12:30 UTC was a Friday
+        let time = utc_time(1_710_505_800);
+        assert_eq!(time, CalendarTime { year: 2024, month: 3, day: 15, hour: 12, minute: 30, weekday: 5 });
+        assert_eq!(time.date(), "2024-03-15");
+    }
+
+    #[test]
+    fn test_confirm_action_assume_yes() {
+        set_assume_yes(true);
+        assert!(confirm_action("Remove server 'web'?").unwrap());
+        set_assume_yes(false);
+    }
+
+    #[test]
+    fn test_is_valid_server_name() {
+        assert!(is_valid_server_name("server1"));
+        assert!(is_valid_server_name("test-server"));
+        assert!(is_valid_server_name("test_server"));
+        assert!(is_valid_server_name("Server123"));
+        assert!(!is_valid_server_name(""));
+        assert!(!is_valid_server_name("server with spaces"));
+        assert!(!is_valid_server_name("server@special"));
+    }
+} 
\ No newline at end of file
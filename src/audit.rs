@@ -0,0 +1,118 @@
+use crate::redact::redact;
+use crate::utils::{append_private, ensure_dir_exists, local_actor, now_unix};
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// One remote command run through hop
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix timestamp (seconds) when the command finished
+    pub timestamp: u64,
+    pub server: String,
+    pub command: String,
+    pub exit_code: i32,
+    pub duration_ms: u64,
+    /// Local `user@host` that ran the command
+    pub user: String,
+    /// Justification given with `--override` to run a command the policy denies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_reason: Option<String>,
+    /// Extra fields added by the `after_exec` hook, such as a ticket number
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl AuditEntry {
+    pub fn new(server: &str, command: &str, exit_code: i32, duration: Duration) -> Self {
+        AuditEntry {
+            timestamp: now_unix(),
+            server: server.to_string(),
+            command: redact(command),
+            exit_code,
+            duration_ms: duration.as_millis() as u64,
+            user: local_actor(),
+            override_reason: None,
+            annotations: BTreeMap::new(),
+        }
+    }
+
+    /// Single-line form used for syslog
+    fn summary(&self) -> String {
+        let mut summary = format!(
+            "user={} server={} exit={} duration_ms={} command={:?}",
+            self.user, self.server, self.exit_code, self.duration_ms, self.command
+        );
+        if let Some(reason) = &self.override_reason {
+            summary.push_str(&format!(" override={:?}", reason));
+        }
+        for (key, value) in &self.annotations {
+            summary.push_str(&format!(" {}={:?}", key, value));
+        }
+        summary
+    }
+}
+
+/// Get the path to the audit log (one JSON entry per line)
+pub fn get_audit_file_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("audit.jsonl"))
+}
+
+/// Append an entry to the audit log, and to syslog when `syslog` is set
+///
+/// The file is only ever opened for appending; hop never rewrites it.
+pub fn record(entry: &AuditEntry, syslog: bool) -> Result<()> {
+    let path = get_audit_file_path()?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+
+    let mut file = append_private(&path)
+        .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write audit log: {}", path.display()))?;
+
+    if syslog {
+        send_to_syslog(&entry.summary())?;
+    }
+    Ok(())
+}
+
+/// Forward a message to the system log through logger(1)
+fn send_to_syslog(message: &str) -> Result<()> {
+    debug!("argv: \"logger\" [\"-t\", \"hop\", \"-p\", \"authpriv.info\", ...]");
+    let status = Command::new("logger")
+        .args(["-t", "hop", "-p", "authpriv.info", "--", message])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run logger; is syslog available?")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("logger exited with {}", status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_summary() {
+        let mut entry = AuditEntry::new("web", "systemctl restart \"nginx\"", 3, Duration::from_millis(1500));
+        entry.user = "alice@laptop".to_string();
+        assert_eq!(entry.duration_ms, 1500);
+        assert_eq!(
+            entry.summary(),
+            r#"user=alice@laptop server=web exit=3 duration_ms=1500 command="systemctl restart \"nginx\"""#
+        );
+    }
+}
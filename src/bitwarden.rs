@@ -0,0 +1,46 @@
+use anyhow::Result;
+use log::debug;
+use std::io::ErrorKind;
+use std::process::Command;
+
+/// Fetch the password of a Bitwarden item by name
+///
+/// Tries `rbw` first, since it keeps its own unlocked agent, then the official
+/// `bw` CLI, which needs BW_SESSION from `bw unlock`.
+pub fn password(item: &str) -> Result<String> {
+    let attempts: [(&str, &[&str]); 2] = [
+        ("rbw", &["get", item]),
+        ("bw", &["get", "password", item]),
+    ];
+    
+    let mut failures = Vec::new();
+    for (program, args) in attempts {
+        debug!("argv: {:?} {:?}", program, args);
+        let output = match Command::new(program).args(args).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(anyhow::anyhow!("Failed to run {}: {}", program, e)),
+        };
+        
+        if output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // rbw prints the password on the first line, followed by any notes
+            let password = stdout.lines().next().unwrap_or_default().to_string();
+            if !password.is_empty() {
+                return Ok(password);
+            }
+            failures.push(format!("{}: item has no password", program));
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            failures.push(format!("{}: {}", program, stderr.trim()));
+        }
+    }
+    
+    if failures.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Bitwarden item '{}' is configured but neither `rbw` nor `bw` is installed",
+            item
+        ));
+    }
+    Err(anyhow::anyhow!("Could not read Bitwarden item '{}': {}", item, failures.join("; ")))
+}
@@ -1,4 +1,13 @@
-use clap::{Parser, Subcommand};
+use crate::exit_codes::USAGE_ERROR;
+use crate::keys::KeyType;
+use crate::service::ServiceAction;
+use crate::shell_init::Shell;
+use crate::rollout::Rollout;
+use crate::utils::parse_duration;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "hop")]
@@ -6,8 +15,32 @@ use clap::{Parser, Subcommand};
 #[command(version = "0.1.0")]
 #[command(author = "Alp Emre Elmas <elmasalpemre@gmail.com>")]
 pub struct Cli {
+    /// Suppress informational output
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Increase verbosity (-v for debug details, -vv for trace)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Also append log output to hop.log in the config directory
+    #[arg(long, global = true)]
+    pub log_file: bool,
+
+    /// Answer yes to all confirmation prompts (for scripts, CI and cron)
+    #[arg(short, long, global = true, env = "HOP_ASSUME_YES", value_parser = clap::builder::FalseyValueParser::new())]
+    pub yes: bool,
+
+    /// Parse servers.json instead of reusing the cached copy
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Server name or list number to connect to (shortcut for `hop connect`)
+    #[arg(value_name = "SERVER")]
+    pub target: Option<String>,
+
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
@@ -22,42 +55,252 @@ pub enum Commands {
         #[arg(short, long)]
         user: String,
 
-        /// IP address or hostname of the server
+        /// IP address (IPv4 or IPv6) or hostname of the server
         #[arg(short, long)]
         ip: String,
+
+        #[command(flatten)]
+        options: ServerOptions,
+
+        /// Fetch the host keys with ssh-keyscan and confirm their fingerprints
+        #[arg(long)]
+        scan: bool,
+
+        /// Also add the confirmed host keys to ~/.ssh/known_hosts
+        #[arg(long, requires = "scan")]
+        known_hosts: bool,
+
+        /// Check that the hostname resolves before saving
+        #[arg(long)]
+        resolve: bool,
     },
 
-    /// List all configured servers
+    /// List all configured servers (use -v for detailed information)
     List {
-        /// Show detailed information
+        /// Sort order (defaults to configuration order)
+        #[arg(long, value_enum)]
+        sort: Option<ListSort>,
+
+        /// Show the cached reachability from the last `hop health` run
+        #[arg(long)]
+        status: bool,
+
+        /// Render servers under headings instead of one flat list
+        #[arg(long, value_enum)]
+        group_by: Option<GroupBy>,
+
+        /// Only show servers unreachable for stale-days or unused for stale-months
+        #[arg(long)]
+        stale: bool,
+
+        /// Only show servers carrying this tag (repeatable; all must match)
+        #[arg(long)]
+        tag: Vec<String>,
+
+        /// Show every detail of each server (user, port, tags, description)
+        #[arg(short, long)]
+        long: bool,
+
+        /// Move stale servers to the archive file, which `hop import --merge` can restore
+        #[arg(long, conflicts_with_all = ["sort", "status", "group_by", "stale", "tag"])]
+        archive_stale: bool,
+
+        /// Archive without asking for confirmation
+        #[arg(short, long, requires = "archive_stale")]
+        force: bool,
+    },
+
+    /// Check which servers are reachable and cache the results
+    Health {
+        /// Servers to check (defaults to all)
+        servers: Vec<String>,
+
+        /// Only check servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+
+        #[command(flatten)]
+        rollout: RolloutArgs,
+    },
+
+    /// Probe servers concurrently and print a table of reachable and unreachable hosts
+    Test {
+        /// Servers to test
+        #[arg(required_unless_present_any = ["all", "tag"])]
+        servers: Vec<String>,
+
+        /// Test every configured server
+        #[arg(long, conflicts_with = "servers")]
+        all: bool,
+
+        /// Only test servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with_all = ["servers", "all"])]
+        tag: Vec<String>,
+
+        /// How many servers to probe at once
+        /// (default: the `parallel` setting, 8 unless changed)
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+
+        /// Only check that the SSH port accepts connections; no login, so nothing reaches auth logs
+        #[arg(long)]
+        tcp_only: bool,
+    },
+
+    /// Time spent in interactive sessions per server and day
+    Stats {
+        /// Number of days to cover, including today
+        #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u64).range(1..))]
+        days: u64,
+
+        /// Only show this server
+        #[arg(long)]
+        server: Option<String>,
+    },
+
+    /// Print a file from several servers at once, each line prefixed with its server
+    Tail {
+        /// File to read, e.g. /var/log/app/current
+        path: String,
+
+        /// Servers to read from (defaults to all)
+        servers: Vec<String>,
+
+        /// Only read from servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+
+        /// Number of lines to show from the end of the file
+        #[arg(short = 'n', long, default_value_t = 10)]
+        lines: u32,
+
+        /// Keep streaming new lines, reconnecting dropped connections
         #[arg(short, long)]
-        verbose: bool,
+        follow: bool,
+    },
+
+    /// Show a refreshing table of CPU load, memory and disk usage
+    Top {
+        /// Servers to show (defaults to all)
+        servers: Vec<String>,
+
+        /// Only show servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+
+        /// Seconds between refreshes
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
+        /// Print the table once and exit
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Compare a file across servers and report the ones that differ from the majority
+    Drift {
+        /// Servers to compare (defaults to all)
+        servers: Vec<String>,
+
+        /// Only compare servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+
+        /// File to compare, e.g. /etc/nginx/nginx.conf
+        #[arg(long)]
+        path: String,
+
+        /// Show a unified diff against this server for every server that differs from it
+        #[arg(long, value_name = "SERVER")]
+        reference: Option<String>,
+    },
+
+    /// Reports gathered from several servers at once
+    Report {
+        #[command(subcommand)]
+        action: ReportAction,
+    },
+
+    /// Watch servers in the background and notify when one goes down or recovers
+    Watchdog {
+        #[command(subcommand)]
+        action: WatchdogAction,
+    },
+
+    /// Keep master connections to your most-used servers open so connect and exec start instantly
+    Pool {
+        #[command(subcommand)]
+        action: PoolAction,
+    },
+
+    /// Forward local ports through a server, reconnecting when ssh drops
+    Tunnel {
+        #[command(subcommand)]
+        action: TunnelAction,
+    },
+
+    /// Commands run on a cron schedule by the background watchdog
+    Job {
+        #[command(subcommand)]
+        action: JobAction,
     },
 
     /// Connect to a server via SSH
     Connect {
-        /// Server name to connect to
+        /// Server name or list number to connect to
         identifier: String,
 
         /// Test connection without actually connecting
         #[arg(short, long)]
         test: bool,
+
+        /// Start the remote shell in this directory
+        #[arg(long, value_name = "DIR", conflicts_with = "test")]
+        cd: Option<String>,
+
+        /// Reconnect automatically when the connection drops
+        #[arg(long, conflicts_with = "test")]
+        reconnect: bool,
+    },
+
+    /// Show everything hop knows about a server
+    Show {
+        /// Server name or list number to show
+        identifier: String,
+
+        /// Render the entry as a QR code for scanning with a phone
+        #[arg(long)]
+        qr: bool,
+
+        /// What the QR code contains
+        #[arg(long, value_enum, default_value_t = QrPayload::Uri, requires = "qr")]
+        qr_format: QrPayload,
     },
 
     /// Remove a server from the configuration
     Remove {
         /// Server name to remove
-        identifier: String,
+        #[arg(required_unless_present = "tag")]
+        identifier: Option<String>,
+
+        /// Remove every server carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "identifier")]
+        tag: Vec<String>,
 
         /// Force removal without confirmation
         #[arg(short, long)]
         force: bool,
+
+        /// Also delete the hosts' entries from ~/.ssh/known_hosts without asking
+        #[arg(long)]
+        known_hosts: bool,
     },
 
     /// Edit a server configuration
     Edit {
         /// Server name to edit
-        identifier: String,
+        #[arg(required_unless_present = "tag")]
+        identifier: Option<String>,
 
         /// New name for the server
         #[arg(long)]
@@ -70,10 +313,52 @@ pub enum Commands {
         /// New IP address or hostname
         #[arg(long)]
         ip: Option<String>,
+
+        /// New SSH port
+        #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+        port: Option<u16>,
+
+        /// Notes shown before connecting (empty string clears them)
+        #[arg(long)]
+        notes: Option<String>,
+
+        #[command(flatten)]
+        tags: TagEdits,
+
+        /// Open the server's record as JSON in $EDITOR
+        #[arg(short, long, conflicts_with_all = ["name", "user", "ip", "port", "notes", "add_tag", "remove_tag"])]
+        editor: bool,
+
+        /// Edit every server carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "identifier", requires = "set")]
+        tag: Vec<String>,
+
+        /// Field change, e.g. --set user=deploy; with --tag it applies to every matching server
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_key_val, conflicts_with_all = ["name", "user", "ip", "port", "notes", "add_tag", "remove_tag", "editor"])]
+        set: Vec<(String, String)>,
+    },
+
+    /// Lock a server entry against edits and removal
+    Lock {
+        /// Server name to lock
+        identifier: String,
+
+        /// Remove the lock instead
+        #[arg(long)]
+        unlock: bool,
+    },
+
+    /// Organize servers into groups like "staging" or "clients/acme"
+    Group {
+        #[command(subcommand)]
+        action: GroupAction,
     },
 
     /// Show configuration file information
     Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+
         /// Show the path to the configuration file
         #[arg(short, long)]
         path: bool,
@@ -81,12 +366,612 @@ pub enum Commands {
         /// Initialize configuration file
         #[arg(short, long)]
         init: bool,
+    },
+
+    /// Execute a command on a server
+    Exec {
+        /// Server name or list number to run the command on; separate several with commas
+        server: String,
+
+        /// Command to execute
+        command: String,
+
+        /// Show a desktop notification with the exit status when the command finishes
+        #[arg(long)]
+        notify: bool,
+
+        /// Run the command through sudo; the password comes from the sudo-password secret or a prompt
+        #[arg(long)]
+        sudo: bool,
+
+        /// Run a command the exec policy denies; the justification goes to the audit log
+        #[arg(long = "override", value_name = "JUSTIFICATION")]
+        override_reason: Option<String>,
+
+        /// With several servers, stop at the first one where the command fails
+        #[arg(long)]
+        fail_fast: bool,
+
+        #[command(flatten)]
+        rollout: RolloutArgs,
+    },
+
+    /// Check, start, stop, restart or read the logs of a service (systemd, OpenRC or SysV)
+    Service {
+        /// Server name or list number; separate several with commas
+        servers: String,
+
+        /// What to do with the service
+        #[arg(value_enum)]
+        action: ServiceAction,
+
+        /// Service name, e.g. nginx
+        name: String,
+
+        /// Number of log lines to show with `logs`
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: u32,
+
+        /// Run through sudo; the password comes from the sudo-password secret or a prompt
+        #[arg(long)]
+        sudo: bool,
+    },
+
+    /// Copy a file to or from a server using SCP
+    Copy {
+        /// Server name or list number to copy to/from
+        server: String,
+
+        /// Source path
+        source: String,
+
+        /// Destination path
+        destination: String,
+
+        /// Copy from the server to the local machine
+        #[arg(short, long)]
+        from: bool,
+    },
+
+    /// Import servers from a JSON or JSON Lines file
+    Import {
+        /// Path to the JSON file
+        #[arg(required_unless_present = "onepassword")]
+        file: Option<String>,
+
+        /// Merge with existing servers instead of replacing them
+        #[arg(short, long)]
+        merge: bool,
+
+        /// Keep pre_connect/post_disconnect commands from the file, after confirming them
+        #[arg(long, conflicts_with = "onepassword")]
+        allow_hooks: bool,
+
+        /// Import Server items from 1Password via the `op` CLI (always merges)
+        #[arg(long = "1password", conflicts_with = "file")]
+        onepassword: bool,
+
+        /// 1Password vault to import from (defaults to all vaults)
+        #[arg(long, requires = "onepassword")]
+        vault: Option<String>,
+    },
+
+    /// Export servers to a JSON file
+    Export {
+        /// Path to the output file
+        file: String,
+
+        /// Pretty-print the JSON output
+        #[arg(short, long)]
+        pretty: bool,
+
+        /// Output format: JSON or JSON Lines for re-import, Markdown/HTML for an inventory report
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+    },
+
+    /// Manage encrypted per-server secrets (login password, sudo password, TOTP seed, ...)
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+
+    /// Manage SSH keys
+    Key {
+        #[command(subcommand)]
+        action: KeyAction,
+    },
+
+    /// Print the man page for hop or one of its subcommands
+    Man {
+        /// Subcommand to show, e.g. connect
+        command: Option<String>,
+    },
+
+    /// Generate documentation from the command line definition
+    Docs {
+        #[command(subcommand)]
+        action: DocsAction,
+    },
+
+    /// List the servers you connected to most recently
+    Recent {
+        /// How many servers to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+    },
+
+    /// Print server names starting with PREFIX, most recently used first
+    #[command(hide = true)]
+    Complete {
+        #[arg(default_value = "")]
+        prefix: String,
+    },
+
+    /// Print shell aliases for every server, completion, a hopcd helper and an Alt-h picker
+    #[command(name = "shell-init")]
+    ShellInit {
+        /// Shell to generate the script for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum QrPayload {
+    /// An ssh://user@host URI
+    Uri,
+    /// A JSON snippet that `hop import` accepts
+    Import,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    /// JSON Lines: one server per line, for very large inventories
+    Jsonl,
+    Markdown,
+    Html,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ListSort {
+    /// Alphabetically by name
+    Name,
+    /// Most recently connected first
+    LastUsed,
+    /// Most connections first
+    Uses,
+    /// Lowest latency from the last `hop health`/`hop test` first
+    Latency,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// One heading per tag; untagged servers are listed last
+    Tag,
+    /// Groups as a tree; ungrouped servers are listed last
+    Group,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Show the value of a setting (or all settings)
+    Get {
+        /// Setting name, e.g. case-insensitive
+        key: Option<String>,
+    },
+
+    /// Change a setting
+    Set {
+        /// Setting name, e.g. case-insensitive
+        key: String,
+
+        /// New value
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ReportAction {
+    /// Disk usage of every filesystem, highlighting the ones that are nearly full
+    Disk {
+        /// Servers to include (defaults to all)
+        servers: Vec<String>,
+
+        /// Only include servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+
+        /// Usage percentage at which a filesystem is flagged
+        #[arg(long, default_value_t = 85, value_parser = clap::value_parser!(u8).range(1..=100))]
+        threshold: u8,
+
+        /// Only list filesystems at or above the threshold
+        #[arg(long)]
+        over: bool,
+    },
+
+    /// TLS certificate expiry, checked with openssl on each server
+    Certs {
+        /// Servers to include (defaults to all)
+        servers: Vec<String>,
+
+        /// Only include servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+
+        /// Port serving TLS, checked on the server's localhost (repeatable) [default: 443]
+        #[arg(long)]
+        port: Vec<u16>,
+
+        /// PEM certificate file on the server (repeatable)
+        #[arg(long)]
+        path: Vec<String>,
+
+        /// Warn about certificates expiring within this many days
+        #[arg(long, default_value_t = 30)]
+        days: u64,
+    },
+
+    /// Pending package updates per server (apt, dnf, yum, pacman, apk)
+    Updates {
+        /// Servers to include (defaults to all)
+        servers: Vec<String>,
+
+        /// Only include servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WatchdogAction {
+    /// Start watching (in the background unless --foreground)
+    Start {
+        /// Servers to watch (defaults to all)
+        servers: Vec<String>,
+
+        /// Only watch servers carrying this tag (repeatable; all must match)
+        #[arg(long, conflicts_with = "servers")]
+        tag: Vec<String>,
+
+        /// Seconds between rounds of checks
+        #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(5..))]
+        interval: u64,
+
+        /// Log in with ssh instead of only checking the SSH port
+        #[arg(long)]
+        ssh: bool,
+
+        /// Run in this terminal instead of in the background
+        #[arg(long)]
+        foreground: bool,
+    },
+
+    /// Stop the background watchdog
+    Stop,
+
+    /// Show whether the watchdog runs and the last known state of each server
+    Status,
+}
+
+#[derive(Subcommand)]
+pub enum PoolAction {
+    /// Start the pool (in the background unless --foreground)
+    Start {
+        /// Servers to keep warm [default: the most-used servers from history]
+        servers: Vec<String>,
+
+        /// How many of the most-used servers to keep warm
+        #[arg(long, default_value_t = 5, conflicts_with = "servers")]
+        size: usize,
+
+        /// Run in this terminal instead of in the background
+        #[arg(long)]
+        foreground: bool,
+    },
+
+    /// Stop the pool and close its master connections
+    Stop,
+
+    /// Show which servers have a warm master connection
+    Status,
+}
+
+#[derive(Subcommand)]
+pub enum GroupAction {
+    /// List groups as a tree, with how many servers each holds
+    List,
+
+    /// Move servers into a group
+    Move {
+        /// Servers to move
+        #[arg(required = true)]
+        servers: Vec<String>,
+
+        /// Group to move them to, e.g. clients/acme
+        #[arg(long, required_unless_present = "ungroup")]
+        to: Option<String>,
+
+        /// Take the servers out of their group instead
+        #[arg(long, conflicts_with = "to")]
+        ungroup: bool,
+    },
+
+    /// Rename a group, moving its subgroups along
+    Rename {
+        /// Current group name
+        from: String,
+
+        /// New group name
+        to: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TunnelAction {
+    /// Open a tunnel and keep it up until stopped
+    Start {
+        /// Server name or list number to tunnel through
+        server: String,
+
+        /// Forward a local port: [bind_address:]port:host:hostport (repeatable)
+        #[arg(short = 'L', long = "local", value_name = "SPEC", required = true)]
+        forwards: Vec<String>,
+
+        /// Name for the tunnel [default: the server name]
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Run in the background instead of in this terminal
+        #[arg(short, long)]
+        detach: bool,
+    },
+
+    /// Stop a tunnel
+    Stop {
+        /// Tunnel name
+        #[arg(required_unless_present = "all")]
+        name: Option<String>,
+
+        /// Stop every tunnel
+        #[arg(long, conflicts_with = "name")]
+        all: bool,
+    },
+
+    /// List tunnels with their status and how often they reconnected
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum JobAction {
+    /// Schedule a command on a server
+    Add {
+        /// Job name
+        name: String,
+
+        /// Server name or list number to run the command on
+        #[arg(long)]
+        server: String,
+
+        /// Cron expression (minute hour day month weekday) in local time, or @hourly/@daily/@weekly/@monthly
+        #[arg(long)]
+        cron: String,
+
+        /// Command to run
+        #[arg(long)]
+        command: String,
+    },
+
+    /// List scheduled jobs and their last run
+    List,
+
+    /// Remove a scheduled job
+    Remove {
+        /// Job name
+        name: String,
+    },
+
+    /// Run a job now, in this terminal
+    Run {
+        /// Job name
+        name: String,
+    },
+
+    /// Show recent runs
+    History {
+        /// Only show runs of this job
+        name: Option<String>,
+
+        /// Number of runs to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Include each run's output
+        #[arg(long)]
+        output: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeyAction {
+    /// Generate a key pair with ssh-keygen, including FIDO2 security-key types
+    Gen {
+        /// Key type; the -sk types need a FIDO2 security key plugged in
+        #[arg(short = 't', long = "type", value_enum, default_value = "ed25519")]
+        key_type: KeyType,
+
+        /// Output file [default: ~/.ssh/id_<type>]
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Key comment
+        #[arg(short = 'C', long)]
+        comment: Option<String>,
+
+        /// Store the key on the security key so `ssh-add -K` can load it on another machine
+        #[arg(long)]
+        resident: bool,
+
+        /// Require the security key PIN in addition to a touch
+        #[arg(long)]
+        verify_required: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SecretAction {
+    /// Store a secret; the value is prompted for without echo
+    Set {
+        /// Server name or list number
+        server: String,
+
+        /// Secret name, e.g. sudo-password
+        name: String,
+
+        /// Read the value from stdin instead of prompting
+        #[arg(long)]
+        stdin: bool,
+
+        /// Store it in the OS keyring instead of the encrypted secrets file
+        #[arg(long)]
+        keyring: bool,
+    },
+
+    /// Print a secret's value
+    Get {
+        /// Server name or list number
+        server: String,
+
+        /// Secret name
+        name: String,
+    },
+
+    /// List secret names in the secrets file (values stay encrypted)
+    List {
+        /// Only list this server's secrets
+        server: Option<String>,
+    },
+
+    /// Delete a secret
+    Remove {
+        /// Server name or list number
+        server: String,
+
+        /// Secret name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DocsAction {
+    /// Write man pages and a Markdown command reference
+    Generate {
+        /// Output directory (man pages go to <DIR>/man1)
+        dir: PathBuf,
+    },
+}
+
+/// Optional settings for a server being added
+#[derive(Args, Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// SSH port, if sshd doesn't listen on 22
+    #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub port: Option<u16>,
+
+    /// Private key to log in with, instead of whatever the agent offers first
+    #[arg(long, value_name = "FILE")]
+    pub identity: Option<String>,
+
+    /// Tag the server, e.g. --tag web --tag prod (repeatable)
+    #[arg(long)]
+    pub tag: Vec<String>,
+
+    /// Put the server in a group, e.g. clients/acme
+    #[arg(long)]
+    pub group: Option<String>,
+
+    /// What the server is for, e.g. "billing API, EU"
+    #[arg(short, long)]
+    pub description: Option<String>,
+}
+
+/// Tag changes for `hop edit`
+#[derive(Args, Debug, Clone, Default)]
+pub struct TagEdits {
+    /// Add a tag to the server (repeatable)
+    #[arg(long, value_name = "TAG")]
+    pub add_tag: Vec<String>,
+
+    /// Remove a tag from the server (repeatable)
+    #[arg(long, value_name = "TAG")]
+    pub remove_tag: Vec<String>,
+}
+
+impl TagEdits {
+    pub fn is_empty(&self) -> bool {
+        self.add_tag.is_empty() && self.remove_tag.is_empty()
+    }
+}
+
+/// Concurrency options shared by commands that work on many servers
+#[derive(Args, Debug, Clone, Default)]
+pub struct RolloutArgs {
+    /// How many servers to work on at once
+    #[arg(short = 'j', long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub parallel: Option<u16>,
+
+    /// One server at a time (same as -j 1)
+    #[arg(long, conflicts_with = "parallel")]
+    pub serial: bool,
+
+    /// Work through the servers in batches of N
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub batch: Option<u16>,
+
+    /// Pause between batches, e.g. 30s or 2m
+    #[arg(long, value_name = "DURATION", requires = "batch", value_parser = parse_delay)]
+    pub batch_delay: Option<Duration>,
+}
+
+impl RolloutArgs {
+    /// The rollout these options ask for; without -j a batch runs all at
+    /// once, and otherwise `default_parallel` servers run at a time
+    pub fn rollout(&self, default_parallel: usize) -> Rollout {
+        let batch = self.batch.map(usize::from);
+        let parallel = match (self.serial, self.parallel) {
+            (true, _) => 1,
+            (false, Some(parallel)) => parallel.into(),
+            (false, None) => batch.unwrap_or(default_parallel),
+        };
+        Rollout {
+            parallel,
+            batch,
+            batch_delay: self.batch_delay.unwrap_or_default(),
+        }
     }
 }
 
+fn parse_delay(s: &str) -> Result<Duration, String> {
+    parse_duration(s).map_err(|e| e.to_string())
+}
+
+/// Parse a `KEY=VALUE` argument
+fn parse_key_val(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))?;
+    Ok((key.trim().to_string(), value.trim().to_string()))
+}
+
 impl Cli {
+    /// Parse the command line, exiting with USAGE_ERROR on invalid usage
     pub fn new() -> Self {
-        Self::parse()
+        Self::try_parse().unwrap_or_else(|e| {
+            let _ = e.print();
+            process::exit(if e.use_stderr() { USAGE_ERROR } else { 0 });
+        })
     }
 }
 
@@ -103,18 +988,44 @@ mod tests {
     #[test]
     fn test_cli_parsing() {
         // Test basic command parsing
-        let cli = Cli::try_parse_from(&["hop", "list"]);
+        let cli = Cli::try_parse_from(["hop", "list"]);
         assert!(cli.is_ok());
         
         match cli.unwrap().command {
-            Commands::List { verbose } => assert!(!verbose),
+            Some(Commands::List { sort, status, group_by, .. }) => assert!(sort.is_none() && !status && group_by.is_none()),
             _ => panic!("Expected List command"),
         }
     }
 
+    #[test]
+    fn test_quick_connect_parsing() {
+        let cli = Cli::try_parse_from(["hop", "3"]).unwrap();
+        assert_eq!(cli.target.as_deref(), Some("3"));
+        assert!(cli.command.is_none());
+
+        let cli = Cli::try_parse_from(["hop", "-v", "list"]).unwrap();
+        assert!(cli.target.is_none());
+        assert!(matches!(cli.command, Some(Commands::List { .. })));
+
+        let cli = Cli::try_parse_from(["hop", "list", "-l"]).unwrap();
+        assert_eq!(cli.verbose, 0);
+        assert!(matches!(cli.command, Some(Commands::List { long: true, .. })));
+    }
+
+    #[test]
+    fn test_global_verbosity_flags() {
+        let cli = Cli::try_parse_from(["hop", "list", "-vv"]).unwrap();
+        assert_eq!(cli.verbose, 2);
+
+        let cli = Cli::try_parse_from(["hop", "-q", "connect", "web"]).unwrap();
+        assert!(cli.quiet);
+
+        assert!(Cli::try_parse_from(["hop", "-q", "-v", "list"]).is_err());
+    }
+
     #[test]
     fn test_add_command_parsing() {
-        let cli = Cli::try_parse_from(&[
+        let cli = Cli::try_parse_from([
             "hop", "add", 
             "--name", "test-server", 
             "--user", "ubuntu", 
@@ -124,10 +1035,11 @@ mod tests {
         assert!(cli.is_ok());
         
         match cli.unwrap().command {
-            Commands::Add { name, user, ip } => {
+            Some(Commands::Add { name, user, ip, scan, .. }) => {
                 assert_eq!(name, "test-server");
                 assert_eq!(user, "ubuntu");
                 assert_eq!(ip, "192.168.1.1");
+                assert!(!scan);
             },
             _ => panic!("Expected Add command"),
         }
@@ -135,15 +1047,115 @@ mod tests {
 
     #[test]
     fn test_connect_command_parsing() {
-        let cli = Cli::try_parse_from(&["hop", "connect", "test-server"]);
+        let cli = Cli::try_parse_from(["hop", "connect", "test-server"]);
         assert!(cli.is_ok());
         
         match cli.unwrap().command {
-            Commands::Connect { identifier, test } => {
+            Some(Commands::Connect { identifier, test, .. }) => {
                 assert_eq!(identifier, "test-server");
                 assert!(!test);
             },
             _ => panic!("Expected Connect command"),
         }
     }
+
+    #[test]
+    fn test_show_qr_format_requires_qr() {
+        let cli = Cli::try_parse_from(["hop", "show", "web", "--qr", "--qr-format", "import"]).unwrap();
+        match cli.command {
+            Some(Commands::Show { qr, qr_format, .. }) => {
+                assert!(qr);
+                assert_eq!(qr_format, QrPayload::Import);
+            }
+            _ => panic!("Expected Show command"),
+        }
+
+        assert!(Cli::try_parse_from(["hop", "show", "web", "--qr-format", "import"]).is_err());
+    }
+
+    #[test]
+    fn test_edit_editor_conflicts_with_field_flags() {
+        let cli = Cli::try_parse_from(["hop", "edit", "web", "--editor"]).unwrap();
+        match cli.command {
+            Some(Commands::Edit { identifier, editor, .. }) => {
+                assert_eq!(identifier.as_deref(), Some("web"));
+                assert!(editor);
+            },
+            _ => panic!("Expected Edit command"),
+        }
+
+        assert!(Cli::try_parse_from(["hop", "edit", "web", "--editor", "--user", "root"]).is_err());
+    }
+
+    #[test]
+    fn test_key_gen_parsing() {
+        let cli = Cli::try_parse_from(["hop", "key", "gen", "-t", "ed25519-sk", "--resident"]).unwrap();
+        match cli.command {
+            Some(Commands::Key { action: KeyAction::Gen { key_type, file, resident, verify_required, .. } }) => {
+                assert_eq!(key_type, KeyType::Ed25519Sk);
+                assert_eq!(file, None);
+                assert!(resident);
+                assert!(!verify_required);
+            },
+            _ => panic!("Expected Key Gen command"),
+        }
+    }
+
+    #[test]
+    fn test_test_command_parsing() {
+        let cli = Cli::try_parse_from(["hop", "test", "--tag", "prod", "-j", "4"]).unwrap();
+        match cli.command {
+            Some(Commands::Test { servers, all, tag, jobs, tcp_only }) => {
+                assert!(servers.is_empty());
+                assert!(!all);
+                assert_eq!(tag, vec!["prod"]);
+                assert_eq!(jobs, Some(4));
+                assert!(!tcp_only);
+            },
+            _ => panic!("Expected Test command"),
+        }
+        
+        assert!(Cli::try_parse_from(["hop", "test"]).is_err());
+        assert!(Cli::try_parse_from(["hop", "test", "--all", "-j", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_bulk_edit_parsing() {
+        let cli = Cli::try_parse_from([
+            "hop", "edit", "--tag", "legacy", "--set", "user=deploy", "--set", "ip=10.0.0.1",
+        ]).unwrap();
+        match cli.command {
+            Some(Commands::Edit { identifier, tag, set, .. }) => {
+                assert!(identifier.is_none());
+                assert_eq!(tag, vec!["legacy"]);
+                assert_eq!(set, vec![
+                    ("user".to_string(), "deploy".to_string()),
+                    ("ip".to_string(), "10.0.0.1".to_string()),
+                ]);
+            },
+            _ => panic!("Expected Edit command"),
+        }
+
+        assert!(Cli::try_parse_from(["hop", "edit", "--tag", "legacy"]).is_err());
+        assert!(Cli::try_parse_from(["hop", "edit", "--tag", "legacy", "--set", "user"]).is_err());
+
+        let cli = Cli::try_parse_from(["hop", "edit", "web", "--set", "identity_agent=1password"]).unwrap();
+        match cli.command {
+            Some(Commands::Edit { identifier, set, .. }) => {
+                assert_eq!(identifier.as_deref(), Some("web"));
+                assert_eq!(set, vec![("identity_agent".to_string(), "1password".to_string())]);
+            },
+            _ => panic!("Expected Edit command"),
+        }
+
+        let cli = Cli::try_parse_from(["hop", "edit", "web", "--add-tag", "prod", "--remove-tag", "staging"]).unwrap();
+        match cli.command {
+            Some(Commands::Edit { tags, .. }) => {
+                assert_eq!(tags.add_tag, vec!["prod"]);
+                assert_eq!(tags.remove_tag, vec!["staging"]);
+            },
+            _ => panic!("Expected Edit command"),
+        }
+        assert!(Cli::try_parse_from(["hop", "edit", "web", "--add-tag", "prod", "--set", "user=deploy"]).is_err());
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use clap::Command;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Render the man page for `cmd` in roff format
+#[cfg(feature = "man")]
+pub fn render_man_page(cmd: &Command, out: &mut dyn Write) -> Result<()> {
+    clap_mangen::Man::new(cmd.clone()).render(out)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "man"))]
+pub fn render_man_page(_cmd: &Command, _out: &mut dyn Write) -> Result<()> {
+    Err(man_disabled())
+}
+
+/// Write `hop.1` plus one `hop-<subcommand>.1` page per subcommand into `dir`
+///
+/// `cmd` must already be built so global flags appear on every page.
+pub fn write_man_pages(cmd: &Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    write_man_page(cmd, cmd.get_name(), dir, &mut written)?;
+    Ok(written)
+}
+
+#[cfg(not(feature = "man"))]
+fn man_disabled() -> anyhow::Error {
+    anyhow::anyhow!("hop was built without the 'man' feature (cargo build --features man)")
+}
+
+fn write_man_page(cmd: &Command, name: &str, dir: &Path, written: &mut Vec<PathBuf>) -> Result<()> {
+    let path = dir.join(format!("{}.1", name));
+    let mut page = Vec::new();
+    render_man_page(&cmd.clone().name(name.to_string()), &mut page)?;
+    fs::write(&path, page)
+        .with_context(|| format!("Failed to create '{}'", path.display()))?;
+    written.push(path);
+
+    for sub in documented_subcommands(cmd) {
+        write_man_page(sub, &format!("{}-{}", name, sub.get_name()), dir, written)?;
+    }
+    Ok(())
+}
+
+/// Subcommands worth documenting, skipping hidden ones and clap's `help`
+fn documented_subcommands(cmd: &Command) -> impl Iterator<Item = &Command> {
+    cmd.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+}
+
+/// Render a Markdown reference of every command, its arguments and defaults
+pub fn render_markdown(cmd: &Command) -> String {
+    let mut out = format!("# {} command reference\n", cmd.get_name());
+    out.push_str("\n_Generated by `hop docs generate`; do not edit by hand._\n");
+    render_command(cmd, cmd.get_name(), &mut out, true);
+    out
+}
+
+/// Global flags are only listed on the root command
+fn render_command(cmd: &Command, path: &str, out: &mut String, root: bool) {
+    out.push_str(&format!("\n## `{}`\n\n", path));
+    if let Some(about) = cmd.get_long_about().or(cmd.get_about()) {
+        out.push_str(&format!("{}\n\n", about));
+    }
+    let usage = cmd.clone().render_usage().to_string();
+    out.push_str(&format!("```\n{}\n```\n", usage.trim_start_matches("Usage: ")));
+
+    let args: Vec<String> = cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && (root || !arg.is_global_set()))
+        .filter(|arg| !matches!(arg.get_id().as_str(), "help" | "version"))
+        .map(render_arg)
+        .collect();
+    if !args.is_empty() {
+        out.push('\n');
+        for arg in args {
+            out.push_str(&format!("- {}\n", arg));
+        }
+    }
+
+    for sub in documented_subcommands(cmd) {
+        render_command(sub, &format!("{} {}", path, sub.get_name()), out, false);
+    }
+}
+
+fn render_arg(arg: &clap::Arg) -> String {
+    let mut names = Vec::new();
+    if let Some(short) = arg.get_short() {
+        names.push(format!("-{}", short));
+    }
+    if let Some(long) = arg.get_long() {
+        names.push(format!("--{}", long));
+    }
+    let value = arg
+        .get_value_names()
+        .and_then(|values| values.first())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| arg.get_id().to_string().to_uppercase());
+    let takes_value = arg.get_num_args().is_some_and(|n| n.takes_values());
+    let spec = match (names.is_empty(), takes_value) {
+        (true, _) => format!("<{}>", value),
+        (false, true) => format!("{} <{}>", names.join(", "), value),
+        (false, false) => names.join(", "),
+    };
+
+    let mut line = format!("`{}`", spec);
+    if let Some(help) = arg.get_help() {
+        line.push_str(&format!(": {}", help));
+    }
+    let defaults: Vec<String> = arg.get_default_values().iter().map(|v| v.to_string_lossy().into_owned()).collect();
+    if !defaults.is_empty() && takes_value {
+        line.push_str(&format!(" (default: `{}`)", defaults.join(",")));
+    }
+    let possible: Vec<String> = arg.get_possible_values().iter().map(|v| v.get_name().to_string()).collect();
+    if !possible.is_empty() && takes_value {
+        line.push_str(&format!(" (values: {})", possible.join(", ")));
+    }
+    if let Some(env) = arg.get_env() {
+        line.push_str(&format!(" (env: `{}`)", env.to_string_lossy()));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_render_markdown_covers_subcommands() {
+        let mut cmd = Cli::command();
+        cmd.build();
+        let doc = render_markdown(&cmd);
+
+        assert!(doc.contains("## `hop connect`"));
+        assert!(doc.contains("## `hop config set`"));
+        assert!(doc.contains("`-y, --yes`"));
+        assert!(doc.contains("(env: `HOP_ASSUME_YES`)"));
+        assert!(doc.contains("(values: json, jsonl, markdown, html)"));
+        assert_eq!(doc.matches("`-y, --yes`").count(), 1);
+    }
+
+    #[cfg(feature = "man")]
+    #[test]
+    fn test_write_man_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cmd = Cli::command();
+        cmd.build();
+        let written = write_man_pages(&cmd, dir.path()).unwrap();
+
+        assert!(written.contains(&dir.path().join("hop.1")));
+        assert!(written.contains(&dir.path().join("hop-config-get.1")));
+        assert!(!written.contains(&dir.path().join("hop-help.1")));
+        let page = fs::read_to_string(dir.path().join("hop-connect.1")).unwrap();
+        assert!(page.contains(".TH hop-connect"));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::utils::shell_quote;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Marker printed by `hash_command` when the file is not there
+const MISSING: &str = "missing";
+
+/// Remote probe: print the SHA-256 of the file at `path`, or `missing`
+/// when it is not a readable file
+pub fn hash_command(path: &str) -> String {
+    let path = shell_quote(path);
+    format!(
+        "if [ -f {path} ] && [ -r {path} ]; then \
+         (sha256sum {path} 2>/dev/null || shasum -a 256 {path}) | cut -d ' ' -f 1; \
+         else echo {MISSING}; fi"
+    )
+}
+
+/// Parse the output of `hash_command`; `None` means the file is missing
+pub fn parse_hash(output: &str) -> Result<Option<String>> {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    if line == MISSING {
+        return Ok(None);
+    }
+    if line.len() != 64 || !line.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!("Unexpected output from sha256sum: {}", line));
+    }
+    Ok(Some(line.to_lowercase()))
+}
+
+/// The file state most servers share (a missing file counts as a state),
+/// or `None` when no state has more servers than every other
+pub fn majority<'a>(states: &[Option<&'a str>]) -> Option<Option<&'a str>> {
+    let mut counts: HashMap<Option<&str>, usize> = HashMap::new();
+    for state in states {
+        *counts.entry(*state).or_default() += 1;
+    }
+    let most = counts.values().copied().max()?;
+    let mut leaders = counts.into_iter().filter(|&(_, count)| count == most);
+    match (leaders.next(), leaders.next()) {
+        (Some((state, _)), None) => Some(state),
+        _ => None,
+    }
+}
+
+/// Remote command printing the file at `path`
+pub fn read_command(path: &str) -> String {
+    format!("cat -- {}", shell_quote(path))
+}
+
+/// Remote script comparing the file at `path` with the reference content on
+/// stdin, printing a unified diff; exits 0 whether or not the files differ
+pub fn diff_command(path: &str, reference: &str, server: &str) -> String {
+    format!(
+        "diff -u -L {} -L {} - {}; [ $? -le 1 ]",
+        shell_quote(&format!("{}:{}", reference, path)),
+        shell_quote(&format!("{}:{}", server, path)),
+        shell_quote(path)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hash() {
+        let hash = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+        assert_eq!(parse_hash(&format!("{}\n", hash)).unwrap(), Some(hash.to_lowercase()));
+        assert_eq!(parse_hash("missing\n").unwrap(), None);
+        assert!(parse_hash("cut: not found\n").is_err());
+
+        assert!(hash_command("/etc/my app.conf").contains("sha256sum '/etc/my app.conf'"));
+    }
+
+    #[test]
+    fn test_majority() {
+        assert_eq!(majority(&[Some("a"), Some("b"), Some("a")]), Some(Some("a")));
+        assert_eq!(majority(&[None, None, Some("a")]), Some(None));
+        assert_eq!(majority(&[Some("a"), Some("b")]), None);
+        assert_eq!(majority(&[]), None);
+    }
+}
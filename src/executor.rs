@@ -0,0 +1,167 @@
+use crate::config::DEFAULT_PARALLEL;
+use crate::rollout::Rollout;
+use crate::signals;
+use crate::utils::is_quiet;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+static PARALLEL: AtomicUsize = AtomicUsize::new(DEFAULT_PARALLEL);
+
+/// Use `parallel` workers by default (set from the `parallel` setting)
+pub fn set_default_parallel(parallel: usize) {
+    PARALLEL.store(parallel.max(1), Ordering::Relaxed);
+}
+
+/// Workers used by read-only fleet commands without `-j`
+pub fn default_parallel() -> usize {
+    PARALLEL.load(Ordering::Relaxed)
+}
+
+/// Runs one task per item on a bounded pool of worker threads, batch by
+/// batch as a `Rollout` asks
+///
+/// Results come back in the order of the items. Once `cancel` is called, or
+/// hop gets Ctrl-C, no new task starts; the ones that never ran are `None`.
+pub struct Executor {
+    rollout: Rollout,
+    cancelled: AtomicBool,
+    /// Shown with a done/total counter on stderr while tasks run
+    progress: Option<String>,
+}
+
+impl Executor {
+    pub fn new(rollout: Rollout) -> Self {
+        Executor { rollout, cancelled: AtomicBool::new(false), progress: None }
+    }
+
+    /// At most `jobs` tasks at a time, all in one batch
+    pub fn parallel(jobs: usize) -> Self {
+        Executor::new(Rollout::parallel(jobs))
+    }
+
+    /// Report progress as `label: done/total` on a terminal
+    ///
+    /// Only for tasks that print nothing themselves, since the counter
+    /// rewrites the current line.
+    pub fn with_progress(mut self, label: &str) -> Self {
+        self.progress = Some(label.to_string());
+        self
+    }
+
+    /// Stop starting new tasks; running ones finish
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || signals::interrupted().is_some()
+    }
+
+    /// Run `task` for every item (with its index)
+    pub fn run<I: Sync, T: Send>(&self, items: &[I], task: impl Fn(usize, &I) -> T + Sync) -> Vec<Option<T>> {
+        let progress = self.progress.as_deref().filter(|_| io::stderr().is_terminal() && !is_quiet());
+        // Let the running tasks finish, so the caller can report on them
+        let _deferred = signals::defer();
+        let done = AtomicUsize::new(0);
+        let batches = self.rollout.batches(items.len());
+        let mut results = Vec::with_capacity(items.len());
+        for (number, range) in batches.iter().enumerate() {
+            if self.is_cancelled() {
+                results.extend(range.clone().map(|_| None));
+                continue;
+            }
+            if number > 0 {
+                self.rollout.pause(number, batches.len());
+            }
+            let start = range.start;
+            results.extend(self.run_batch(&items[range.clone()], |offset, item| {
+                let result = task(start + offset, item);
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(label) = progress {
+                    eprint!("\r{}: {}/{}", label, done, items.len());
+                    let _ = io::stderr().flush();
+                }
+                result
+            }));
+        }
+        if progress.is_some() && done.into_inner() > 0 {
+            eprint!("\r\x1b[2K");
+        }
+        results
+    }
+
+    /// Like `run`, for tasks that are never cancelled
+    pub fn run_all<I: Sync, T: Send>(&self, items: &[I], task: impl Fn(usize, &I) -> T + Sync) -> Vec<T> {
+        self.run(items, task)
+            .into_iter()
+            .map(|result| result.expect("an executor that is not cancelled runs every task"))
+            .collect()
+    }
+
+    /// One batch on at most `rollout.parallel` threads
+    fn run_batch<I: Sync, T: Send>(&self, items: &[I], task: impl Fn(usize, &I) -> T + Sync) -> Vec<Option<T>> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<T>>>());
+
+        thread::scope(|scope| {
+            for _ in 0..self.rollout.parallel.max(1).min(items.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    if self.is_cancelled() {
+                        continue;
+                    }
+                    let result = task(index, item);
+                    results.lock().expect("executor results lock")[index] = Some(result);
+                });
+            }
+        });
+
+        results.into_inner().expect("executor results lock")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_results_keep_item_order() {
+        let items: Vec<u64> = (0..20).collect();
+        let results = Executor::parallel(4).run_all(&items, |index, item| {
+            thread::sleep(Duration::from_millis(20 - item));
+            (index, item * 2)
+        });
+        assert_eq!(results, items.iter().map(|&i| (i as usize, i * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_concurrency_is_bounded() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        Executor::parallel(3).run_all(&[(); 12], |_, _| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        assert_eq!(peak.into_inner(), 3);
+    }
+
+    #[test]
+    fn test_cancel_skips_remaining_tasks() {
+        let executor = Executor::new(Rollout { parallel: 1, batch: Some(2), batch_delay: Duration::ZERO });
+        let results = executor.run(&[1, 2, 3, 4, 5], |_, &item| {
+            if item == 2 {
+                executor.cancel();
+            }
+            item
+        });
+        assert_eq!(results, vec![Some(1), Some(2), None, None, None]);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::health::HealthCache;
+use crate::history::Usage;
+use crate::models::Server;
+use crate::utils::{format_time_ago, format_utc_date};
+use std::collections::{BTreeMap, HashMap};
+
+/// Everything the inventory report shows about one server
+pub struct InventoryRow<'a> {
+    pub server: &'a Server,
+    pub last_used: Option<u64>,
+    pub uses: usize,
+    /// Cached reachability and the time of the check
+    pub status: Option<(bool, u64)>,
+}
+
+impl<'a> InventoryRow<'a> {
+    pub fn collect(
+        servers: &'a [Server],
+        usage: &HashMap<String, Usage>,
+        health: &HealthCache,
+    ) -> Vec<InventoryRow<'a>> {
+        servers
+            .iter()
+            .map(|server| {
+                let stats = usage.get(&server.qualified_name()).copied().unwrap_or_default();
+                InventoryRow {
+                    server,
+                    last_used: (stats.count > 0).then_some(stats.last_used),
+                    uses: stats.count,
+                    status: health.get(&server.qualified_name()).map(|h| (h.reachable, h.checked_at)),
+                }
+            })
+            .collect()
+    }
+
+    fn last_used_text(&self) -> String {
+        self.last_used.map(format_time_ago).unwrap_or_else(|| "never".to_string())
+    }
+
+    fn status_text(&self) -> String {
+        match self.status {
+            Some((true, checked_at)) => format!("reachable ({})", format_time_ago(checked_at)),
+            Some((false, checked_at)) => format!("unreachable ({})", format_time_ago(checked_at)),
+            None => "unchecked".to_string(),
+        }
+    }
+}
+
+/// Servers listed under each tag, in name order
+fn servers_by_tag<'a>(rows: &[InventoryRow<'a>]) -> BTreeMap<&'a str, Vec<&'a str>> {
+    let mut tags: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for row in rows {
+        for tag in &row.server.tags {
+            tags.entry(tag.as_str()).or_default().push(row.server.name.as_str());
+        }
+    }
+    for names in tags.values_mut() {
+        names.sort();
+    }
+    tags
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "<br>")
+}
+
+/// Render the inventory as a Markdown document
+pub fn render_markdown(rows: &[InventoryRow], generated_at: u64) -> String {
+    let mut out = String::new();
+    out.push_str("# Server Inventory\n\n");
+    out.push_str(&format!(
+        "_Generated by hop on {} — {} server(s)._\n\n",
+        format_utc_date(generated_at),
+        rows.len()
+    ));
+
+    out.push_str("| Name | User | Host | Tags | Notes | Last used | Uses | Status |\n");
+    out.push_str("|------|------|------|------|-------|-----------|------|--------|\n");
+    for row in rows {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            escape_markdown(&row.server.name),
+            escape_markdown(&row.server.user),
+            escape_markdown(&row.server.ip),
+            escape_markdown(&row.server.tags.join(", ")),
+            escape_markdown(row.server.notes.as_deref().unwrap_or("")),
+            row.last_used_text(),
+            row.uses,
+            row.status_text(),
+        ));
+    }
+
+    let tags = servers_by_tag(rows);
+    if !tags.is_empty() {
+        out.push_str("\n## By tag\n");
+        for (tag, names) in tags {
+            out.push_str(&format!("\n### {}\n\n", escape_markdown(tag)));
+            for name in names {
+                out.push_str(&format!("- {}\n", escape_markdown(name)));
+            }
+        }
+    }
+
+    out
+}
+
+/// Render the inventory as a standalone HTML page
+pub fn render_html(rows: &[InventoryRow], generated_at: u64) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Server Inventory</title>\n");
+    out.push_str("<style>\n");
+    out.push_str("body { font-family: sans-serif; margin: 2em; }\n");
+    out.push_str("table { border-collapse: collapse; }\n");
+    out.push_str("th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }\n");
+    out.push_str("th { background: #f4f4f4; }\n");
+    out.push_str(".tag { background: #e8eefc; border-radius: 3px; padding: 0 4px; margin-right: 2px; }\n");
+    out.push_str("</style>\n</head>\n<body>\n");
+    out.push_str("<h1>Server Inventory</h1>\n");
+    out.push_str(&format!(
+        "<p><em>Generated by hop on {} — {} server(s).</em></p>\n",
+        format_utc_date(generated_at),
+        rows.len()
+    ));
+
+    out.push_str("<table>\n<tr><th>Name</th><th>User</th><th>Host</th><th>Tags</th><th>Notes</th><th>Last used</th><th>Uses</th><th>Status</th></tr>\n");
+    for row in rows {
+        let tags: Vec<String> = row.server.tags
+            .iter()
+            .map(|tag| format!("<span class=\"tag\">{}</span>", escape_html(tag)))
+            .collect();
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&row.server.name),
+            escape_html(&row.server.user),
+            escape_html(&row.server.ip),
+            tags.join(""),
+            escape_html(row.server.notes.as_deref().unwrap_or("")),
+            row.last_used_text(),
+            row.uses,
+            row.status_text(),
+        ));
+    }
+    out.push_str("</table>\n");
+
+    let tags = servers_by_tag(rows);
+    if !tags.is_empty() {
+        out.push_str("<h2>By tag</h2>\n");
+        for (tag, names) in tags {
+            out.push_str(&format!("<h3>{}</h3>\n<ul>\n", escape_html(tag)));
+            for name in names {
+                out.push_str(&format!("<li>{}</li>\n", escape_html(name)));
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Server> {
+        let mut web = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        web.tags = vec!["prod".to_string()];
+        web.notes = Some("customer data | careful".to_string());
+        let db = Server::new("db".to_string(), "postgres".to_string(), "10.0.0.2".to_string());
+        vec![web, db]
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let servers = sample();
+        let rows = InventoryRow::collect(&servers, &HashMap::new(), &HealthCache::default());
+        let doc = render_markdown(&rows, 0);
+
+        assert!(doc.starts_with("# Server Inventory"));
+        assert!(doc.contains("1970-01-01"));
+        assert!(doc.contains("| web | root | 10.0.0.1 | prod | customer data \\| careful | never | 0 | unchecked |"));
+        assert!(doc.contains("### prod\n\n- web\n"));
+    }
+
+    #[test]
+    fn test_render_html_escapes() {
+        let mut servers = sample();
+        servers[1].notes = Some("<script>".to_string());
+        let rows = InventoryRow::collect(&servers, &HashMap::new(), &HealthCache::default());
+        let doc = render_html(&rows, 0);
+
+        assert!(doc.contains("&lt;script&gt;"));
+        assert!(!doc.contains("<script>"));
+        assert!(doc.contains("<span class=\"tag\">prod</span>"));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::utils::{ensure_dir_exists, write_private};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How long a TCP connect may take before the host counts as unreachable
+const TCP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Result of the most recent reachability check for a server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub reachable: bool,
+    /// Unix timestamp (seconds) of the check
+    pub checked_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Time to open a TCP connection to the SSH port, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_ms: Option<u64>,
+    /// Time for a full non-interactive ssh login, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_ms: Option<u64>,
+    /// Whether the server waits for a reboot, when the login check could tell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reboot_required: Option<bool>,
+    /// Unix timestamp of the first check in the current run of failed checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unreachable_since: Option<u64>,
+}
+
+impl HealthStatus {
+    /// Latency used for display and sorting: the TCP connect time, which
+    /// reflects network distance better than the login time
+    pub fn latency_ms(&self) -> Option<u64> {
+        self.tcp_ms.or(self.ssh_ms)
+    }
+}
+
+/// Remote check for a pending reboot, printing `reboot-required` or
+/// `reboot-ok`, or nothing when the distribution gives no hint
+///
+/// Debian/Ubuntu leave /var/run/reboot-required after patching; on
+/// RHEL-likes `needs-restarting -r` exits with 1. Always exits 0.
+pub const REBOOT_CHECK: &str = "\
+if [ -f /var/run/reboot-required ]; then echo reboot-required; \
+elif [ -d /var/lib/update-notifier ]; then echo reboot-ok; \
+elif command -v needs-restarting >/dev/null 2>&1; then \
+needs-restarting -r >/dev/null 2>&1; case $? in 0) echo reboot-ok;; 1) echo reboot-required;; esac; \
+fi; true";
+
+/// Read the result of `REBOOT_CHECK` from command output
+pub fn parse_reboot(output: &str) -> Option<bool> {
+    output.lines().map(str::trim).find_map(|line| match line {
+        "reboot-required" => Some(true),
+        "reboot-ok" => Some(false),
+        _ => None,
+    })
+}
+
+/// Cached health results keyed by server name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthCache {
+    pub servers: HashMap<String, HealthStatus>,
+}
+
+impl HealthCache {
+    pub fn get(&self, server: &str) -> Option<&HealthStatus> {
+        self.servers.get(server)
+    }
+
+    /// Store the latest result, carrying over when the server became unreachable
+    pub fn record(&mut self, server: &str, mut status: HealthStatus) {
+        status.unreachable_since = match self.servers.get(server) {
+            _ if status.reachable => None,
+            Some(previous) if !previous.reachable => previous.unreachable_since.or(Some(previous.checked_at)),
+            _ => Some(status.checked_at),
+        };
+        self.servers.insert(server.to_string(), status);
+    }
+}
+
+/// Measure how long it takes to open a TCP connection to `host:port`
+pub fn tcp_latency(host: &str, port: u16) -> Result<Duration> {
+    let addr = (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("Cannot resolve {}", host))?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Cannot resolve {}", host))?;
+    
+    let started = Instant::now();
+    TcpStream::connect_timeout(&addr, TCP_TIMEOUT)
+        .with_context(|| format!("Port {} closed", port))?;
+    Ok(started.elapsed())
+}
+
+/// Get the path to the health cache file
+pub fn get_health_file_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("health.json"))
+}
+
+/// Load cached health results, returning an empty cache if none exist
+pub fn load_health() -> Result<HealthCache> {
+    let path = get_health_file_path()?;
+    if !path.exists() {
+        return Ok(HealthCache::default());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read health cache: {}", path.display()))?;
+
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse health cache: {}", path.display()))
+}
+
+/// Save health results
+pub fn save_health(cache: &HealthCache) -> Result<()> {
+    let path = get_health_file_path()?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+
+    let contents = serde_json::to_string_pretty(cache)
+        .context("Failed to serialize health cache")?;
+
+    write_private(&path, &contents)
+        .with_context(|| format!("Failed to write health cache: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_cache_roundtrip() {
+        let mut cache = HealthCache::default();
+        cache.record("web", HealthStatus { reachable: true, checked_at: 100, error: None, tcp_ms: Some(3), ssh_ms: Some(120), reboot_required: None, unreachable_since: None });
+        cache.record("db", HealthStatus { reachable: false, checked_at: 200, error: Some("timeout".to_string()), tcp_ms: None, ssh_ms: None, reboot_required: None, unreachable_since: None });
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let loaded: HealthCache = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.get("web"), cache.get("web"));
+        assert_eq!(loaded.get("db").unwrap().error.as_deref(), Some("timeout"));
+        assert!(loaded.get("other").is_none());
+        assert_eq!(loaded.get("web").unwrap().latency_ms(), Some(3));
+
+        // Entries cached before latency was measured still load
+        let old: HealthStatus = serde_json::from_str(r#"{"reachable":true,"checked_at":1}"#).unwrap();
+        assert_eq!(old.latency_ms(), None);
+
+        // A run of failures keeps the time of the first one
+        let down = cache.get("db").unwrap().clone();
+        let failed = |checked_at| HealthStatus { checked_at, ..down.clone() };
+        cache.record("db", failed(300));
+        assert_eq!(cache.get("db").unwrap().unreachable_since, Some(200));
+        cache.record("db", HealthStatus { reachable: true, error: None, ..failed(400) });
+        assert_eq!(cache.get("db").unwrap().unreachable_since, None);
+    }
+
+    #[test]
+    fn test_parse_reboot() {
+        assert_eq!(parse_reboot("reboot-required\n"), Some(true));
+        assert_eq!(parse_reboot("banner\nreboot-ok\n"), Some(false));
+        assert_eq!(parse_reboot(""), None);
+    }
+
+    #[test]
+    fn test_tcp_latency_closed_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(tcp_latency("127.0.0.1", port).is_ok());
+        drop(listener);
+        assert!(tcp_latency("127.0.0.1", port).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use crate::models::Server;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+#[cfg(feature = "scripting")]
+use crate::utils::{local_time, now_unix, print_warning, prompt_input};
+#[cfg(feature = "scripting")]
+use rhai::{Dynamic, Engine, Scope, AST};
+#[cfg(feature = "scripting")]
+use std::sync::OnceLock;
+
+/// Upper bound on the work one hook call may do, so a runaway loop can't hang hop
+#[cfg(feature = "scripting")]
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Get the path to the hooks script
+pub fn get_hooks_file_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("hooks.rhai"))
+}
+
+/// A compiled hooks.rhai; every hook function in it is optional
+#[cfg(feature = "scripting")]
+struct Hooks {
+    engine: Engine,
+    ast: AST,
+}
+
+#[cfg(feature = "scripting")]
+impl Hooks {
+    fn compile(source: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.register_fn("env", |name: &str| std::env::var(name).unwrap_or_default());
+        engine.register_fn("local_hour", || local_time(now_unix()).hour as i64);
+        // 0 = Sunday
+        engine.register_fn("local_weekday", || local_time(now_unix()).weekday as i64);
+        engine.register_fn("prompt", |message: &str| {
+            prompt_input(message).ok().flatten().unwrap_or_default()
+        });
+
+        let ast = engine.compile(source).map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(Hooks { engine, ast })
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    fn call(&self, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic> {
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args)
+            .map_err(|e| anyhow::anyhow!("hooks.rhai: {}: {}", name, e))
+    }
+
+    /// The reason to refuse the connection, if the hook refuses it
+    fn before_connect(&self, server: &Server) -> Result<Option<String>> {
+        if !self.has("before_connect") {
+            return Ok(None);
+        }
+        let verdict = self.call("before_connect", (to_dynamic(server)?,))?;
+        if verdict.is_unit() {
+            return Ok(None);
+        }
+        if let Ok(allowed) = verdict.as_bool() {
+            return Ok((!allowed).then(|| "refused by hooks.rhai".to_string()));
+        }
+        if verdict.is_string() {
+            return Ok(Some(verdict.to_string()));
+        }
+        Err(anyhow::anyhow!("hooks.rhai: before_connect must return true, false, a reason or nothing"))
+    }
+
+    /// Extra fields to record in the audit entry
+    fn after_exec(&self, server: &Server, command: &str, exit_code: i32) -> Result<BTreeMap<String, String>> {
+        if !self.has("after_exec") {
+            return Ok(BTreeMap::new());
+        }
+        let result = self.call("after_exec", (to_dynamic(server)?, command.to_string(), exit_code as i64))?;
+        if result.is_unit() {
+            return Ok(BTreeMap::new());
+        }
+        let map = result.try_cast::<rhai::Map>()
+            .ok_or_else(|| anyhow::anyhow!("hooks.rhai: after_exec must return a map or nothing"))?;
+        Ok(map.into_iter().map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    /// The server to import, possibly changed, or `None` to skip it
+    fn on_import(&self, server: Server) -> Result<Option<Server>> {
+        if !self.has("on_import") {
+            return Ok(Some(server));
+        }
+        let result = self.call("on_import", (to_dynamic(&server)?,))?;
+        if result.is_unit() {
+            return Ok(Some(server));
+        }
+        if let Ok(keep) = result.as_bool() {
+            return Ok(keep.then_some(server));
+        }
+        if result.is_map() {
+            let changed = rhai::serde::from_dynamic(&result)
+                .map_err(|e| anyhow::anyhow!("hooks.rhai: on_import returned an invalid server: {}", e))?;
+            return Ok(Some(changed));
+        }
+        Err(anyhow::anyhow!("hooks.rhai: on_import must return a server, true, false or nothing"))
+    }
+}
+
+/// The server as a rhai map; unset optional fields read as `()`
+#[cfg(feature = "scripting")]
+fn to_dynamic(server: &Server) -> Result<Dynamic> {
+    let mut map: rhai::Map = rhai::serde::to_dynamic(server)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .cast();
+    // Empty tags are left out of the JSON, but scripts should be able to call tags.contains()
+    map.entry("tags".into()).or_insert_with(|| Dynamic::from_array(Vec::new()));
+    Ok(map.into())
+}
+
+/// hooks.rhai, compiled on first use; `None` when there is no such file
+#[cfg(feature = "scripting")]
+fn hooks() -> Result<Option<&'static Hooks>> {
+    static HOOKS: OnceLock<std::result::Result<Option<Hooks>, String>> = OnceLock::new();
+
+    let loaded = HOOKS.get_or_init(|| {
+        let load = || -> Result<Option<Hooks>> {
+            let path = get_hooks_file_path()?;
+            if !path.exists() {
+                return Ok(None);
+            }
+            let source = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+            Hooks::compile(&source)
+                .map(Some)
+                .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", path.display(), e))
+        };
+        load().map_err(|e| format!("{:#}", e))
+    });
+    loaded.as_ref().map(Option::as_ref).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// Fail early when hooks.rhai exists but doesn't compile
+#[cfg(feature = "scripting")]
+pub fn check() -> Result<()> {
+    hooks().map(|_| ())
+}
+
+/// Run the `before_connect` hook; a refusal or a broken script stops the connection
+#[cfg(feature = "scripting")]
+pub fn before_connect(server: &Server) -> Result<()> {
+    let Some(hooks) = hooks()? else {
+        return Ok(());
+    };
+    match hooks.before_connect(server)? {
+        Some(reason) => Err(anyhow::anyhow!("Connection to {} refused: {}", server.name, reason)),
+        None => Ok(()),
+    }
+}
+
+/// Run the `after_exec` hook; failures only produce a warning since the command already ran
+#[cfg(feature = "scripting")]
+pub fn after_exec(server: &Server, command: &str, exit_code: i32) -> BTreeMap<String, String> {
+    let result = hooks().and_then(|hooks| match hooks {
+        Some(hooks) => hooks.after_exec(server, command, exit_code),
+        None => Ok(BTreeMap::new()),
+    });
+    result.unwrap_or_else(|e| {
+        print_warning(&format!("{:#}", e));
+        BTreeMap::new()
+    })
+}
+
+/// Run the `on_import` hook; `None` means the server should be skipped
+#[cfg(feature = "scripting")]
+pub fn on_import(server: Server) -> Result<Option<Server>> {
+    match hooks()? {
+        Some(hooks) => hooks.on_import(server),
+        None => Ok(Some(server)),
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn check() -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn before_connect(_server: &Server) -> Result<()> {
+    warn_ignored();
+    Ok(())
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn after_exec(_server: &Server, _command: &str, _exit_code: i32) -> BTreeMap<String, String> {
+    warn_ignored();
+    BTreeMap::new()
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn on_import(server: Server) -> Result<Option<Server>> {
+    warn_ignored();
+    Ok(Some(server))
+}
+
+/// Point out, once, that an existing hooks.rhai has no effect in this build
+#[cfg(not(feature = "scripting"))]
+fn warn_ignored() {
+    use std::sync::Once;
+
+    static WARNED: Once = Once::new();
+    WARNED.call_once(|| {
+        if get_hooks_file_path().is_ok_and(|path| path.exists()) {
+            crate::utils::print_warning(
+                "hooks.rhai is ignored: this hop was built without the 'scripting' feature (rebuild with --features scripting)",
+            );
+        }
+    });
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        let mut server = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        server.tags = vec!["prod".to_string()];
+        server
+    }
+
+    #[test]
+    fn test_before_connect_verdicts() {
+        let hooks = Hooks::compile(r#"
+            fn before_connect(server) {
+                if server.tags.contains("prod") { return "prod is frozen"; }
+            }
+        "#).unwrap();
+        assert_eq!(hooks.before_connect(&server()).unwrap().as_deref(), Some("prod is frozen"));
+
+        let hooks = Hooks::compile("fn before_connect(server) { server.name != \"web\" }").unwrap();
+        assert!(hooks.before_connect(&server()).unwrap().is_some());
+
+        let hooks = Hooks::compile("fn before_connect(server) { 42 }").unwrap();
+        assert!(hooks.before_connect(&server()).is_err());
+
+        let hooks = Hooks::compile("fn on_import(server) { true }").unwrap();
+        assert_eq!(hooks.before_connect(&server()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_after_exec_annotations() {
+        let hooks = Hooks::compile(r#"
+            fn after_exec(server, command, exit_code) {
+                #{ ticket: "OPS-" + 42, failed: exit_code != 0 }
+            }
+        "#).unwrap();
+        let annotations = hooks.after_exec(&server(), "uptime", 3).unwrap();
+        assert_eq!(annotations["ticket"], "OPS-42");
+        assert_eq!(annotations["failed"], "true");
+    }
+
+    #[test]
+    fn test_untagged_server_has_tags() {
+        let hooks = Hooks::compile("fn before_connect(server) { !server.tags.contains(\"prod\") }").unwrap();
+        let untagged = Server::new("db".to_string(), "root".to_string(), "10.0.0.2".to_string());
+        assert_eq!(hooks.before_connect(&untagged).unwrap(), None);
+    }
+
+    #[test]
+    fn test_on_import_transforms_and_skips() {
+        let hooks = Hooks::compile(r#"
+            fn on_import(server) {
+                if server.ip.starts_with("192.168.") { return false; }
+                server.tags.push("cmdb");
+                server
+            }
+        "#).unwrap();
+        let imported = hooks.on_import(server()).unwrap().unwrap();
+        assert_eq!(imported.tags, vec!["prod", "cmdb"]);
+
+        let local = Server::new("nas".to_string(), "admin".to_string(), "192.168.1.2".to_string());
+        assert_eq!(hooks.on_import(local).unwrap(), None);
+    }
+
+    #[test]
+    fn test_runaway_hook_is_stopped() {
+        let hooks = Hooks::compile("fn before_connect(server) { loop {} }").unwrap();
+        assert!(hooks.before_connect(&server()).is_err());
+    }
+}
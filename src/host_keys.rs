@@ -0,0 +1,129 @@
+use crate::platform::{known_hosts_path, openssh_program};
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Seconds ssh-keyscan waits for a host before giving up
+const SCAN_TIMEOUT: &str = "5";
+
+/// Fetch the public keys of the host's sshd on `port` with ssh-keyscan
+///
+/// Each key is returned as `<type> <base64>`, without the host name, ready to
+/// be stored with the server.
+pub fn scan(host: &str, port: u16) -> Result<Vec<String>> {
+    let mut command = Command::new(openssh_program("ssh-keyscan"));
+    command.args(["-T", SCAN_TIMEOUT, "-p", &port.to_string(), host]);
+    debug!("argv: {:?}", command);
+    let output = command
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run ssh-keyscan. Please install the OpenSSH client.")?;
+    
+    let keys = parse_scan(&String::from_utf8_lossy(&output.stdout));
+    if keys.is_empty() {
+        return Err(anyhow::anyhow!("ssh-keyscan returned no host keys for {}", host));
+    }
+    Ok(keys)
+}
+
+fn parse_scan(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let (_host, key_type, key) = (parts.next()?, parts.next()?, parts.next()?);
+            Some(format!("{} {}", key_type, key))
+        })
+        .collect()
+}
+
+/// Fingerprint lines for the given keys, as printed by `ssh-keygen -l`
+pub fn fingerprints(host: &str, keys: &[String]) -> Result<Vec<String>> {
+    let mut child = Command::new(openssh_program("ssh-keygen"))
+        .args(["-l", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to run ssh-keygen. Please install the OpenSSH client.")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(known_hosts_lines(host, keys).as_bytes())?;
+    }
+    
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ssh-keygen could not fingerprint the host keys"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+/// Append the keys to the user's ~/.ssh/known_hosts
+pub fn add_to_known_hosts(host: &str, keys: &[String]) -> Result<PathBuf> {
+    let path = known_hosts_path()?;
+    if let Some(parent) = path.parent() {
+        crate::utils::ensure_dir_exists(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(known_hosts_lines(host, keys).as_bytes())?;
+    Ok(path)
+}
+
+/// Whether ~/.ssh/known_hosts has an entry for `host`, hashed or not
+pub fn is_known(host: &str) -> bool {
+    let Ok(path) = known_hosts_path() else {
+        return false;
+    };
+    path.exists() && Command::new(openssh_program("ssh-keygen"))
+        .args(["-F", host, "-f"])
+        .arg(&path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Delete every ~/.ssh/known_hosts entry for `host`
+///
+/// Uses `ssh-keygen -R`, which also matches hashed entries and keeps the
+/// previous file as known_hosts.old.
+pub fn remove_from_known_hosts(host: &str) -> Result<()> {
+    let output = Command::new(openssh_program("ssh-keygen"))
+        .args(["-R", host, "-f"])
+        .arg(known_hosts_path()?)
+        .output()
+        .context("Failed to run ssh-keygen. Please install the OpenSSH client.")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("ssh-keygen -R {} failed: {}", host, stderr.trim()));
+    }
+    Ok(())
+}
+
+fn known_hosts_lines(host: &str, keys: &[String]) -> String {
+    keys.iter().map(|key| format!("{} {}\n", host, key)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scan() {
+        let output = "# 10.0.0.1:22 SSH-2.0-OpenSSH_9.6\n\
+                      10.0.0.1 ssh-ed25519 AAAAC3Nza\n\
+                      10.0.0.1 ecdsa-sha2-nistp256 AAAAE2Vj\n";
+        assert_eq!(parse_scan(output), vec!["ssh-ed25519 AAAAC3Nza", "ecdsa-sha2-nistp256 AAAAE2Vj"]);
+        assert_eq!(
+            known_hosts_lines("10.0.0.1", &parse_scan(output)),
+            "10.0.0.1 ssh-ed25519 AAAAC3Nza\n10.0.0.1 ecdsa-sha2-nistp256 AAAAE2Vj\n"
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use crate::config::Settings;
+use anyhow::{Context, Result};
+use log::debug;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Build an HTTP agent for `url` that honours the proxy and TLS settings
+///
+/// The `proxy` setting wins over HTTPS_PROXY/HTTP_PROXY/ALL_PROXY, which
+/// are ignored for hosts listed in NO_PROXY.
+pub fn agent_for(url: &str, settings: &Settings, timeout: Duration) -> Result<ureq::Agent> {
+    let mut builder = ureq::AgentBuilder::new().timeout(timeout);
+    
+    if let Some(proxy) = proxy_for(url, settings.proxy.as_deref(), |name| std::env::var(name).ok()) {
+        debug!("http: using proxy {} for {}", proxy, url);
+        let proxy = ureq::Proxy::new(&proxy)
+            .map_err(|e| anyhow::anyhow!("Invalid proxy '{}': {}", proxy, e))?;
+        builder = builder.proxy(proxy);
+    }
+    
+    if let Some(config) = tls_config(settings)? {
+        builder = builder.tls_config(Arc::new(config));
+    }
+    
+    Ok(builder.build())
+}
+
+/// Custom TLS configuration, if a CA bundle or client certificate is configured
+///
+/// The bundled web roots stay trusted alongside the certificates in `ca-bundle`.
+fn tls_config(settings: &Settings) -> Result<Option<rustls::ClientConfig>> {
+    let client_auth = match (&settings.client_cert, &settings.client_key) {
+        (None, None) => None,
+        (Some(cert), Some(key)) => Some((cert, key)),
+        _ => return Err(anyhow::anyhow!("Set both client-cert and client-key to use a client certificate")),
+    };
+    if settings.ca_bundle.is_none() && client_auth.is_none() {
+        return Ok(None);
+    }
+    
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    if let Some(ca_bundle) = &settings.ca_bundle {
+        for cert in read_certs(ca_bundle, "CA bundle")? {
+            roots.add(cert).with_context(|| format!("Invalid certificate in CA bundle '{}'", ca_bundle))?;
+        }
+    }
+    
+    let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+    let config = match client_auth {
+        Some((cert, key)) => {
+            let certs = read_certs(cert, "client certificate")?;
+            let key = PrivateKeyDer::from_pem_file(key)
+                .with_context(|| format!("Failed to read client key '{}'", key))?;
+            builder.with_client_auth_cert(certs, key)
+                .context("Invalid client certificate or key")?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Some(config))
+}
+
+/// Read every certificate from a PEM file, failing if there are none
+fn read_certs(path: &str, what: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read {} '{}'", what, path))?;
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("{} '{}' contains no certificates", what, path));
+    }
+    Ok(certs)
+}
+
+/// Pick the proxy for `url`: the explicit setting, else the usual environment variables
+fn proxy_for(url: &str, explicit: Option<&str>, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(proxy) = explicit {
+        return Some(proxy.to_string());
+    }
+    
+    let var = |name: &str| env(name).or_else(|| env(&name.to_lowercase())).filter(|v| !v.is_empty());
+    let host = host_of(url);
+    if let Some(no_proxy) = var("NO_PROXY") {
+        if no_proxy.split(',').map(str::trim).any(|pattern| bypasses(host, pattern)) {
+            return None;
+        }
+    }
+    
+    let scheme_var = if url.starts_with("https://") { "HTTPS_PROXY" } else { "HTTP_PROXY" };
+    var(scheme_var).or_else(|| var("ALL_PROXY"))
+}
+
+/// Host part of an http(s) URL, without credentials or port
+fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    if let Some(bracketed) = host.strip_prefix('[') {
+        return bracketed.split(']').next().unwrap_or(bracketed);
+    }
+    host.split(':').next().unwrap_or(host)
+}
+
+/// Check a host against one NO_PROXY entry (`*`, `example.com` or `.example.com`)
+fn bypasses(host: &str, pattern: &str) -> bool {
+    let pattern = pattern.split(':').next().unwrap_or(pattern);
+    if pattern == "*" {
+        return true;
+    }
+    let domain = pattern.trim_start_matches('.');
+    !domain.is_empty()
+        && (host.eq_ignore_ascii_case(domain)
+            || host.to_ascii_lowercase().ends_with(&format!(".{}", domain.to_ascii_lowercase())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_proxy_for() {
+        let vars = env(&[("HTTPS_PROXY", "http://proxy:3128"), ("no_proxy", "localhost,.internal")]);
+        assert_eq!(proxy_for("https://hooks.example.com/x", None, &vars).as_deref(), Some("http://proxy:3128"));
+        assert_eq!(proxy_for("https://cmdb.internal/x", None, &vars), None);
+        assert_eq!(proxy_for("https://localhost:8080/x", None, &vars), None);
+        assert_eq!(proxy_for("http://hooks.example.com/x", None, &vars), None);
+        assert_eq!(proxy_for("https://cmdb.internal/x", Some("http://explicit:1"), &vars).as_deref(), Some("http://explicit:1"));
+    }
+
+    #[test]
+    fn test_tls_config_requires_cert_and_key() {
+        let mut settings = Settings::default();
+        assert!(tls_config(&settings).unwrap().is_none());
+
+        settings.client_cert = Some("client.pem".to_string());
+        let err = tls_config(&settings).unwrap_err();
+        assert!(err.to_string().contains("client-key"));
+    }
+
+    #[test]
+    fn test_host_of() {
+        assert_eq!(host_of("https://user:pw@Example.com:8443/path?q"), "Example.com");
+        assert_eq!(host_of("http://[::1]:8080/"), "::1");
+        assert_eq!(host_of("http://10.0.0.1"), "10.0.0.1");
+    }
+}
@@ -0,0 +1,233 @@
+use crate::utils::{append_private, ensure_dir_exists, write_private, CalendarTime};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// How much of a job's output is kept in the history
+const OUTPUT_LIMIT: usize = 4096;
+
+/// A command run on a server on a cron schedule by the background watchdog
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub name: String,
+    pub server: String,
+    /// Five-field cron expression, or @hourly/@daily/@weekly/@monthly
+    pub cron: String,
+    pub command: String,
+}
+
+/// All scheduled jobs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Jobs {
+    #[serde(default)]
+    pub jobs: Vec<Job>,
+}
+
+/// The outcome of one job run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRun {
+    pub job: String,
+    pub server: String,
+    /// Unix timestamp (seconds) when the run started
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+    /// The end of the command's output, or the error
+    #[serde(default)]
+    pub output: String,
+}
+
+impl JobRun {
+    /// Keep only the end of long output
+    pub fn set_output(&mut self, output: &str) {
+        let output = output.trim_end();
+        let start = output.len().saturating_sub(OUTPUT_LIMIT);
+        let start = (start..=output.len()).find(|&i| output.is_char_boundary(i)).unwrap_or(output.len());
+        self.output = output[start..].to_string();
+    }
+}
+
+/// A parsed cron schedule; each field is a bit set of the allowed values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day-of-month and day-of-week were both restricted; cron then
+    /// runs when either matches
+    either_day: bool,
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow::anyhow!(
+                "Invalid cron expression '{}': expected 5 fields (minute hour day month weekday)",
+                expression
+            ));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // Both 0 and 7 mean Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Schedule {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+
+    pub fn matches(&self, time: &CalendarTime) -> bool {
+        let has = |set: u64, value: u32| set & (1 << value) != 0;
+        let day = has(self.days, time.day);
+        let weekday = has(self.weekdays, time.weekday);
+        let day_matches = if self.either_day { day || weekday } else { day && weekday };
+        has(self.minutes, time.minute) && has(self.hours, time.hour) && has(self.months, time.month) && day_matches
+    }
+}
+
+/// Parse one cron field (`*`, `5`, `1-5`, `*/15`, `0-30/10`, lists of those)
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || anyhow::anyhow!("Invalid cron field '{}' (allowed values {}-{})", field, min, max);
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&s| s > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    // "5/10" means from 5 to the end in steps of 10
+                    (value, if part.contains('/') { max } else { value })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn hop_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop"))
+}
+
+/// Get the path to the scheduled jobs file
+pub fn get_jobs_file_path() -> Result<PathBuf> {
+    Ok(hop_dir()?.join("jobs.json"))
+}
+
+/// Get the path to the job history (one JSON run per line)
+pub fn get_history_file_path() -> Result<PathBuf> {
+    Ok(hop_dir()?.join("job-history.jsonl"))
+}
+
+pub fn load_jobs() -> Result<Jobs> {
+    let path = get_jobs_file_path()?;
+    if !path.exists() {
+        return Ok(Jobs::default());
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read jobs file: {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse jobs file: {}", path.display()))
+}
+
+pub fn save_jobs(jobs: &Jobs) -> Result<()> {
+    let path = get_jobs_file_path()?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    write_private(&path, &serde_json::to_string_pretty(jobs)?)
+        .with_context(|| format!("Failed to write jobs file: {}", path.display()))
+}
+
+/// Append a run to the job history
+pub fn record_run(run: &JobRun) -> Result<()> {
+    let path = get_history_file_path()?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    let mut file = append_private(&path)
+        .with_context(|| format!("Failed to open job history: {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(run)?)
+        .with_context(|| format!("Failed to write job history: {}", path.display()))
+}
+
+/// Load the job history, oldest first, skipping lines that cannot be parsed
+pub fn load_history() -> Result<Vec<JobRun>> {
+    let path = get_history_file_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read job history: {}", path.display()))?;
+    Ok(contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minute: u32, hour: u32, day: u32, month: u32, weekday: u32) -> CalendarTime {
+        CalendarTime { year: 2024, minute, hour, day, month, weekday }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let nightly = Schedule::parse("0 3 * * *").unwrap();
+        assert!(nightly.matches(&at(0, 3, 14, 6, 2)));
+        assert!(!nightly.matches(&at(1, 3, 14, 6, 2)));
+
+        let business = Schedule::parse("*/15 9-17 * * 1-5").unwrap();
+        assert!(business.matches(&at(45, 17, 1, 1, 5)));
+        assert!(!business.matches(&at(45, 17, 1, 1, 6)));
+        assert!(!business.matches(&at(10, 12, 1, 1, 3)));
+
+        // Day of month and day of week both set: either may match
+        let either = Schedule::parse("0 0 1 * 7").unwrap();
+        assert!(either.matches(&at(0, 0, 1, 3, 4)));
+        assert!(either.matches(&at(0, 0, 9, 3, 0)));
+
+        assert_eq!(Schedule::parse("@daily").unwrap(), Schedule::parse("0 0 * * *").unwrap());
+        assert!(Schedule::parse("0 3 * *").is_err());
+        assert!(Schedule::parse("60 * * * *").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_output_is_truncated() {
+        let mut run = JobRun { job: "j".to_string(), server: "s".to_string(), started_at: 0, duration_ms: 0, exit_code: 0, output: String::new() };
+        run.set_output(&"é".repeat(OUTPUT_LIMIT));
+        assert!(run.output.len() <= OUTPUT_LIMIT);
+        assert!(run.output.ends_with('é'));
+    }
+}
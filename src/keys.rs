@@ -0,0 +1,91 @@
+use crate::platform::openssh_program;
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Key types `hop key gen` can create
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum KeyType {
+    Ed25519,
+    /// Ed25519 key backed by a FIDO2 security key
+    Ed25519Sk,
+    /// ECDSA key backed by a FIDO2 security key, for older tokens
+    EcdsaSk,
+    Rsa,
+}
+
+impl KeyType {
+    /// The name ssh-keygen expects for -t
+    pub fn ssh_name(self) -> &'static str {
+        match self {
+            KeyType::Ed25519 => "ed25519",
+            KeyType::Ed25519Sk => "ed25519-sk",
+            KeyType::EcdsaSk => "ecdsa-sk",
+            KeyType::Rsa => "rsa",
+        }
+    }
+
+    /// Whether the private key lives on a hardware security key
+    pub fn is_security_key(self) -> bool {
+        matches!(self, KeyType::Ed25519Sk | KeyType::EcdsaSk)
+    }
+
+    /// The file ssh looks for by default, e.g. ~/.ssh/id_ed25519_sk
+    pub fn default_file(self, ssh_dir: &Path) -> PathBuf {
+        ssh_dir.join(format!("id_{}", self.ssh_name().replace('-', "_")))
+    }
+}
+
+/// Options for generating a key with ssh-keygen
+#[derive(Debug, Clone)]
+pub struct KeygenOptions {
+    pub key_type: KeyType,
+    pub file: PathBuf,
+    pub comment: Option<String>,
+    /// Store the key handle on the security key so it can be loaded with `ssh-add -K`
+    pub resident: bool,
+    /// Require the security key PIN on every use, not just a touch
+    pub verify_required: bool,
+}
+
+/// Build the ssh-keygen invocation for `options`
+///
+/// The command inherits the terminal so ssh-keygen can ask for a PIN, a
+/// touch and the passphrase itself.
+pub fn keygen_command(options: &KeygenOptions) -> Command {
+    let mut command = Command::new(openssh_program("ssh-keygen"));
+    command.arg("-t").arg(options.key_type.ssh_name()).arg("-f").arg(&options.file);
+    if let Some(comment) = &options.comment {
+        command.arg("-C").arg(comment);
+    }
+    if options.resident {
+        command.arg("-O").arg("resident");
+    }
+    if options.verify_required {
+        command.arg("-O").arg("verify-required");
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keygen_command() {
+        let options = KeygenOptions {
+            key_type: KeyType::Ed25519Sk,
+            file: KeyType::Ed25519Sk.default_file(Path::new("/home/alice/.ssh")),
+            comment: Some("alice@laptop".to_string()),
+            resident: true,
+            verify_required: false,
+        };
+        let command = keygen_command(&options);
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["-t", "ed25519-sk", "-f", "/home/alice/.ssh/id_ed25519_sk", "-C", "alice@laptop", "-O", "resident"]
+        );
+        assert!(KeyType::EcdsaSk.is_security_key());
+        assert!(!KeyType::Ed25519.is_security_key());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::models::{LocalHook, OnFailure, Server, DEFAULT_HOOK_TIMEOUT};
+use crate::signals;
+use crate::utils::{print_info, print_warning};
+use anyhow::Result;
+use log::debug;
+use std::io::Write;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a running hook is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Run a server's local pre_connect/post_disconnect command
+///
+/// Failures are handled as the hook asks: only `abort` returns an error.
+pub fn run(server: &Server, name: &str, hook: &LocalHook) -> Result<()> {
+    print_info(&format!("Running {} for {}: {}", name, server.name, hook.command));
+    let failure = match execute(shell_command(server, hook.command), hook.timeout, None) {
+        Ok(status) if status.success() => return Ok(()),
+        Ok(status) => format!("{} for {} failed ({})", name, server.name, status),
+        Err(e) => format!("{} for {} failed: {:#}", name, server.name, e),
+    };
+
+    match hook.on_failure {
+        OnFailure::Abort => Err(anyhow::anyhow!(failure)),
+        OnFailure::Warn => {
+            print_warning(&failure);
+            Ok(())
+        }
+        OnFailure::Ignore => {
+            debug!("{}", failure);
+            Ok(())
+        }
+    }
+}
+
+/// Run the `change-hook` command after `event` changed `server`
+///
+/// The server's entry is written to the command's stdin as JSON. The change
+/// is already saved, so failures only produce a warning.
+pub fn run_change_hook(command: &str, event: &str, server: &Server) {
+    let result = serde_json::to_string(server).map_err(anyhow::Error::from).and_then(|json| {
+        let mut shell = shell_command(server, command);
+        shell.env("HOP_EVENT", event);
+        execute(shell, DEFAULT_HOOK_TIMEOUT, Some(&json))
+    });
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => print_warning(&format!("change-hook for {} {} failed ({})", event, server.name, status)),
+        Err(e) => print_warning(&format!("change-hook for {} {} failed: {:#}", event, server.name, e)),
+    }
+}
+
+/// `command` run through the local shell, with the server in its environment
+fn shell_command(server: &Server, command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    shell
+        .env("HOP_SERVER", &server.name)
+        .env("HOP_USER", &server.user)
+        .env("HOP_HOST", &server.ip);
+    shell
+}
+
+/// Run a shell command, killing it after `timeout`
+///
+/// Without `input` the command keeps the terminal, so it can ask for a
+/// password (e.g. sudo for a VPN). Only the shell itself is killed on timeout.
+fn execute(mut shell: Command, timeout: Duration, input: Option<&str>) -> Result<ExitStatus> {
+    if input.is_some() {
+        shell.stdin(Stdio::piped());
+    }
+    let mut child = shell.spawn()?;
+    let _tracked = signals::track(&child);
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        // A command that doesn't read its input closes the pipe early; that's fine
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::anyhow!("timed out after {}s", timeout.as_secs()));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hook(command: &str, on_failure: OnFailure) -> LocalHook<'_> {
+        LocalHook { command, timeout: Duration::from_secs(5), on_failure }
+    }
+
+    #[test]
+    fn test_failure_handling() {
+        let server = Server::new("web".to_string(), "deploy".to_string(), "10.0.0.1".to_string());
+        assert!(run(&server, "pre_connect", &hook("test \"$HOP_HOST\" = 10.0.0.1", OnFailure::Abort)).is_ok());
+
+        let err = run(&server, "pre_connect", &hook("exit 3", OnFailure::Abort)).unwrap_err();
+        assert!(err.to_string().contains("pre_connect for web failed"));
+        assert!(run(&server, "post_disconnect", &hook("exit 3", OnFailure::Warn)).is_ok());
+        assert!(run(&server, "post_disconnect", &hook("exit 3", OnFailure::Ignore)).is_ok());
+    }
+
+    #[test]
+    fn test_execute_passes_input() {
+        let server = Server::new("web".to_string(), "deploy".to_string(), "10.0.0.1".to_string());
+        let mut shell = shell_command(&server, "grep -q '\"name\":\"web\"' && test \"$HOP_EVENT\" = add");
+        shell.env("HOP_EVENT", "add");
+        let json = serde_json::to_string(&server).unwrap();
+        assert!(execute(shell, Duration::from_secs(5), Some(&json)).unwrap().success());
+    }
+
+    #[test]
+    fn test_timeout_kills_command() {
+        let server = Server::new("web".to_string(), "deploy".to_string(), "10.0.0.1".to_string());
+        let slow = LocalHook { command: "exec sleep 5", timeout: Duration::from_millis(200), on_failure: OnFailure::Abort };
+        let started = Instant::now();
+        let err = run(&server, "pre_connect", &slow).unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::redact::redact;
+use crate::utils::{append_private, ensure_dir_exists, now_unix};
+use anyhow::{Context, Result};
+use colored::*;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Logger that writes debug details to stderr and, optionally, to a log file
+struct HopLogger {
+    stderr_level: LevelFilter,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for HopLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = redact(&record.args().to_string());
+        if record.level() <= self.stderr_level {
+            let label = match record.level() {
+                Level::Error => "error".red(),
+                Level::Warn => "warn".yellow(),
+                Level::Info => "info".blue(),
+                Level::Debug => "debug".dimmed(),
+                Level::Trace => "trace".dimmed(),
+            };
+            eprintln!("{}: {}", label, message);
+        }
+
+        if let Some(file) = &self.file {
+            let timestamp = now_unix();
+            if let Ok(mut file) = file.lock() {
+                let _ = writeln!(file, "{} {:<5} {}: {}", timestamp, record.level(), record.target(), message);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// Map the number of `-v` flags to a log level for stderr output
+pub fn level_for_verbosity(verbosity: u8) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Get the path to the log file under the config directory
+pub fn get_log_file_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("hop.log"))
+}
+
+/// Install the global logger
+///
+/// Stderr output follows the verbosity level. When `log_to_file` is set, all
+/// records (debug and above, trace with `-vv`) are appended to `hop.log`.
+pub fn init(verbosity: u8, log_to_file: bool) -> Result<()> {
+    let stderr_level = level_for_verbosity(verbosity);
+
+    let file = if log_to_file {
+        let path = get_log_file_path()?;
+        if let Some(parent) = path.parent() {
+            ensure_dir_exists(parent)?;
+        }
+        let file = append_private(&path)
+            .with_context(|| format!("Failed to open log file: {}", path.display()))?;
+        Some(Mutex::new(file))
+    } else {
+        None
+    };
+
+    let max_level = if file.is_some() {
+        stderr_level.max(level_for_verbosity(verbosity.max(1)))
+    } else {
+        stderr_level
+    };
+
+    log::set_boxed_logger(Box::new(HopLogger { stderr_level, file }))
+        .map_err(|e| anyhow::anyhow!("Failed to initialize logger: {}", e))?;
+    log::set_max_level(max_level);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_for_verbosity() {
+        assert_eq!(level_for_verbosity(0), LevelFilter::Off);
+        assert_eq!(level_for_verbosity(1), LevelFilter::Debug);
+        assert_eq!(level_for_verbosity(2), LevelFilter::Trace);
+        assert_eq!(level_for_verbosity(5), LevelFilter::Trace);
+    }
+}
@@ -1,78 +1,301 @@
+mod bitwarden;
+mod audit;
 mod cli;
-mod config;
-mod models;
-mod ssh;
-mod utils;
+mod docs;
+mod drift;
+mod executor;
+mod export;
+mod health;
+mod hooks;
+mod host_keys;
+#[cfg(feature = "webhooks")]
+mod http;
+mod jobs;
+mod keys;
+mod local_hooks;
+mod logging;
+mod secrets;
+mod service;
+mod notify;
+mod onepassword;
+mod plugin;
+mod policy;
+mod report;
+mod rollout;
+mod shell_init;
+mod tail;
+mod stale;
+mod stream;
+mod top;
+mod totp;
+mod tunnel;
+mod watchdog;
+mod vault;
+mod webhook;
 
-use cli::{Cli, Commands};
-use config::{load_config, save_config, get_config_file_path, init_config};
+// Inventory, config and connection logic shared with other tools
+use hop_core::{background, config, error, exit_codes, history, index, models, platform, pool, redact, signals, ssh, utils};
+
+use cli::{Cli, Commands, ConfigAction, GroupAction, ServerOptions, TagEdits, DocsAction, JobAction, KeyAction, PoolAction, ReportAction, SecretAction, TunnelAction, WatchdogAction, ExportFormat, GroupBy, ListSort, QrPayload};
+use error::HopError;
+use exit_codes::server_not_found;
+use config::{load_config, save_config, get_config_file_path, init_config, Resolution, ServerFilter, Settings};
 use models::Server;
-use ssh::SshClient;
+use executor::Executor;
+use rollout::Rollout;
+use ssh::{SessionEnd, SshClient};
 use utils::{
-    print_error, print_success, print_info, print_warning, 
-    is_valid_ip, is_valid_server_name, confirm_action
+    print_error, print_hint, print_success, print_info, print_warning, set_quiet, set_assume_yes, is_quiet,
+    is_valid_server_name, confirm_action, open_in_editor, choose_from, prompt_input,
+    confirm_by_typing, prompt_secret, format_time_ago, format_duration, format_utc_date, now_unix
 };
 
 use anyhow::Result;
+use clap::CommandFactory;
 use colored::*;
+use log::debug;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::process;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{self, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
 fn main() {
-    if let Err(e) = run() {
-        print_error(&format!("{}", e));
-        process::exit(1);
+    let result = run();
+    // Whatever failed after a Ctrl-C is a consequence of it
+    if let Some(signal) = signals::interrupted() {
+        signals::restore_terminal();
+        eprintln!();
+        print_warning("Interrupted.");
+        process::exit(signals::exit_code(signal));
+    }
+    if let Err(e) = result {
+        match exit_codes::error_kind_for(&e) {
+            Some(kind) => {
+                print_error(&format!("{} [{}]", e, kind.id()));
+                print_hint(kind.hint());
+            }
+            None => print_error(&format!("{}", e)),
+        }
+        process::exit(exit_codes::exit_code_for(&e));
     }
 }
 
 fn run() -> Result<()> {
+    signals::install();
+    if let Some(code) = run_plugin()? {
+        process::exit(code);
+    }
     let cli = Cli::new();
     
-    match cli.command {
-        Commands::Add { name, user, ip } => {
-            handle_add(name, user, ip)?;
+    set_quiet(cli.quiet);
+    set_assume_yes(cli.yes);
+    config::set_no_cache(cli.no_cache);
+    if let Ok(config) = load_config() {
+        executor::set_default_parallel(config.settings.parallel);
+        ssh::set_connect_timeout(config.settings.connect_timeout);
+        redact::set_patterns(config.settings.redact_patterns);
+    }
+    logging::init(cli.verbose, cli.log_file)?;
+    
+    let command = match (cli.target, cli.command) {
+        (Some(_), Some(_)) => {
+            return Err(HopError::UsageError("Cannot combine a server shortcut with a subcommand".to_string()).into());
+        }
+        (Some(target), None) => Commands::Connect { identifier: target, test: false, cd: None, reconnect: false },
+        (None, Some(command)) => command,
+        (None, None) => {
+            Cli::command().print_help()?;
+            return Ok(());
+        }
+    };
+    
+    match command {
+        Commands::Add { name, user, ip, options, scan, known_hosts, resolve } => {
+            handle_add(name, user, ip, options, scan, known_hosts, resolve)?;
+        }
+        Commands::List { archive_stale: true, force, .. } => {
+            handle_archive_stale(force)?;
+        }
+        Commands::List { sort, status, group_by, stale, tag, long, .. } => {
+            handle_list(long, sort, status, group_by, stale, ServerFilter::new(tag))?;
+        }
+        Commands::Health { servers, tag, rollout } => {
+            handle_health(servers, ServerFilter::new(tag), rollout.rollout(executor::default_parallel()))?;
+        }
+        Commands::Test { servers, all: _, tag, jobs, tcp_only } => {
+            handle_test(servers, ServerFilter::new(tag), Rollout::parallel(jobs.map_or_else(executor::default_parallel, usize::from)), tcp_only)?;
+        }
+        Commands::Stats { days, server } => {
+            handle_stats(days, server)?;
         }
-        Commands::List { verbose } => {
-            handle_list(verbose)?;
+        Commands::Tail { path, servers, tag, lines, follow } => {
+            handle_tail(path, servers, tag, lines, follow)?;
         }
-        Commands::Connect { identifier, test } => {
-            handle_connect(identifier, test)?;
+        Commands::Top { servers, tag, interval, once } => {
+            handle_top(servers, ServerFilter::new(tag), Duration::from_secs(interval), once)?;
         }
-        Commands::Remove { identifier, force } => {
-            handle_remove(identifier, force)?;
+        Commands::Drift { servers, tag, path, reference } => {
+            handle_drift(servers, tag, path, reference)?;
         }
-        Commands::Edit { identifier, name, user, ip } => {
-            handle_edit(identifier, name, user, ip)?;
+        Commands::Report { action } => {
+            handle_report(action)?;
         }
-        Commands::Config { path, init } => {
+        Commands::Watchdog { action } => {
+            handle_watchdog(action)?;
+        }
+        Commands::Pool { action } => {
+            handle_pool(action)?;
+        }
+        Commands::Tunnel { action } => {
+            handle_tunnel(action)?;
+        }
+        Commands::Job { action } => {
+            handle_job(action)?;
+        }
+        Commands::Connect { identifier, test, cd, reconnect } => {
+            handle_connect(identifier, test, cd, reconnect)?;
+        }
+        Commands::Show { identifier, qr, qr_format } => {
+            handle_show(identifier, qr.then_some(qr_format))?;
+        }
+        Commands::Remove { identifier, tag, force, known_hosts } => {
+            match identifier {
+                Some(identifier) => handle_remove(identifier, force, known_hosts)?,
+                None => handle_bulk_remove(ServerFilter::new(tag), force, known_hosts)?,
+            }
+        }
+        Commands::Edit { identifier, name, user, ip, port, notes, tags, editor, tag, set } => {
+            match identifier {
+                Some(identifier) if editor => handle_edit_in_editor(identifier)?,
+                Some(identifier) if !set.is_empty() => handle_set_fields(identifier, set)?,
+                Some(identifier) => handle_edit(identifier, name, user, ip, port, notes, tags)?,
+                None => handle_bulk_edit(ServerFilter::new(tag), set)?,
+            }
+        }
+        Commands::Lock { identifier, unlock } => {
+            handle_lock(identifier, !unlock)?;
+        }
+        Commands::Group { action } => {
+            handle_group(action)?;
+        }
+        Commands::Config { action: Some(action), .. } => {
+            handle_config_action(action)?;
+        }
+        Commands::Config { action: None, path, init } => {
             handle_config(path, init)?;
         }
+        Commands::Exec { server, command, notify, sudo, override_reason, fail_fast, rollout } => {
+            handle_exec(server, command, notify, sudo, override_reason, fail_fast, rollout.rollout(1))?;
+        }
+        Commands::Service { servers, action, name, lines, sudo } => {
+            handle_service(servers, action, name, lines, sudo)?;
+        }
+        Commands::Copy { server, source, destination, from } => {
+            handle_copy(server, source, destination, from)?;
+        }
+        Commands::Import { file: Some(file), merge, allow_hooks, .. } => {
+            handle_import(file, merge, allow_hooks)?;
+        }
+        Commands::Import { file: None, vault, .. } => {
+            handle_onepassword_import(vault)?;
+        }
+        Commands::Export { file, pretty, format } => {
+            handle_export(file, pretty, format)?;
+        }
+        Commands::Secret { action } => {
+            handle_secret(action)?;
+        }
+        Commands::Key { action } => {
+            handle_key(action)?;
+        }
+        Commands::Man { command } => {
+            handle_man(command)?;
+        }
+        Commands::Docs { action: DocsAction::Generate { dir } } => {
+            handle_docs_generate(&dir)?;
+        }
+        Commands::Recent { count } => {
+            handle_recent(count)?;
+        }
+        Commands::Complete { prefix } => {
+            handle_complete(&prefix)?;
+        }
+        Commands::ShellInit { shell } => {
+            handle_shell_init(shell)?;
+        }
     }
     
     Ok(())
 }
 
-fn handle_add(name: String, user: String, ip: String) -> Result<()> {
+fn handle_add(name: String, user: String, ip: String, options: ServerOptions, scan: bool, known_hosts: bool, resolve: bool) -> Result<()> {
     // Validate inputs
     if !is_valid_server_name(&name) {
         return Err(anyhow::anyhow!("Invalid server name. Use only alphanumeric characters, hyphens, and underscores."));
     }
     
-    if !is_valid_ip(&ip) {
-        return Err(anyhow::anyhow!("Invalid IP address format."));
+    let ip = utils::normalize_host(&ip).ok_or_else(|| models::invalid_host(&ip))?;
+    if resolve {
+        let addresses = utils::resolve_host(&ip)?;
+        let addresses: Vec<String> = addresses.iter().map(ToString::to_string).collect();
+        print_info(&format!("{} resolves to {}", ip, addresses.join(", ")));
     }
     
     let mut config = load_config()?;
-    let server = Server::new(name, user, ip);
+    let mut server = Server::new(name, user, ip);
+    server.port = options.port;
+    for tag in &options.tag {
+        server.add_tag(tag)?;
+    }
+    if let Some(group) = options.group {
+        server.set_field("group", &group)?;
+    }
+    if let Some(description) = options.description {
+        server.set_field("description", &description)?;
+    }
+    if let Some(identity) = options.identity {
+        server.set_field("identity_file", &identity)?;
+        let path = server.identity_file_path().unwrap_or_default();
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("Identity file not found: {}", path.display()));
+        }
+    }
+    server.added_at = Some(now_unix());
+    
+    if scan {
+        // Fail on a duplicate name before asking the user to check fingerprints
+        if config.contains(&server) {
+            return Err(anyhow::anyhow!("Server with name '{}' already exists", server.qualified_name()));
+        }
+        let keys = host_keys::scan(&server.ip, server.ssh_port())?;
+        println!("Host keys for {}:", server.known_hosts_name().bold());
+        for fingerprint in host_keys::fingerprints(&server.known_hosts_name(), &keys)? {
+            println!("  {}", fingerprint);
+        }
+        if !confirm_action("Do these fingerprints match the server?")? {
+            print_info("Operation cancelled.");
+            return Ok(());
+        }
+        if known_hosts {
+            let path = host_keys::add_to_known_hosts(&server.known_hosts_name(), &keys)?;
+            print_info(&format!("Added {} host key(s) to {}", keys.len(), path.display()));
+        }
+        server.host_keys = keys;
+    }
     
     config.add_server(server.clone())?;
     save_config(&config)?;
     
     print_success(&format!("Added server: {}", server));
+    notify_change(&config, "add", &[&server]);
     Ok(())
 }
 
-fn handle_list(verbose: bool) -> Result<()> {
+fn handle_list(long: bool, sort: Option<ListSort>, status: bool, group_by: Option<GroupBy>, stale: bool, filter: ServerFilter) -> Result<()> {
     let config = load_config()?;
     
     if config.is_empty() {
@@ -80,204 +303,3111 @@ fn handle_list(verbose: bool) -> Result<()> {
         return Ok(());
     }
     
+    let usage = history::load_history()
+        .map(|entries| history::usage_by_server(&entries))
+        .unwrap_or_else(|e| {
+            debug!("Failed to load history: {}", e);
+            Default::default()
+        });
+    let usage_of = |server: &Server| usage.get(&server.qualified_name()).copied().unwrap_or_default();
+    
+    let health = if status || stale || sort == Some(ListSort::Latency) {
+        health::load_health().unwrap_or_else(|e| {
+            print_warning(&format!("Failed to load health cache: {}", e));
+            Default::default()
+        })
+    } else {
+        Default::default()
+    };
+    
+    // Keep the configuration index so numbers still work with `hop <n>` after sorting
+    let mut entries: Vec<(usize, &Server)> = config.list_servers().iter().enumerate().filter(|(_, s)| filter.matches(s)).collect();
+    if entries.is_empty() {
+        print_info(&format!("No servers with {}.", filter));
+        return Ok(());
+    }
+    let now = now_unix();
+    let stale_reason = |server: &Server| stale::stale_reason(server, health.get(&server.qualified_name()), usage_of(server), &config.settings, now);
+    if stale {
+        entries.retain(|(_, s)| stale_reason(s).is_some());
+        if entries.is_empty() {
+            print_info("No stale servers.");
+            return Ok(());
+        }
+    }
+    match sort {
+        Some(ListSort::Name) => entries.sort_by(|a, b| a.1.name.cmp(&b.1.name)),
+        Some(ListSort::LastUsed) => entries.sort_by_key(|(_, s)| std::cmp::Reverse(usage_of(s).last_used)),
+        Some(ListSort::Uses) => entries.sort_by_key(|(_, s)| std::cmp::Reverse(usage_of(s).count)),
+        Some(ListSort::Latency) => entries.sort_by_key(|(_, s)| {
+            // Unreachable and unchecked servers go last
+            health.get(&s.qualified_name()).filter(|h| h.reachable).and_then(|h| h.latency_ms()).unwrap_or(u64::MAX)
+        }),
+        None => {}
+    }
+    
     println!("{}", "Configured servers:".bold());
     println!();
     
-    for server in config.list_servers() {
-        if verbose {
-            println!("  {}", server.name.green().bold());
-            println!("    User: {}", server.user);
-            println!("    IP: {}", server.ip);
-            println!("    SSH Command: {}", server.ssh_command().yellow());
+    let width = config.list_servers().len().to_string().len();
+    let display_width = entries.iter().map(|(_, s)| s.to_string().chars().count()).max().unwrap_or(0);
+    let print_row = |index: usize, server: &Server, indent: &str| {
+        let number = format!("{:>width$}.", index + 1, width = width).dimmed();
+        let stats = usage_of(server);
+        let last_used = if stats.count == 0 { "never".to_string() } else { format_time_ago(stats.last_used) };
+        if long {
+            println!("{}  {} {}", indent, number, server.name.green().bold());
+            if let Some(description) = &server.description {
+                println!("{}    Description: {}", indent, description);
+            }
+            println!("{}    User: {}", indent, server.user);
+            println!("{}    IP: {}", indent, server.ip);
+            if let Some(port) = server.port {
+                println!("{}    Port: {}", indent, port);
+            }
+            if let Some(identity) = &server.identity_file {
+                println!("{}    Identity: {}", indent, identity);
+            }
+            println!("{}    SSH Command: {}", indent, server.ssh_command().yellow());
+            if !server.tags.is_empty() {
+                println!("{}    Tags: {}", indent, server.tags.join(", "));
+            }
+            if let Some(group) = &server.group {
+                println!("{}    Group: {}", indent, group);
+            }
+            if let Some(notes) = &server.notes {
+                println!("{}    Notes: {}", indent, notes);
+            }
+            if server.locked {
+                println!("{}    Locked: yes", indent);
+            }
+            println!("{}    Last used: {} ({} uses)", indent, last_used, stats.count);
+            if status {
+                println!("{}    Status: {}", indent, format_health(health.get(&server.qualified_name())));
+            }
+            if let Some(reason) = stale.then(|| stale_reason(server)).flatten() {
+                println!("{}    Stale: {}", indent, format_stale(reason));
+            }
             println!();
         } else {
-            println!("  {}", server);
+            let display = format!("{:<display_width$}", server.to_string(), display_width = display_width);
+            let columns = format!("{:<10} {:>4} uses", last_used, stats.count).dimmed();
+            let mut suffix = if server.locked { " [locked]".dimmed().to_string() } else { String::new() };
+            if let Some(reason) = stale.then(|| stale_reason(server)).flatten() {
+                suffix.push_str(&format!("  {}", format_stale(reason).yellow()));
+            }
+            if status {
+                println!("{}  {} {}  {}  {}{}", indent, number, display, format_health(health.get(&server.qualified_name())), columns, suffix);
+            } else {
+                println!("{}  {} {}  {}{}", indent, number, display, columns, suffix);
+            }
+        }
+    };
+    
+    match group_by {
+        Some(GroupBy::Tag) => {
+            // Servers with several tags are listed under each of them
+            let mut groups: BTreeMap<&str, Vec<(usize, &Server)>> = BTreeMap::new();
+            let mut untagged = Vec::new();
+            for &(index, server) in &entries {
+                if server.tags.is_empty() {
+                    untagged.push((index, server));
+                }
+                for tag in &server.tags {
+                    groups.entry(tag.as_str()).or_default().push((index, server));
+                }
+            }
+            
+            let groups = groups.into_iter().chain((!untagged.is_empty()).then_some(("(untagged)", untagged)));
+            for (heading, members) in groups {
+                println!("  {} {}", heading.cyan().bold(), format!("({})", members.len()).dimmed());
+                for (index, server) in members {
+                    print_row(index, server, "  ");
+                }
+                if !long {
+                    println!();
+                }
+            }
+        }
+        Some(GroupBy::Group) => {
+            // Parents sort before their subgroups, so the tree prints in one pass
+            let mut groups: BTreeMap<Vec<&str>, Vec<(usize, &Server)>> = BTreeMap::new();
+            let mut ungrouped = Vec::new();
+            for &(index, server) in &entries {
+                match &server.group {
+                    Some(group) => groups.entry(group.split('/').collect()).or_default().push((index, server)),
+                    None => ungrouped.push((index, server)),
+                }
+            }
+            
+            let mut shown: &[&str] = &[];
+            for (path, members) in &groups {
+                // Headings for the parts of the path not printed yet
+                let common = shown.iter().zip(path).take_while(|(a, b)| a == b).count();
+                if common == 0 && !shown.is_empty() && !long {
+                    println!();
+                }
+                for depth in common..path.len() {
+                    let prefix = &path[..=depth];
+                    let total: usize = groups.iter().filter(|(p, _)| p.starts_with(prefix)).map(|(_, m)| m.len()).sum();
+                    println!("{}  {} {}", "  ".repeat(depth), path[depth].cyan().bold(), format!("({})", total).dimmed());
+                }
+                shown = path;
+                for &(index, server) in members {
+                    print_row(index, server, &"  ".repeat(path.len()));
+                }
+            }
+            if !ungrouped.is_empty() {
+                if !groups.is_empty() && !long {
+                    println!();
+                }
+                println!("  {} {}", "(ungrouped)".cyan().bold(), format!("({})", ungrouped.len()).dimmed());
+                for (index, server) in ungrouped {
+                    print_row(index, server, "  ");
+                }
+            }
+        }
+        None => {
+            for (index, server) in entries {
+                print_row(index, server, "");
+            }
         }
     }
     
     Ok(())
 }
 
-fn handle_connect(identifier: String, test: bool) -> Result<()> {
-    let config = load_config()?;
-    
-    let server = config.find_server(&identifier)
-        .ok_or_else(|| anyhow::anyhow!("Server '{}' not found", identifier))?;
-    
-    let ssh_client = SshClient::new();
-    
-    if test {
-        ssh_client.test_connection(server)?;
-    } else {
-        ssh_client.connect(server)?;
+/// Describe why `hop list --stale` flagged a server
+fn format_stale(reason: stale::StaleReason) -> String {
+    match reason {
+        stale::StaleReason::Unreachable { since } => format!("unreachable since {}", format_time_ago(since)),
+        stale::StaleReason::Unused { since } => format!("unused since {}", format_time_ago(since)),
     }
-    
-    Ok(())
 }
 
-fn handle_remove(identifier: String, force: bool) -> Result<()> {
+/// Move stale, unlocked servers from the configuration to the archive file
+fn handle_archive_stale(force: bool) -> Result<()> {
     let mut config = load_config()?;
+    let usage = history::usage_by_server(&history::load_history()?);
+    let health = health::load_health()?;
+    let now = now_unix();
     
-    let server = config.find_server(&identifier)
-        .ok_or_else(|| anyhow::anyhow!("Server '{}' not found", identifier))?;
+    let stale: Vec<(&Server, stale::StaleReason)> = config
+        .list_servers()
+        .iter()
+        .filter_map(|server| {
+            let usage = usage.get(&server.qualified_name()).copied().unwrap_or_default();
+            stale::stale_reason(server, health.get(&server.qualified_name()), usage, &config.settings, now).map(|reason| (server, reason))
+        })
+        .collect();
+    warn_locked(&stale.iter().map(|(server, _)| *server).collect::<Vec<_>>());
+    let stale: Vec<_> = stale.into_iter().filter(|(server, _)| !server.locked).collect();
+    
+    if stale.is_empty() {
+        print_info("No stale servers.");
+        return Ok(());
+    }
+    
+    println!("{}", "Servers to archive:".bold());
+    println!();
+    for (server, reason) in &stale {
+        println!("  {}  {}", server, format_stale(*reason).dimmed());
+    }
+    println!();
     
-    if !force && !confirm_action(&format!("Remove server '{}'?", server)) {
+    if !force && !confirm_action(&format!("Archive {} server(s)?", stale.len()))? {
         print_info("Operation cancelled.");
         return Ok(());
     }
     
-    let removed_server = config.remove_server(&identifier)?;
+    let names: Vec<String> = stale.iter().map(|(server, _)| server.qualified_name()).collect();
+    let mut archived = Vec::new();
+    for name in &names {
+        archived.push(config.remove_server(name)?);
+    }
+    // Write the archive first so a failure cannot lose the servers
+    let path = stale::archive(&archived)?;
     save_config(&config)?;
     
-    print_success(&format!("Removed server: {}", removed_server));
+    print_success(&format!("Archived {} server(s) to {}", archived.len(), path.display()));
+    print_hint(&format!("Restore with: hop import --merge {}", path.display()));
+    notify_change(&config, "remove", &archived.iter().collect::<Vec<_>>());
     Ok(())
 }
 
-fn handle_edit(identifier: String, name: Option<String>, user: Option<String>, ip: Option<String>) -> Result<()> {
-    let mut config = load_config()?;
-    
-    let mut changed = false;
-    let updated_server = {
-        let server = config.find_server_mut(&identifier)
-            .ok_or_else(|| anyhow::anyhow!("Server '{}' not found", identifier))?;
-        
-        if let Some(new_name) = name {
-            if !is_valid_server_name(&new_name) {
-                return Err(anyhow::anyhow!("Invalid server name. Use only alphanumeric characters, hyphens, and underscores."));
-            }
-            server.name = new_name;
-            changed = true;
-        }
-        
-        if let Some(new_user) = user {
-            server.user = new_user;
-            changed = true;
-        }
-        
-        if let Some(new_ip) = ip {
-            if !is_valid_ip(&new_ip) {
-                return Err(anyhow::anyhow!("Invalid IP address format."));
-            }
-            server.ip = new_ip;
-            changed = true;
+/// Render a cached health result as a status mark with the age of the check
+fn format_health(status: Option<&health::HealthStatus>) -> String {
+    match status {
+        Some(status) if status.reachable => {
+            let latency = status.latency_ms().map(|ms| format!("{}ms", ms)).unwrap_or_default();
+            format!("{} {:<10} {:>6}", "✓".green(), format_time_ago(status.checked_at), latency)
         }
-        
-        server.clone()
-    };
-    
-    if !changed {
-        print_warning("No changes specified. Use --name, --user, or --ip to edit the server.");
-        return Ok(());
+        Some(status) => format!("{} {:<10} {:>6}", "✗".red(), format_time_ago(status.checked_at), ""),
+        None => format!("{} {:<10} {:>6}", "?".yellow(), "unchecked", ""),
+    }
+}
+
+/// Servers named on the command line, or every server matching `filter`
+fn servers_for_check<'a>(config: &'a config::Config, names: &[String], filter: &ServerFilter) -> Result<Vec<&'a Server>> {
+    if names.is_empty() {
+        Ok(config.filter_servers(filter))
+    } else {
+        names.iter().map(|name| select_server(config, name)).collect()
     }
+}
+
+/// Resolve the ssh client (and credentials) for each server up front
+fn clients_for(settings: &Settings, servers: &[&Server]) -> Result<Vec<SshClient>> {
+    servers.iter().map(|server| ssh_client_for(settings, server)).collect()
+}
+
+/// Run a read-only command on every server in parallel and capture its output
+fn capture_all(servers: &[&Server], clients: &[SshClient], command: &str) -> Vec<Result<String>> {
+    Executor::parallel(executor::default_parallel())
+        .with_progress("Querying")
+        .run_all(servers, |index, server| clients[index].capture(server, command))
+}
+
+/// Probe servers in parallel, recording each result in the health cache
+fn probe_servers(servers: &[&Server], rollout: &Rollout, tcp_only: bool) -> Result<Vec<health::HealthStatus>> {
+    let ssh_client = SshClient::new();
+    let statuses = Executor::new(*rollout)
+        .with_progress("Probing")
+        .run_all(servers, |_, server| probe_server(&ssh_client, server, tcp_only));
     
-    save_config(&config)?;
-    print_success(&format!("Updated server: {}", updated_server));
-    Ok(())
+    let mut cache = health::load_health().unwrap_or_default();
+    for (server, status) in servers.iter().zip(&statuses) {
+        cache.record(&server.qualified_name(), status.clone());
+    }
+    health::save_health(&cache)?;
+    Ok(statuses)
 }
 
-fn handle_config(path: bool, init: bool) -> Result<()> {
-    if init {
-        init_config()?;
-        print_success("Configuration initialized successfully.");
+/// Check that the SSH port answers, then (unless `tcp_only`) time a non-interactive login
+fn probe_server(ssh_client: &SshClient, server: &Server, tcp_only: bool) -> health::HealthStatus {
+    let tcp = health::tcp_latency(&server.ip, server.ssh_port());
+    let started = Instant::now();
+    let result = match &tcp {
+        Ok(_) if tcp_only => Ok(String::new()),
+        Ok(_) => ssh_client.probe(server, health::REBOOT_CHECK),
+        Err(e) => Err(anyhow::anyhow!("{:#}", e)),
+    };
+    health::HealthStatus {
+        reachable: result.is_ok(),
+        checked_at: now_unix(),
+        tcp_ms: tcp.ok().map(|d| d.as_millis() as u64),
+        ssh_ms: (result.is_ok() && !tcp_only).then(|| started.elapsed().as_millis() as u64),
+        reboot_required: result.as_deref().ok().and_then(health::parse_reboot),
+        error: result.err().map(|e| e.to_string()),
+        unreachable_since: None,
     }
+}
+
+/// Reboot marker for `hop health` and `hop test`
+fn format_reboot(status: &health::HealthStatus) -> ColoredString {
+    match status.reboot_required {
+        Some(true) => "reboot required".yellow().bold(),
+        _ => "".normal(),
+    }
+}
+
+/// Latency column for `hop health` and `hop test`
+fn format_latency(status: &health::HealthStatus) -> String {
+    match (status.tcp_ms, status.ssh_ms) {
+        (Some(tcp), Some(ssh)) => format!("tcp {}ms, ssh {}ms", tcp, ssh),
+        (Some(tcp), None) => format!("tcp {}ms", tcp),
+        _ => String::new(),
+    }
+}
+
+fn handle_health(names: Vec<String>, filter: ServerFilter, rollout: Rollout) -> Result<()> {
+    let config = load_config()?;
+    let servers = servers_for_check(&config, &names, &filter)?;
     
-    if path {
-        let config_path = get_config_file_path()?;
-        println!("Configuration file: {}", config_path.display());
+    if servers.is_empty() {
+        print_info("No servers to check.");
+        return Ok(());
     }
     
-    if !path && !init {
-        let config_path = get_config_file_path()?;
-        println!("Configuration file: {}", config_path.display());
-        
-        if config_path.exists() {
-            let config = load_config()?;
-            println!("Servers configured: {}", config.list_servers().len());
-        } else {
-            println!("Configuration file does not exist. Use --init to create it.");
+    let statuses = probe_servers(&servers, &rollout, false)?;
+    let mut unreachable = 0;
+    for (server, status) in servers.iter().zip(&statuses) {
+        match &status.error {
+            None => println!("  {} {}  {}  {}", "✓".green(), server, format_latency(status).dimmed(), format_reboot(status)),
+            Some(error) => {
+                unreachable += 1;
+                println!("  {} {}  {}", "✗".red(), server, error.dimmed());
+            }
         }
     }
     
+    if unreachable > 0 {
+        return Err(HopError::SshFailure { code: None, message: format!("{} server(s) unreachable.", unreachable) }.into());
+    }
+    
+    print_success("All servers reachable.");
+    let reboots: Vec<&str> = servers
+        .iter()
+        .zip(&statuses)
+        .filter(|(_, status)| status.reboot_required == Some(true))
+        .map(|(server, _)| server.name.as_str())
+        .collect();
+    if !reboots.is_empty() {
+        print_warning(&format!("Waiting for a reboot: {}", reboots.join(", ")));
+    }
     Ok(())
 }
 
-fn handle_copy(server_id: String, source: String, destination: String, from: bool) -> Result<()> {
+fn handle_test(names: Vec<String>, filter: ServerFilter, rollout: Rollout, tcp_only: bool) -> Result<()> {
     let config = load_config()?;
+    let servers = servers_for_check(&config, &names, &filter)?;
     
-    let server = config.find_server(&server_id)
-        .ok_or_else(|| anyhow::anyhow!("Server '{}' not found", server_id))?;
+    if servers.is_empty() {
+        print_info(&format!("No servers with {}.", filter));
+        return Ok(());
+    }
     
-    let ssh_client = SshClient::new();
+    print_info(&format!("Testing {} server(s), {} at a time...", servers.len(), rollout.parallel.min(servers.len())));
+    let started = Instant::now();
+    let statuses = probe_servers(&servers, &rollout, tcp_only)?;
     
-    if from {
-        ssh_client.copy_file_from(server, &source, &destination)?;
-    } else {
-        ssh_client.copy_file(server, &source, &destination)?;
+    let name_width = servers.iter().map(|s| s.name.chars().count()).max().unwrap_or(0).max("SERVER".len());
+    let host_width = servers.iter().map(|s| s.ip.chars().count()).max().unwrap_or(0).max("HOST".len());
+    println!("{:<name_width$}  {:<host_width$}  {:<11}  {:<22}  {}", "SERVER".bold(), "HOST".bold(), "STATUS".bold(), "LATENCY".bold(), "DETAIL".bold());
+    for (server, status) in servers.iter().zip(&statuses) {
+        let label = if status.reachable { format!("{:<11}", "reachable").green() } else { format!("{:<11}", "unreachable").red() };
+        println!(
+            "{:<name_width$}  {:<host_width$}  {}  {:<22}  {}",
+            server.name,
+            server.ip,
+            label,
+            format_latency(status),
+            match &status.error {
+                Some(error) => error.dimmed(),
+                None => format_reboot(status),
+            }
+        );
     }
     
+    let unreachable = statuses.iter().filter(|s| !s.reachable).count();
+    println!();
+    println!(
+        "{} reachable, {} unreachable in {}",
+        statuses.len() - unreachable,
+        unreachable,
+        format_duration(started.elapsed().as_secs())
+    );
+    
+    if unreachable > 0 {
+        return Err(HopError::SshFailure { code: None, message: format!("{} server(s) unreachable.", unreachable) }.into());
+    }
     Ok(())
 }
 
-fn handle_exec(server_id: String, command: String) -> Result<()> {
+fn handle_tail(path: String, names: Vec<String>, tag: Vec<String>, lines: u32, follow: bool) -> Result<()> {
     let config = load_config()?;
+    let Some((servers, clients)) = report_targets(&config, &names, tag)? else {
+        return Ok(());
+    };
+    let width = servers.iter().map(|s| s.name.chars().count()).max().unwrap_or(0);
     
-    let server = config.find_server(&server_id)
-        .ok_or_else(|| anyhow::anyhow!("Server '{}' not found", server_id))?;
-    
-    let ssh_client = SshClient::new();
-    let output = ssh_client.execute_command(server, &command)?;
+    // Every server streams at once, since `--follow` never finishes
+    let results = Executor::parallel(servers.len()).run_all(&servers, |index, server| {
+        let prefix = utils::server_prefix(&server.name, index, width);
+        let result = stream_tail(server, &clients[index], &path, lines, follow, &prefix);
+        if let Err(e) = &result {
+            eprintln!("{} {}", prefix, format!("{:#}", e).red());
+        }
+        result
+    });
     
-    print!("{}", output);
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    if failed > 0 {
+        return Err(HopError::SshFailure { code: None, message: format!("Could not read {} from {} server(s).", path, failed) }.into());
+    }
     Ok(())
 }
 
-fn handle_import(file: String, merge: bool) -> Result<()> {
-    let content = fs::read_to_string(&file)
-        .map_err(|e| anyhow::anyhow!("Failed to read file '{}': {}", file, e))?;
-    
-    let imported_servers: Vec<Server> = serde_json::from_str(&content)
-        .map_err(|e| anyhow::anyhow!("Failed to parse JSON: {}", e))?;
-    
-    let mut config = if merge {
-        load_config()?
-    } else {
-        config::Config::new()
+/// Print a remote `tail` line by line with `prefix`; with `follow`, start it
+/// again after the connection drops, without repeating earlier lines
+fn stream_tail(server: &Server, client: &SshClient, path: &str, lines: u32, follow: bool, prefix: &ColoredString) -> Result<()> {
+    let mut lines = lines;
+    let mut attempt = 0;
+    loop {
+        let mut command = client.stream_command(server, &tail::tail_command(path, lines, follow));
+        command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        let started = Instant::now();
+        let mut child = command.spawn().map_err(|e| exit_codes::spawn_failure(&command.get_program().to_string_lossy(), e))?;
+        let _tracked = signals::track(&child);
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        
+        // tail -F reports a missing or rotated file on stderr and keeps going
+        let errors = thread::spawn({
+            let prefix = prefix.clone();
+            move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    eprintln!("{} {}", prefix, line.dimmed());
+                }
+            }
+        });
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            println!("{} {}", prefix, line);
+        }
+        let status = child.wait()?;
+        let _ = errors.join();
+        
+        if !follow || signals::interrupted().is_some() {
+            return if status.success() { Ok(()) } else { Err(anyhow::anyhow!("ssh exited ({})", status)) };
+        }
+        
+        if started.elapsed() >= tunnel::STABLE_AFTER {
+            attempt = 0;
+        }
+        let wait = tunnel::backoff(attempt);
+        attempt += 1;
+        lines = 0;
+        eprintln!("{} {}", prefix, format!("connection closed ({}), reconnecting in {}s", status, wait.as_secs()).yellow());
+        if !signals::sleep(wait) {
+            return Ok(());
+        }
+    }
+}
+
+fn handle_drift(names: Vec<String>, tag: Vec<String>, path: String, reference: Option<String>) -> Result<()> {
+    let config = load_config()?;
+    let Some((mut servers, mut clients)) = report_targets(&config, &names, tag)? else {
+        return Ok(());
+    };
+    // The reference may come from outside the group, e.g. a known-good host
+    let reference = match reference {
+        Some(name) => {
+            let server = select_server(&config, &name)?;
+            Some(match servers.iter().position(|s| std::ptr::eq(*s, server)) {
+                Some(index) => index,
+                None => {
+                    clients.push(ssh_client_for(&config.settings, server)?);
+                    servers.push(server);
+                    servers.len() - 1
+                }
+            })
+        }
+        None => None,
     };
     
-    let mut added_count = 0;
-    let mut skipped_count = 0;
+    let results: Vec<Result<Option<String>>> = capture_all(&servers, &clients, &drift::hash_command(&path))
+        .into_iter()
+        .map(|output| output.and_then(|output| drift::parse_hash(&output)))
+        .collect();
+    let states: Vec<Option<&str>> = results.iter().filter_map(|r| r.as_ref().ok()).map(Option::as_deref).collect();
+    let majority = drift::majority(&states);
     
-    for server in imported_servers {
-        match config.add_server(server.clone()) {
-            Ok(_) => {
-                added_count += 1;
-                print_success(&format!("Imported: {}", server));
+    let name_width = servers.iter().map(|s| s.name.chars().count()).max().unwrap_or(0).max("SERVER".len());
+    println!("{:<name_width$}  {:<12}  {}", "SERVER".bold(), "SHA-256".bold(), "STATUS".bold());
+    let mut unreachable = 0;
+    let mut differing = 0;
+    for (server, result) in servers.iter().zip(&results) {
+        let (hash, status) = match result {
+            Err(e) => {
+                unreachable += 1;
+                ("".to_string(), e.to_string().lines().next().unwrap_or_default().red())
             }
-            Err(_) => {
-                skipped_count += 1;
-                print_warning(&format!("Skipped (already exists): {}", server));
+            Ok(state) => {
+                let hash = state.as_deref().map_or("missing".to_string(), |hash| hash[..12].to_string());
+                let status = match majority {
+                    Some(common) if common == state.as_deref() => "same as majority".green(),
+                    Some(_) => {
+                        differing += 1;
+                        "differs".red().bold()
+                    }
+                    None => "no majority".yellow(),
+                };
+                (hash, status)
             }
-        }
+        };
+        println!("{:<name_width$}  {:<12}  {}", server.name, hash, status);
     }
     
-    save_config(&config)?;
+    if let Some(reference) = reference {
+        println!();
+        if let Err(e) = show_drift_diffs(&servers, &clients, &results, reference, &path) {
+            print_warning(&format!("{:#}", e));
+        }
+    }
     
-    print_success(&format!("Import complete. Added: {}, Skipped: {}", added_count, skipped_count));
+    println!();
+    if unreachable > 0 {
+        return Err(HopError::SshFailure { code: None, message: format!("{} server(s) unreachable.", unreachable) }.into());
+    }
+    if majority.is_none() && states.len() > 1 {
+        return Err(HopError::Failed(format!("No version of {} is shared by most servers.", path)).into());
+    }
+    if differing > 0 {
+        return Err(HopError::Failed(format!("{} server(s) differ from the majority.", differing)).into());
+    }
+    print_success(&format!("{} is the same on all {} server(s).", path, states.len()));
     Ok(())
 }
 
-fn handle_export(file: String, pretty: bool) -> Result<()> {
-    let config = load_config()?;
-    
-    let json = if pretty {
-        serde_json::to_string_pretty(&config.list_servers())?
-    } else {
-        serde_json::to_string(&config.list_servers())?
+/// Print a unified diff against the reference server for every server whose file differs from it
+///
+/// The reference's copy is piped to `diff` on the other server, so only diff is needed remotely.
+fn show_drift_diffs(servers: &[&Server], clients: &[SshClient], results: &[Result<Option<String>>], reference: usize, path: &str) -> Result<()> {
+    let reference_server = servers[reference];
+    let reference_state = match &results[reference] {
+        Ok(Some(hash)) => hash,
+        Ok(None) => return Err(anyhow::anyhow!("{} does not exist on the reference server {}", path, reference_server.name)),
+        Err(_) => return Err(anyhow::anyhow!("No diff: the reference server {} is unreachable", reference_server.name)),
     };
+    let contents = clients[reference].capture(reference_server, &drift::read_command(path))?;
     
-    fs::write(&file, json)
-        .map_err(|e| anyhow::anyhow!("Failed to write file '{}': {}", file, e))?;
-    
-    print_success(&format!("Exported {} servers to '{}'", config.list_servers().len(), file));
+    for (index, (server, result)) in servers.iter().zip(results).enumerate() {
+        match result {
+            Ok(Some(hash)) if hash != reference_state => {
+                let command = drift::diff_command(path, &reference_server.name, &server.name);
+                match clients[index].capture_with_input(server, &command, &contents) {
+                    Ok(diff) => {
+                        for line in diff.lines() {
+                            if line.starts_with("+++") || line.starts_with("---") {
+                                println!("{}", line.bold());
+                            } else if line.starts_with('+') {
+                                println!("{}", line.green());
+                            } else if line.starts_with('-') {
+                                println!("{}", line.red());
+                            } else if line.starts_with("@@") {
+                                println!("{}", line.cyan());
+                            } else {
+                                println!("{}", line);
+                            }
+                        }
+                    }
+                    Err(e) => print_warning(&format!("{}: cannot diff: {}", server.name, e.to_string().lines().next().unwrap_or_default())),
+                }
+            }
+            Ok(None) => print_warning(&format!("{}: {} does not exist", server.name, path)),
+            _ => {}
+        }
+    }
     Ok(())
-} 
\ No newline at end of file
+}
+
+fn handle_report(action: ReportAction) -> Result<()> {
+    match action {
+        ReportAction::Disk { servers, tag, threshold, over } => {
+            let config = load_config()?;
+            let Some((servers, clients)) = report_targets(&config, &servers, tag)? else {
+                return Ok(());
+            };
+            let results: Vec<Result<Vec<report::Filesystem>>> = capture_all(&servers, &clients, report::DISK_COMMAND)
+                .into_iter()
+                .map(|output| output.and_then(|output| report::parse_df(&output)))
+                .collect();
+            
+            let mut rows: Vec<(&Server, &report::Filesystem)> = servers
+                .iter()
+                .copied()
+                .zip(&results)
+                .filter_map(|(server, result)| result.as_ref().ok().map(|filesystems| (server, filesystems)))
+                .flat_map(|(server, filesystems)| filesystems.iter().map(move |fs| (server, fs)))
+                .collect();
+            // Fullest filesystems first
+            rows.sort_by(|a, b| b.1.percent.cmp(&a.1.percent).then_with(|| a.0.name.cmp(&b.0.name)));
+            let flagged = rows.iter().filter(|(_, fs)| fs.percent >= threshold).count();
+            if over {
+                rows.retain(|(_, fs)| fs.percent >= threshold);
+            }
+            
+            if !rows.is_empty() {
+                let name_width = rows.iter().map(|(s, _)| s.name.chars().count()).max().unwrap_or(0).max("SERVER".len());
+                let mount_width = rows.iter().map(|(_, fs)| fs.mount.chars().count()).max().unwrap_or(0).max("MOUNT".len());
+                println!(
+                    "{:<name_width$}  {:<mount_width$}  {:>5}  {:>8}  {:>8}  {:>8}",
+                    "SERVER".bold(), "MOUNT".bold(), "USE%", "USED", "FREE", "SIZE"
+                );
+                for (server, fs) in &rows {
+                    let percent = format!("{:>4}%", fs.percent);
+                    let percent = if fs.percent >= threshold { percent.red().bold() } else { percent.normal() };
+                    println!(
+                        "{:<name_width$}  {:<mount_width$}  {}  {:>8}  {:>8}  {:>8}",
+                        server.name,
+                        fs.mount,
+                        percent,
+                        top::format_bytes(fs.used_kb * 1024),
+                        top::format_bytes(fs.available_kb * 1024),
+                        top::format_bytes(fs.size_kb * 1024),
+                    );
+                }
+                println!();
+            }
+            
+            let mut unreachable = 0;
+            for (server, result) in servers.iter().zip(&results) {
+                if let Err(e) = result {
+                    unreachable += 1;
+                    let reason = e.to_string();
+                    print_warning(&format!("{}: {}", server.name, reason.lines().next().unwrap_or_default()));
+                }
+            }
+            
+            let reporting: Vec<&report::Filesystem> = results.iter().flatten().flatten().collect();
+            let used: u64 = reporting.iter().map(|fs| fs.used_kb).sum();
+            let size: u64 = reporting.iter().map(|fs| fs.size_kb).sum();
+            println!(
+                "{} filesystem(s) on {} server(s): {} of {} used",
+                reporting.len(),
+                servers.len() - unreachable,
+                top::format_bytes(used * 1024),
+                top::format_bytes(size * 1024)
+            );
+            if flagged > 0 {
+                print_warning(&format!("{} filesystem(s) at or above {}%.", flagged, threshold));
+            } else if unreachable == 0 {
+                print_success(&format!("No filesystem at or above {}%.", threshold));
+            }
+            
+            if unreachable > 0 {
+                return Err(HopError::SshFailure { code: None, message: format!("{} server(s) unreachable.", unreachable) }.into());
+            }
+        }
+        ReportAction::Certs { servers, tag, port, path, days } => {
+            let mut targets: Vec<report::CertTarget> = port.into_iter().map(report::CertTarget::Port).collect();
+            targets.extend(path.into_iter().map(report::CertTarget::File));
+            if targets.is_empty() {
+                targets.push(report::CertTarget::Port(443));
+            }
+            
+            let config = load_config()?;
+            let Some((servers, clients)) = report_targets(&config, &servers, tag)? else {
+                return Ok(());
+            };
+            let results = Executor::parallel(executor::default_parallel())
+                .with_progress("Checking certificates")
+                .run_all(&servers, |index, server| {
+                    clients[index].capture(server, &report::certs_command(&targets, &server.ip)).map(|output| report::parse_certs(&output))
+                });
+            
+            let now = now_unix();
+            let mut rows: Vec<(&Server, String, Result<report::CertInfo>)> = Vec::new();
+            let mut unreachable = 0;
+            for (server, result) in servers.iter().copied().zip(results) {
+                match result {
+                    Ok(certs) => rows.extend(certs.into_iter().map(|(target, cert)| (server, target, cert))),
+                    Err(e) => {
+                        unreachable += 1;
+                        let reason = e.to_string();
+                        print_warning(&format!("{}: {}", server.name, reason.lines().next().unwrap_or_default()));
+                    }
+                }
+            }
+            // Soonest expiry first, missing certificates last
+            rows.sort_by_key(|(server, target, cert)| {
+                (cert.as_ref().map_or(u64::MAX, |c| c.expires), server.name.clone(), target.clone())
+            });
+            
+            if !rows.is_empty() {
+                let name_width = rows.iter().map(|(s, _, _)| s.name.chars().count()).max().unwrap_or(0).max("SERVER".len());
+                let target_width = rows.iter().map(|(_, t, _)| t.chars().count()).max().unwrap_or(0).max("TARGET".len());
+                println!(
+                    "{:<name_width$}  {:<target_width$}  {:<10}  {:>6}  {}",
+                    "SERVER".bold(), "TARGET".bold(), "EXPIRES".bold(), "DAYS", "SUBJECT".bold()
+                );
+                for (server, target, cert) in &rows {
+                    match cert {
+                        Ok(cert) => {
+                            let left = (cert.expires as i64 - now as i64).div_euclid(86_400);
+                            let label = format!("{:>6}", left);
+                            let label = if left < 0 {
+                                label.red().bold()
+                            } else if (left as u64) < days {
+                                label.yellow().bold()
+                            } else {
+                                label.green()
+                            };
+                            println!(
+                                "{:<name_width$}  {:<target_width$}  {}  {}  {}",
+                                server.name,
+                                target,
+                                format_utc_date(cert.expires),
+                                label,
+                                cert.subject.dimmed()
+                            );
+                        }
+                        Err(e) => println!("{:<name_width$}  {:<target_width$}  {}", server.name, target, e.to_string().red()),
+                    }
+                }
+                println!();
+            }
+            
+            let expired = rows.iter().filter(|(_, _, c)| c.as_ref().is_ok_and(|c| c.expires <= now)).count();
+            let expiring = rows
+                .iter()
+                .filter(|(_, _, c)| c.as_ref().is_ok_and(|c| c.expires > now && c.expires - now < days * 86_400))
+                .count();
+            if expired > 0 {
+                print_warning(&format!("{} certificate(s) have expired.", expired));
+            }
+            if expiring > 0 {
+                print_warning(&format!("{} certificate(s) expire within {} days.", expiring, days));
+            }
+            if expired == 0 && expiring == 0 && unreachable == 0 && rows.iter().all(|(_, _, c)| c.is_ok()) {
+                print_success(&format!("No certificate expires within {} days.", days));
+            }
+            
+            if unreachable > 0 {
+                return Err(HopError::SshFailure { code: None, message: format!("{} server(s) unreachable.", unreachable) }.into());
+            }
+        }
+        ReportAction::Updates { servers, tag } => {
+            let config = load_config()?;
+            let Some((servers, clients)) = report_targets(&config, &servers, tag)? else {
+                return Ok(());
+            };
+            let results: Vec<Result<report::Updates>> = capture_all(&servers, &clients, &report::updates_command())
+                .into_iter()
+                .map(|output| output.and_then(|output| report::parse_updates(&output)))
+                .collect();
+            
+            // Most security updates first, then most updates; failures last
+            let mut rows: Vec<(&Server, &Result<report::Updates>)> = servers.iter().copied().zip(&results).collect();
+            rows.sort_by_key(|(server, result)| match result {
+                Ok(updates) => (0, std::cmp::Reverse(updates.security.unwrap_or(0)), std::cmp::Reverse(updates.total), server.name.clone()),
+                Err(_) => (1, std::cmp::Reverse(0), std::cmp::Reverse(0), server.name.clone()),
+            });
+            
+            let width = servers.iter().map(|s| s.name.chars().count()).max().unwrap_or(0).max("SERVER".len());
+            println!("{:<width$}  {:<7}  {:>7}  {:>8}  {}", "SERVER".bold(), "MANAGER".bold(), "UPDATES", "SECURITY", "REBOOT".bold());
+            for (server, result) in &rows {
+                match result {
+                    Ok(updates) => {
+                        let security = match updates.security {
+                            Some(0) => format!("{:>8}", 0).green(),
+                            Some(count) => format!("{:>8}", count).red().bold(),
+                            None => format!("{:>8}", "-").dimmed(),
+                        };
+                        let reboot = match updates.reboot_required {
+                            Some(true) => "required".yellow().bold(),
+                            Some(false) => "no".normal(),
+                            None => "-".dimmed(),
+                        };
+                        println!("{:<width$}  {:<7}  {:>7}  {}  {}", server.name, updates.manager, updates.total, security, reboot);
+                    }
+                    Err(e) => {
+                        let reason = e.to_string();
+                        println!("{:<width$}  {}", server.name, reason.lines().next().unwrap_or_default().red());
+                    }
+                }
+            }
+            
+            let checked: Vec<&report::Updates> = results.iter().flatten().collect();
+            let total: u32 = checked.iter().map(|u| u.total).sum();
+            let security: u32 = checked.iter().filter_map(|u| u.security).sum();
+            println!();
+            println!(
+                "{} update(s), {} security, on {} of {} server(s) checked",
+                total,
+                security,
+                checked.len(),
+                servers.len()
+            );
+            let reboots: Vec<&str> = servers
+                .iter()
+                .zip(&results)
+                .filter(|(_, result)| result.as_ref().is_ok_and(|u| u.reboot_required == Some(true)))
+                .map(|(server, _)| server.name.as_str())
+                .collect();
+            if !reboots.is_empty() {
+                print_warning(&format!("Waiting for a reboot: {}", reboots.join(", ")));
+            }
+            
+            let failed = servers.len() - checked.len();
+            if failed > 0 {
+                return Err(HopError::Failed(format!("Could not check {} server(s).", failed)).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The servers a report covers and their ssh clients, or None when nothing matches
+fn report_targets<'a>(config: &'a config::Config, names: &[String], tag: Vec<String>) -> Result<Option<(Vec<&'a Server>, Vec<SshClient>)>> {
+    let filter = ServerFilter::new(tag);
+    let servers = servers_for_check(config, names, &filter)?;
+    if servers.is_empty() {
+        print_info(&format!("No servers with {}.", filter));
+        return Ok(None);
+    }
+    let clients = clients_for(&config.settings, &servers)?;
+    Ok(Some((servers, clients)))
+}
+
+fn handle_watchdog(action: WatchdogAction) -> Result<()> {
+    match action {
+        WatchdogAction::Start { servers, tag, interval, ssh, foreground } => {
+            if foreground {
+                return run_watchdog(&servers, &ServerFilter::new(tag), Duration::from_secs(interval), ssh);
+            }
+            if let Some(pid) = watchdog::running_pid()? {
+                return Err(anyhow::anyhow!("The watchdog is already running (pid {}). Stop it with 'hop watchdog stop'.", pid));
+            }
+            
+            let mut args = vec!["watchdog".to_string(), "start".to_string(), "--foreground".to_string()];
+            args.extend(["--interval".to_string(), interval.to_string()]);
+            if ssh {
+                args.push("--ssh".to_string());
+            }
+            for tag in tag {
+                args.extend(["--tag".to_string(), tag]);
+            }
+            args.extend(servers);
+            
+            let pid = watchdog::spawn_background(&args)?;
+            print_success(&format!("Watchdog started (pid {}), checking every {}s.", pid, interval));
+            print_info(&format!("Output goes to {}", watchdog::get_log_file_path()?.display()));
+        }
+        WatchdogAction::Stop => {
+            if watchdog::stop()? {
+                print_success("Watchdog stopped.");
+            } else {
+                print_info("The watchdog is not running.");
+            }
+        }
+        WatchdogAction::Status => {
+            match watchdog::running_pid()? {
+                Some(pid) => println!("Watchdog running (pid {})", pid),
+                None => println!("Watchdog not running"),
+            }
+            for (server, reachable) in watchdog::load_state()?.servers {
+                let mark = if reachable { "✓".green() } else { "✗".red() };
+                println!("  {} {}", mark, server);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Probe the selected servers every `interval`, announcing each change
+///
+/// Scheduled jobs are checked once a minute in between rounds.
+fn run_watchdog(names: &[String], filter: &ServerFilter, interval: Duration, ssh: bool) -> Result<()> {
+    let mut state = watchdog::load_state()?;
+    let mut next_probe = Instant::now();
+    let mut last_minute = now_unix() / 60;
+    while signals::interrupted().is_none() {
+        let minute = now_unix() / 60;
+        if minute > last_minute {
+            // After a suspend, only catch up on the last few minutes
+            run_due_jobs(last_minute.max(minute.saturating_sub(5)) + 1..=minute);
+            last_minute = minute;
+        }
+        if Instant::now() < next_probe {
+            let until_next_minute = Duration::from_secs(60 - now_unix() % 60);
+            signals::sleep(next_probe.saturating_duration_since(Instant::now()).min(until_next_minute));
+            continue;
+        }
+        next_probe = Instant::now() + interval;
+        
+        // Reload each round so added, removed and edited servers are picked up
+        let config = load_config()?;
+        let servers = servers_for_check(&config, names, filter)?;
+        let statuses = probe_servers(&servers, &Rollout::parallel(executor::default_parallel()), !ssh)?;
+        // Probes cut short by the signal say nothing about the servers
+        if signals::interrupted().is_some() {
+            break;
+        }
+        let results: Vec<(String, bool)> = servers
+            .iter()
+            .zip(&statuses)
+            .map(|(server, status)| (server.qualified_name(), status.reachable))
+            .collect();
+        
+        for change in state.update(&results) {
+            let (verb, event) = if change.reachable { ("is back up", "watchdog-up") } else { ("is unreachable", "watchdog-down") };
+            let message = format!("{} {}", change.server, verb);
+            println!("{} {}", utils::format_utc_time(now_unix()), message);
+            if let Err(e) = notify::send("hop watchdog", &message) {
+                print_warning(&format!("Failed to show notification: {:#}", e));
+            }
+            notify_webhook(&config, event, &[&change.server]);
+        }
+        watchdog::save_state(&state)?;
+    }
+    Ok(())
+}
+
+/// Start every job whose schedule matches a minute in `minutes`, each on its own thread
+fn run_due_jobs(minutes: std::ops::RangeInclusive<u64>) {
+    let jobs = match jobs::load_jobs() {
+        Ok(jobs) => jobs.jobs,
+        Err(e) => {
+            print_warning(&format!("{:#}", e));
+            return;
+        }
+    };
+    for job in jobs {
+        let schedule = match jobs::Schedule::parse(&job.cron) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                print_warning(&format!("Job {}: {:#}", job.name, e));
+                continue;
+            }
+        };
+        if minutes.clone().any(|minute| schedule.matches(&utils::local_time(minute * 60))) {
+            thread::spawn(move || {
+                let run = run_job(&job);
+                println!("{} job {} on {} exited with {}", utils::format_utc_time(run.started_at), run.job, run.server, run.exit_code);
+                if run.exit_code != 0 {
+                    let message = format!("Job {} failed on {} (exit {})", run.job, run.server, run.exit_code);
+                    if let Err(e) = notify::send("hop watchdog", &message) {
+                        print_warning(&format!("Failed to show notification: {:#}", e));
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Run a job once and record it in the job history and the audit log
+fn run_job(job: &jobs::Job) -> jobs::JobRun {
+    let started_at = now_unix();
+    let started = Instant::now();
+    let config = load_config();
+    let result = config.as_ref().map_err(|e| anyhow::anyhow!("{:#}", e)).and_then(|config| {
+        let server = select_server(config, &job.server)?;
+        ssh_client_for(&config.settings, server)?.capture(server, &job.command)
+    });
+    
+    let mut run = jobs::JobRun {
+        job: job.name.clone(),
+        server: job.server.clone(),
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        exit_code: match &result {
+            Ok(_) => 0,
+            Err(e) => exit_codes::exit_code_for(e),
+        },
+        output: String::new(),
+    };
+    match &result {
+        Ok(output) => run.set_output(output),
+        Err(e) => run.set_output(&format!("{:#}", e)),
+    }
+    
+    if let Err(e) = jobs::record_run(&run) {
+        print_warning(&format!("Failed to write job history: {:#}", e));
+    }
+    let entry = audit::AuditEntry::new(&job.server, &job.command, run.exit_code, started.elapsed());
+    let syslog = config.as_ref().is_ok_and(|config| config.settings.audit_syslog);
+    if let Err(e) = audit::record(&entry, syslog) {
+        print_warning(&format!("Failed to write audit log: {:#}", e));
+    }
+    run
+}
+
+fn handle_pool(action: PoolAction) -> Result<()> {
+    match action {
+        PoolAction::Start { servers, size, foreground } => {
+            if cfg!(windows) {
+                return Err(HopError::UsageError(
+                    "The connection pool needs ssh connection sharing, which Windows OpenSSH does not support. Run hop from WSL to use it.".to_string(),
+                ).into());
+            }
+            if foreground {
+                return run_pool(&servers, size);
+            }
+            if let Some(pid) = pool::running_pid()? {
+                return Err(anyhow::anyhow!("The pool is already running (pid {}). Stop it with 'hop pool stop'.", pid));
+            }
+            
+            let mut args = vec!["pool".to_string(), "start".to_string(), "--foreground".to_string()];
+            if servers.is_empty() {
+                args.extend(["--size".to_string(), size.to_string()]);
+            }
+            args.extend(servers);
+            let pid = pool::spawn_background(&args)?;
+            print_success(&format!("Connection pool started (pid {}).", pid));
+            print_info(&format!("Output goes to {}", pool::get_log_file_path()?.display()));
+        }
+        PoolAction::Stop => {
+            let stopped = pool::stop()?;
+            let config = load_config()?;
+            let mut closed = 0;
+            for name in pool::socket_servers()? {
+                let socket = pool::socket_path(&name)?;
+                if let Some(server) = config.find_server(&name) {
+                    if SshClient::new().control_master(server, &socket, "exit") {
+                        closed += 1;
+                    }
+                }
+                let _ = fs::remove_file(&socket);
+            }
+            if stopped {
+                print_success(&format!("Connection pool stopped, {} master connection(s) closed.", closed));
+            } else {
+                print_info("The connection pool is not running.");
+            }
+        }
+        PoolAction::Status => {
+            match pool::running_pid()? {
+                Some(pid) => println!("Connection pool running (pid {})", pid),
+                None => println!("Connection pool not running"),
+            }
+            let config = load_config()?;
+            let usage = history::usage_by_server(&history::load_history()?);
+            for name in pool::socket_servers()? {
+                let Some(server) = config.find_server(&name) else {
+                    continue;
+                };
+                let warm = SshClient::new().control_master(server, &pool::socket_path(&name)?, "check");
+                let mark = if warm { "●".green() } else { "○".dimmed() };
+                let last_used = usage.get(&name).map(|u| format!("used {}", format_time_ago(u.last_used))).unwrap_or_default();
+                println!(
+                    "  {} {}  {}",
+                    mark,
+                    server,
+                    format!("{}, idle timeout {}m  {}", if warm { "warm" } else { "closed" }, pool::idle_minutes(server, &config.settings), last_used).dimmed()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// How long the pool waits before trying a server again after a failed login
+const POOL_RETRY: Duration = Duration::from_secs(300);
+
+/// Keep master connections open to the pooled servers
+///
+/// A master that closed after its idle timeout is only opened again once
+/// the server has been used since.
+fn run_pool(names: &[String], size: usize) -> Result<()> {
+    let mut warmed: HashSet<String> = HashSet::new();
+    let mut closed_at: HashMap<String, u64> = HashMap::new();
+    let mut failed_at: HashMap<String, Instant> = HashMap::new();
+    while signals::interrupted().is_none() {
+        // Reload each round so new history and edited servers are picked up
+        let config = load_config()?;
+        let usage = history::usage_by_server(&history::load_history()?);
+        let targets = if names.is_empty() { pool::most_used(&usage, size) } else { names.to_vec() };
+        
+        for name in targets {
+            if signals::interrupted().is_some() {
+                break;
+            }
+            let server = match select_server(&config, &name) {
+                Ok(server) => server,
+                Err(e) => {
+                    print_warning(&format!("{:#}", e));
+                    continue;
+                }
+            };
+            let socket = pool::socket_path(&server.qualified_name())?;
+            let ssh_client = match ssh_client_for(&config.settings, server) {
+                Ok(client) => client,
+                Err(e) => {
+                    print_warning(&format!("{}: {:#}", server.name, e));
+                    continue;
+                }
+            };
+            if socket.exists() && ssh_client.control_master(server, &socket, "check") {
+                continue;
+            }
+            
+            let key = server.qualified_name();
+            if warmed.remove(&key) {
+                println!("{} {} closed (idle or dropped)", utils::format_utc_time(now_unix()), key);
+                closed_at.insert(key.clone(), now_unix());
+            }
+            let last_used = usage.get(&key).map_or(0, |u| u.last_used);
+            if closed_at.get(&key).is_some_and(|&at| last_used <= at) {
+                continue;
+            }
+            if failed_at.get(&key).is_some_and(|at| at.elapsed() < POOL_RETRY) {
+                continue;
+            }
+            
+            let _ = fs::remove_file(&socket);
+            if let Some(parent) = socket.parent() {
+                utils::ensure_dir_exists(parent)?;
+            }
+            let idle = pool::idle_minutes(server, &config.settings);
+            match ssh_client.start_master(server, &socket, idle) {
+                Ok(()) => {
+                    println!("{} {} warm (idle timeout {}m)", utils::format_utc_time(now_unix()), key, idle);
+                    closed_at.remove(&key);
+                    failed_at.remove(&key);
+                    warmed.insert(key);
+                }
+                Err(e) => {
+                    print_warning(&format!("{}: {:#}", key, e));
+                    failed_at.insert(key, Instant::now());
+                }
+            }
+        }
+        
+        if !signals::sleep(Duration::from_secs(30)) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn handle_tunnel(action: TunnelAction) -> Result<()> {
+    match action {
+        TunnelAction::Start { server, forwards, name, detach } => {
+            for forward in &forwards {
+                tunnel::validate_forward(forward).map_err(|e| HopError::UsageError(format!("{:#}", e)))?;
+            }
+            let config = load_config()?;
+            let server = select_server(&config, &server)?;
+            let name = name.unwrap_or_else(|| server.name.clone());
+            if !is_valid_server_name(&name) {
+                return Err(HopError::UsageError("Tunnel names may only contain letters, digits, '-' and '_'".to_string()).into());
+            }
+            if let Some(existing) = tunnel::load(&name)?.filter(|t| t.is_running()) {
+                return Err(HopError::UsageError(
+                    format!("Tunnel {} is already running (pid {}). Stop it with 'hop tunnel stop {}' or pick another --name.", name, existing.pid, name),
+                ).into());
+            }
+            
+            if !detach {
+                return run_tunnel(&config.settings, server, &name, forwards);
+            }
+            let mut args = vec!["tunnel".to_string(), "start".to_string(), server.qualified_name(), "--name".to_string(), name.clone()];
+            for forward in &forwards {
+                args.extend(["-L".to_string(), forward.clone()]);
+            }
+            let log_path = tunnel::get_log_file_path(&name)?;
+            let pid = background::spawn(&args, &log_path).map_err(|e| anyhow::anyhow!("Failed to start the tunnel: {:#}", e))?;
+            print_success(&format!("Tunnel {} started in the background (pid {}): {}", name, pid, forwards.join(", ")));
+            print_info(&format!("Output goes to {}", log_path.display()));
+        }
+        TunnelAction::Stop { name, all } => {
+            let tunnels = match name {
+                Some(name) => vec![tunnel::load(&name)?.ok_or_else(|| HopError::UsageError(format!("No tunnel named '{}'", name)))?],
+                None => tunnel::list()?,
+            };
+            if tunnels.is_empty() && all {
+                print_info("No tunnels to stop.");
+            }
+            for state in tunnels {
+                tunnel::stop(&state)?;
+                print_success(&format!("Stopped tunnel {}", state.name));
+            }
+        }
+        TunnelAction::List => {
+            let tunnels = tunnel::list()?;
+            if tunnels.is_empty() {
+                print_info("No tunnels. Start one with 'hop tunnel start <server> -L <port:host:port> --detach'.");
+                return Ok(());
+            }
+            let name_width = tunnels.iter().map(|t| t.name.chars().count()).max().unwrap_or(0).max("TUNNEL".len());
+            let server_width = tunnels.iter().map(|t| t.server.chars().count()).max().unwrap_or(0).max("SERVER".len());
+            println!(
+                "{:<name_width$}  {:<server_width$}  {:<18}  {:>5}  {}",
+                "TUNNEL".bold(), "SERVER".bold(), "STATUS".bold(), "FLAPS", "FORWARDS".bold()
+            );
+            for state in &tunnels {
+                let status = match (state.is_running(), state.connected_since) {
+                    (false, _) => format!("{:<18}", "stopped").red(),
+                    (true, Some(since)) => format!("{:<18}", format!("up {}", format_duration(now_unix().saturating_sub(since)))).green(),
+                    (true, None) => format!("{:<18}", "reconnecting").yellow(),
+                };
+                let flaps = match state.last_flap {
+                    Some(at) => format!("{:>5}  {}", state.flaps, format!("(last {})", format_time_ago(at)).dimmed()),
+                    None => format!("{:>5}", state.flaps),
+                };
+                println!("{:<name_width$}  {:<server_width$}  {}  {}  {}", state.name, state.server, status, flaps, state.forwards.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Keep an ssh port forward running, starting ssh again whenever it exits
+///
+/// Runs until the process is stopped, in a terminal or detached by `hop tunnel start --detach`.
+fn run_tunnel(settings: &Settings, server: &Server, name: &str, forwards: Vec<String>) -> Result<()> {
+    let ssh_client = ssh_client_for(settings, server)?;
+    // A detached supervisor has no terminal to answer prompts on
+    let batch = !std::io::IsTerminal::is_terminal(&std::io::stdin());
+    let mut state = tunnel::TunnelState {
+        name: name.to_string(),
+        server: server.qualified_name(),
+        forwards,
+        pid: process::id(),
+        ssh_pid: None,
+        started_at: now_unix(),
+        connected_since: None,
+        flaps: 0,
+        last_flap: None,
+    };
+    
+    // Stopping the tunnel (Ctrl-C, or `hop tunnel stop` for a detached one)
+    // reaches ssh, and the loop below then forgets the tunnel
+    let _deferred = signals::defer();
+    let mut attempt = 0;
+    loop {
+        let mut command = ssh_client.tunnel_command(server, &state.forwards, batch);
+        let started = Instant::now();
+        let mut child = command.spawn().map_err(|e| exit_codes::spawn_failure(&command.get_program().to_string_lossy(), e))?;
+        let tracked = signals::track(&child);
+        state.ssh_pid = Some(child.id());
+        state.connected_since = Some(now_unix());
+        tunnel::save(&state)?;
+        println!("{} connecting tunnel {} via {}: {}", utils::format_utc_time(now_unix()), name, server.name, state.forwards.join(", "));
+        
+        let status = child.wait()?;
+        drop(tracked);
+        if signals::interrupted().is_some() {
+            return tunnel::forget(&state.name);
+        }
+        if started.elapsed() >= tunnel::STABLE_AFTER {
+            attempt = 0;
+        }
+        let wait = tunnel::backoff(attempt);
+        attempt += 1;
+        
+        state.ssh_pid = None;
+        state.connected_since = None;
+        state.flaps += 1;
+        state.last_flap = Some(now_unix());
+        tunnel::save(&state)?;
+        println!(
+            "{} ssh exited ({}), reconnecting in {}s",
+            utils::format_utc_time(now_unix()),
+            status,
+            wait.as_secs()
+        );
+        if !signals::sleep(wait) {
+            return tunnel::forget(&state.name);
+        }
+    }
+}
+
+fn handle_job(action: JobAction) -> Result<()> {
+    match action {
+        JobAction::Add { name, server, cron, command } => {
+            if !is_valid_server_name(&name) {
+                return Err(HopError::UsageError("Job names may only contain letters, digits, '-' and '_'".to_string()).into());
+            }
+            jobs::Schedule::parse(&cron).map_err(|e| HopError::UsageError(format!("{:#}", e)))?;
+            let config = load_config()?;
+            let server = select_server(&config, &server)?;
+            
+            let policy = policy::load_policy(config.settings.policy_file.as_deref())?;
+            if let Some(rule) = policy.violation(server, &command) {
+                let reason = rule.reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default();
+                return Err(HopError::UsageError(
+                    format!("The exec policy denies '{}' on {}: matches '{}'{}", command, server.name, rule.pattern, reason),
+                ).into());
+            }
+            
+            let mut jobs = jobs::load_jobs()?;
+            if jobs.jobs.iter().any(|job| job.name == name) {
+                return Err(HopError::UsageError(format!("A job named '{}' already exists", name)).into());
+            }
+            jobs.jobs.push(jobs::Job { name: name.clone(), server: server.qualified_name(), cron: cron.clone(), command });
+            jobs::save_jobs(&jobs)?;
+            print_success(&format!("Job {} runs '{}' on {}", name, cron, server.name));
+            if watchdog::running_pid()?.is_none() {
+                print_hint("Jobs run while the background watchdog runs: hop watchdog start");
+            }
+        }
+        JobAction::List => {
+            let jobs = jobs::load_jobs()?.jobs;
+            if jobs.is_empty() {
+                print_info("No jobs scheduled. Add one with 'hop job add'.");
+                return Ok(());
+            }
+            let history = jobs::load_history()?;
+            let name_width = jobs.iter().map(|j| j.name.chars().count()).max().unwrap_or(0).max("JOB".len());
+            let server_width = jobs.iter().map(|j| j.server.chars().count()).max().unwrap_or(0).max("SERVER".len());
+            let cron_width = jobs.iter().map(|j| j.cron.chars().count()).max().unwrap_or(0).max("SCHEDULE".len());
+            println!(
+                "{:<name_width$}  {:<server_width$}  {:<cron_width$}  {:<16}  {}",
+                "JOB".bold(), "SERVER".bold(), "SCHEDULE".bold(), "LAST RUN".bold(), "COMMAND".bold()
+            );
+            for job in &jobs {
+                let last = match history.iter().rev().find(|run| run.job == job.name) {
+                    Some(run) if run.exit_code == 0 => format!("{:<16}", format!("✓ {}", format_time_ago(run.started_at))).green(),
+                    Some(run) => format!("{:<16}", format!("✗ {}", format_time_ago(run.started_at))).red(),
+                    None => format!("{:<16}", "never").dimmed(),
+                };
+                println!("{:<name_width$}  {:<server_width$}  {:<cron_width$}  {}  {}", job.name, job.server, job.cron, last, job.command);
+            }
+            if watchdog::running_pid()?.is_none() {
+                print_hint("The background watchdog is not running, so jobs will not run: hop watchdog start");
+            }
+        }
+        JobAction::Remove { name } => {
+            let mut jobs = jobs::load_jobs()?;
+            let before = jobs.jobs.len();
+            jobs.jobs.retain(|job| job.name != name);
+            if jobs.jobs.len() == before {
+                return Err(HopError::UsageError(format!("No job named '{}'", name)).into());
+            }
+            jobs::save_jobs(&jobs)?;
+            print_success(&format!("Removed job {}", name));
+        }
+        JobAction::Run { name } => {
+            let job = jobs::load_jobs()?
+                .jobs
+                .into_iter()
+                .find(|job| job.name == name)
+                .ok_or_else(|| HopError::UsageError(format!("No job named '{}'", name)))?;
+            print_info(&format!("Running job {} on {}: {}", job.name, job.server, job.command));
+            let run = run_job(&job);
+            if !run.output.is_empty() {
+                println!("{}", run.output);
+            }
+            if run.exit_code != 0 {
+                return Err(HopError::SshFailure { code: Some(run.exit_code), message: format!("Job {} failed (exit {})", job.name, run.exit_code) }.into());
+            }
+            print_success(&format!("Job {} finished in {}", job.name, format_duration(run.duration_ms / 1000)));
+        }
+        JobAction::History { name, limit, output } => {
+            let mut runs: Vec<jobs::JobRun> = jobs::load_history()?
+                .into_iter()
+                .filter(|run| name.as_ref().is_none_or(|name| &run.job == name))
+                .collect();
+            if runs.is_empty() {
+                print_info("No job runs recorded yet.");
+                return Ok(());
+            }
+            runs.drain(..runs.len().saturating_sub(limit));
+            for run in runs.iter().rev() {
+                let mark = if run.exit_code == 0 { "✓".green() } else { "✗".red() };
+                println!(
+                    "{} {}  {} on {}  exit {}  {}",
+                    mark,
+                    utils::format_utc_time(run.started_at),
+                    run.job.bold(),
+                    run.server,
+                    run.exit_code,
+                    format_duration(run.duration_ms / 1000).dimmed()
+                );
+                if output && !run.output.is_empty() {
+                    for line in run.output.lines() {
+                        println!("    {}", line.dimmed());
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_top(names: Vec<String>, filter: ServerFilter, interval: Duration, once: bool) -> Result<()> {
+    let config = load_config()?;
+    let servers = servers_for_check(&config, &names, &filter)?;
+    if servers.is_empty() {
+        print_info("No servers to show.");
+        return Ok(());
+    }
+    let clients = clients_for(&config.settings, &servers)?;
+    let refresh = !once && std::io::IsTerminal::is_terminal(&std::io::stdout());
+    
+    loop {
+        let results: Vec<Result<top::Resources>> = capture_all(&servers, &clients, top::PROBE_COMMAND)
+            .into_iter()
+            .map(|output| output.and_then(|output| top::parse(&output)))
+            .collect();
+        if signals::interrupted().is_some() {
+            return Ok(());
+        }
+        
+        // Busiest servers first, unreachable ones last
+        let mut rows: Vec<(&Server, &Result<top::Resources>)> = servers.iter().copied().zip(&results).collect();
+        rows.sort_by(|a, b| {
+            let pressure = |r: &Result<top::Resources>| r.as_ref().map_or(-1.0, |r| r.pressure());
+            pressure(b.1).total_cmp(&pressure(a.1))
+        });
+        
+        if refresh {
+            print!("\x1b[2J\x1b[H");
+            println!("{} {}", "hop top".bold(), format!("every {}s, Ctrl-C to quit", interval.as_secs()).dimmed());
+            println!();
+        }
+        let width = servers.iter().map(|s| s.name.chars().count()).max().unwrap_or(0).max("SERVER".len());
+        println!("{:<width$}  {:>6}  {:>5}  {:>17}  {:>5}", "SERVER".bold(), "LOAD", "CPU%", "MEMORY", "DISK%");
+        for (server, result) in rows {
+            match result {
+                Ok(r) => {
+                    let percent = |value: f64, text: String| match value {
+                        v if v >= 90.0 => text.red().bold(),
+                        v if v >= 75.0 => text.yellow(),
+                        _ => text.normal(),
+                    };
+                    let memory = format!(
+                        "{}/{} {:>3.0}%",
+                        top::format_bytes(r.mem_total - r.mem_available.min(r.mem_total)),
+                        top::format_bytes(r.mem_total),
+                        r.mem_percent()
+                    );
+                    println!(
+                        "{:<width$}  {:>6.2}  {}  {}  {}",
+                        server.name,
+                        r.load1,
+                        percent(r.cpu_percent(), format!("{:>5.0}", r.cpu_percent())),
+                        percent(r.mem_percent(), format!("{:>17}", memory)),
+                        percent(f64::from(r.disk_percent), format!("{:>5}", r.disk_percent)),
+                    );
+                }
+                Err(e) => {
+                    let reason = e.to_string();
+                    let reason = reason.lines().next().unwrap_or_default();
+                    println!("{:<width$}  {}", server.name, reason.red());
+                }
+            }
+        }
+        
+        if !refresh || !signals::sleep(interval) {
+            return Ok(());
+        }
+    }
+}
+
+fn handle_stats(days: u64, server: Option<String>) -> Result<()> {
+    let entries = history::load_history()?;
+    let today = utils::local_time(now_unix());
+    // Midnight local time, `days - 1` days ago
+    let since = (now_unix() - u64::from(today.hour) * 3_600 - u64::from(today.minute) * 60 - now_unix() % 60)
+        .saturating_sub((days - 1) * 86_400);
+    let mut totals = history::time_by_day(&entries, since, |t| utils::local_time(t).date());
+    if let Some(server) = &server {
+        totals.retain(|(_, name), _| name == server);
+    }
+    
+    if totals.is_empty() {
+        print_info(&format!("No finished sessions in the last {} day(s).", days));
+        return Ok(());
+    }
+    
+    let width = totals.keys().map(|(_, name)| name.chars().count()).max().unwrap_or(0).max("SERVER".len());
+    println!("{:<10}  {:<width$}  {:>8}  {:>8}", "DATE".bold(), "SERVER".bold(), "SESSIONS", "TIME");
+    let mut per_server: BTreeMap<&str, history::DayTotal> = BTreeMap::new();
+    for ((day, name), total) in &totals {
+        println!("{:<10}  {:<width$}  {:>8}  {:>8}", day, name, total.sessions, format_duration(total.seconds));
+        let server_total = per_server.entry(name.as_str()).or_default();
+        server_total.sessions += total.sessions;
+        server_total.seconds += total.seconds;
+    }
+    
+    println!();
+    println!("{:<10}  {:<width$}  {:>8}  {:>8}", "TOTAL".bold(), "SERVER".bold(), "SESSIONS", "TIME");
+    for (name, total) in &per_server {
+        println!("{:<10}  {:<width$}  {:>8}  {:>8}", "", name, total.sessions, format_duration(total.seconds));
+    }
+    let seconds: u64 = per_server.values().map(|t| t.seconds).sum();
+    println!("{:<10}  {:<width$}  {:>8}  {:>8}", "", "all", per_server.values().map(|t| t.sessions).sum::<usize>(), format_duration(seconds).bold());
+    Ok(())
+}
+
+fn handle_connect(identifier: String, test: bool, remote_dir: Option<String>, reconnect: bool) -> Result<()> {
+    let config = load_config()?;
+    
+    // Server names cannot contain '@', so this is always an ad-hoc target
+    if identifier.contains('@') {
+        let server = Server::from_target(&identifier)
+            .ok_or_else(|| anyhow::anyhow!("Invalid target '{}'. Expected user@ip.", identifier))?;
+        hooks::before_connect(&server)?;
+        return handle_adhoc_connect(config, server, test, remote_dir.as_deref(), reconnect);
+    }
+    
+    let server = select_server(&config, &identifier)?;
+    hooks::before_connect(server)?;
+    
+    if test {
+        return ssh_client_for(&config.settings, server)?.test_connection(server);
+    }
+    
+    if config.requires_connect_confirmation(server)
+        && !confirm_by_typing(&format!("{} is a protected server.", server.name.red().bold()), &server.name)?
+    {
+        print_info("Operation cancelled.");
+        return Ok(());
+    }
+    print_banner(server);
+    // Runs before the credentials are fetched, since it may bring up the network Vault needs
+    if let Some(hook) = server.pre_connect_hook() {
+        local_hooks::run(server, "pre_connect", &hook)?;
+        if signals::interrupted().is_some() {
+            return Ok(());
+        }
+    }
+    let result = ssh_client_for(&config.settings, server).and_then(|ssh_client| {
+        let started_at = history::record_connect(&server.qualified_name()).unwrap_or_else(|e| {
+            print_warning(&format!("Failed to record connection history: {}", e));
+            now_unix()
+        });
+        let started = Instant::now();
+        let result = run_session(&config.settings, &ssh_client, server, &server.qualified_name(), remote_dir.as_deref(), reconnect);
+        if let Err(e) = history::record_session_end(&server.qualified_name(), started_at, started.elapsed().as_secs()) {
+            print_warning(&format!("Failed to record connection history: {}", e));
+        }
+        result
+    });
+    // Clean up even when the session failed
+    let cleanup = match server.post_disconnect_hook() {
+        Some(hook) => local_hooks::run(server, "post_disconnect", &hook),
+        None => Ok(()),
+    };
+    result?;
+    cleanup
+}
+
+/// How many dropped sessions in a row `--reconnect` retries
+const MAX_RECONNECT_ATTEMPTS: u32 = 3;
+/// A session that lasted this long counts as healthy and resets the retry budget
+const STABLE_SESSION: Duration = Duration::from_secs(60);
+
+/// Run an interactive session, ringing the bell when the connection drops
+///
+/// With `reconnect`, dropped sessions are retried; otherwise a hint with the
+/// command to reconnect is printed.
+fn run_session(settings: &Settings, ssh_client: &SshClient, server: &Server, target: &str, remote_dir: Option<&str>, reconnect: bool) -> Result<()> {
+    let mut attempts = 0;
+    let mut refreshed = None;
+    loop {
+        show_totp_code(settings, server);
+        let started = Instant::now();
+        let end = match refreshed.as_ref().unwrap_or(ssh_client).connect(server, remote_dir) {
+            Ok(end) => Some(end),
+            // The network may still be down while reconnecting
+            Err(e) if attempts > 0 => {
+                print_warning(&format!("{:#}", e));
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        if end == Some(SessionEnd::Closed) || signals::interrupted().is_some() {
+            return Ok(());
+        }
+        
+        if end.is_some() {
+            eprint!("\x07");
+            print_warning(&format!("Connection to {} dropped.", server.name));
+            if started.elapsed() >= STABLE_SESSION {
+                attempts = 0;
+            }
+        }
+        if !reconnect || attempts == MAX_RECONNECT_ATTEMPTS {
+            break;
+        }
+        attempts += 1;
+        print_info(&format!("Reconnecting (attempt {}/{})...", attempts, MAX_RECONNECT_ATTEMPTS));
+        if !signals::sleep(Duration::from_secs(2)) {
+            return Ok(());
+        }
+        // Vault credentials are short-lived or single-use, so fetch new ones
+        if server.vault_role.is_some() {
+            refreshed = Some(ssh_client_for(settings, server)?);
+        }
+    }
+    
+    if !reconnect {
+        print_info(&format!("Reconnect with: hop connect {} (add --reconnect to retry automatically)", target));
+    }
+    Err(HopError::SshFailure { code: None, message: format!("Connection to {} was lost", server.name) }.into())
+}
+
+/// Print (and copy) the server's current TOTP code, if it has a seed stored
+fn show_totp_code(settings: &Settings, server: &Server) {
+    let seed = match secrets::lookup(settings, &server.qualified_name(), secrets::TOTP_SEED) {
+        Ok(Some(seed)) => seed,
+        Ok(None) => return,
+        Err(e) => {
+            print_warning(&format!("Could not read TOTP seed: {:#}", e));
+            return;
+        }
+    };
+    match totp::decode_seed(&seed) {
+        Ok(key) => {
+            let now = now_unix();
+            let code = totp::code_at(&key, now);
+            let copied = if utils::copy_to_clipboard(&code) { ", copied to clipboard" } else { "" };
+            print_info(&format!(
+                "TOTP code for {}: {} (valid for {}s{})",
+                server.name,
+                code.bold(),
+                totp::seconds_left(now),
+                copied
+            ));
+        }
+        Err(e) => print_warning(&format!("{} for {}", e, server.name)),
+    }
+}
+
+fn handle_show(identifier: String, qr: Option<QrPayload>) -> Result<()> {
+    let config = load_config()?;
+    let server = select_server(&config, &identifier)?;
+    
+    println!("{}", server.name.bold());
+    if let Some(description) = &server.description {
+        println!("  {}", description.italic());
+    }
+    let row = |label: &str, value: &str| {
+        let label = format!("{}:", label);
+        println!("  {:<16} {}", label, value.lines().collect::<Vec<_>>().join(&format!("\n{:19}", "")));
+    };
+    let hook = |hook: models::LocalHook| {
+        format!("{} (timeout {}, on failure {})", hook.command, format_duration(hook.timeout.as_secs()), hook.on_failure.as_str())
+    };
+    
+    row("user", &server.user);
+    row("host", &server.ip);
+    if let Some(port) = server.port {
+        row("port", &port.to_string());
+    }
+    row("ssh", &server.ssh_command());
+    if let Some(group) = &server.group {
+        row("group", group);
+    }
+    if !server.tags.is_empty() {
+        row("tags", &server.tags.join(", "));
+    }
+    if let Some(notes) = &server.notes {
+        row("notes", notes);
+    }
+    if let Some(identity) = &server.identity_file {
+        row("key", identity);
+    }
+    if let Some(agent) = &server.identity_agent {
+        row("agent", agent);
+    }
+    if let Some(role) = &server.vault_role {
+        let mount = server.vault_mount.as_deref().unwrap_or(vault::DEFAULT_MOUNT);
+        row("vault", &format!("{} on {}{}", role, mount, if server.vault_otp { ", one-time password" } else { "" }));
+    }
+    if let Some(item) = &server.bitwarden_item {
+        row("bitwarden", &format!("{}{}", item, if server.bitwarden_passphrase { " (key passphrase)" } else { "" }));
+    }
+    if !server.host_keys.is_empty() {
+        row("host keys", &format!("{} pinned", server.host_keys.len()));
+    }
+    if let Some(minutes) = server.pool_idle {
+        row("pool idle", &format!("{} min", minutes));
+    }
+    if let Some(pre_connect) = server.pre_connect_hook() {
+        row("pre-connect", &hook(pre_connect));
+    }
+    if let Some(post_disconnect) = server.post_disconnect_hook() {
+        row("post-disconnect", &hook(post_disconnect));
+    }
+    let confirm: Vec<&str> = [
+        config.requires_connect_confirmation(server).then_some("connect"),
+        config.requires_exec_confirmation(server).then_some("exec"),
+    ].into_iter().flatten().collect();
+    if !confirm.is_empty() {
+        row("confirm", &confirm.join(", "));
+    }
+    if server.locked {
+        row("locked", &"yes".yellow().to_string());
+    }
+    if let Some(added_at) = server.added_at {
+        row("added", &format!("{} ({})", format_utc_date(added_at), format_time_ago(added_at)));
+    }
+    
+    let usage = history::usage_by_server(&history::load_history()?);
+    match usage.get(&server.qualified_name()) {
+        Some(stats) if stats.count > 0 => {
+            row("used", &format!("{} ({} sessions)", format_time_ago(stats.last_used), stats.count));
+        }
+        _ => row("used", "never"),
+    }
+    
+    if let Some(payload) = qr {
+        let data = match payload {
+            QrPayload::Uri => server.ssh_uri(),
+            QrPayload::Import => {
+                // Local commands must not travel to another machine
+                let mut server = server.clone();
+                server.strip_local_hooks();
+                serde_json::to_string(&[server])?
+            }
+        };
+        println!();
+        print!("{}", render_qr(&data)?);
+        println!("{}", data.dimmed());
+    }
+    
+    Ok(())
+}
+
+/// Render data as a QR code using half-block characters, two modules per text row
+#[cfg(feature = "qr")]
+fn render_qr(data: &str) -> Result<String> {
+    use qrcode::render::unicode::Dense1x2;
+    
+    let code = qrcode::QrCode::new(data.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Cannot encode QR code: {}", e))?;
+    Ok(code.render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build() + "\n")
+}
+
+#[cfg(not(feature = "qr"))]
+fn render_qr(_data: &str) -> Result<String> {
+    Err(anyhow::anyhow!("hop was built without the 'qr' feature (cargo build --features qr)"))
+}
+
+/// Print the server's name, tags and notes before an interactive session
+fn print_banner(server: &Server) {
+    if is_quiet() {
+        return;
+    }
+    
+    let rule = "─".repeat(48).dimmed();
+    println!("{}", rule);
+    
+    let tags: Vec<String> = server.tags.iter().map(|tag| match tag.as_str() {
+        "prod" | "production" => tag.red().bold().to_string(),
+        "staging" | "stage" => tag.yellow().to_string(),
+        _ => tag.cyan().to_string(),
+    }).collect();
+    if tags.is_empty() {
+        println!(" {}", server.name.bold());
+    } else {
+        println!(" {}  [{}]", server.name.bold(), tags.join(", "));
+    }
+    
+    if let Some(notes) = &server.notes {
+        for line in notes.lines() {
+            println!(" {} {}", "!".yellow().bold(), line.yellow());
+        }
+    }
+    
+    println!("{}", rule);
+}
+
+fn handle_adhoc_connect(mut config: config::Config, mut server: Server, test: bool, remote_dir: Option<&str>, reconnect: bool) -> Result<()> {
+    let ssh_client = SshClient::new();
+    
+    if test {
+        return ssh_client.test_connection(&server);
+    }
+    
+    let target = format!("{}@{}", server.user, server.ip);
+    run_session(&config.settings, &ssh_client, &server, &target, remote_dir, reconnect)?;
+    
+    loop {
+        let prompt = format!("Save {}@{} as (empty to skip)", server.user, server.ip);
+        let Some(name) = prompt_input(&prompt)? else {
+            return Ok(());
+        };
+        
+        if !is_valid_server_name(&name) {
+            print_warning("Invalid server name. Use only alphanumeric characters, hyphens, and underscores.");
+            continue;
+        }
+        
+        server.name = name;
+        match config.add_server(server.clone()) {
+            Ok(()) => break,
+            Err(e) => print_warning(&format!("{}", e)),
+        }
+    }
+    
+    save_config(&config)?;
+    print_success(&format!("Added server: {}", server));
+    Ok(())
+}
+
+/// Build the ssh client for a server, logging in with its stored password if it has one
+fn ssh_client_for(settings: &Settings, server: &Server) -> Result<SshClient> {
+    match vault::credential_for(server)? {
+        Some(vault::Credential::Certificate(path)) => Ok(SshClient::with_certificate(path)),
+        Some(vault::Credential::Otp(otp)) => Ok(SshClient::with_password(Some(otp))),
+        None => match &server.bitwarden_item {
+            Some(item) if server.bitwarden_passphrase => Ok(SshClient::with_passphrase(bitwarden::password(item)?)),
+            Some(item) => Ok(SshClient::with_password(Some(bitwarden::password(item)?))),
+            None => Ok(SshClient::with_password(secrets::lookup(settings, &server.qualified_name(), secrets::PASSWORD)?)),
+        },
+    }
+}
+
+/// Resolve an identifier to a server, asking the user to pick when it is ambiguous
+fn select_server<'a>(config: &'a config::Config, identifier: &str) -> Result<&'a Server> {
+    match config.resolve_server(identifier) {
+        Resolution::Found(server) => Ok(server),
+        Resolution::NotFound => Err(server_not_found(identifier)),
+        Resolution::Ambiguous(candidates) => {
+            let items: Vec<String> = candidates
+                .iter()
+                .map(|s| match &s.group {
+                    Some(group) => format!("{}/{}", group, s),
+                    None => s.to_string(),
+                })
+                .collect();
+            let message = format!("'{}' matches {} servers:", identifier, candidates.len());
+            match choose_from(&message, &items)? {
+                Some(index) => Ok(candidates[index]),
+                None => Err(anyhow::anyhow!("No server selected.")),
+            }
+        }
+    }
+}
+
+fn handle_remove(identifier: String, force: bool, known_hosts: bool) -> Result<()> {
+    let mut config = load_config()?;
+    
+    let server = config.find_server(&identifier)
+        .ok_or_else(|| server_not_found(&identifier))?;
+    server.ensure_unlocked()?;
+    
+    if !force && !confirm_action(&format!("Remove server '{}'?", server))? {
+        print_info("Operation cancelled.");
+        return Ok(());
+    }
+    
+    let removed_server = config.remove_server(&identifier)?;
+    save_config(&config)?;
+    
+    print_success(&format!("Removed server: {}", removed_server));
+    clean_known_hosts(&config, &[&removed_server], known_hosts, force);
+    update_secrets(&config.settings, &[&removed_server.qualified_name()], None);
+    notify_change(&config, "remove", &[&removed_server]);
+    Ok(())
+}
+
+fn handle_bulk_remove(filter: ServerFilter, force: bool, known_hosts: bool) -> Result<()> {
+    let mut config = load_config()?;
+    
+    let matching = config.filter_servers(&filter);
+    warn_locked(&matching);
+    let names: Vec<String> = matching
+        .iter()
+        .filter(|s| !s.locked)
+        .map(|s| s.qualified_name())
+        .collect();
+    
+    if names.is_empty() {
+        print_info(&format!("No servers with {}.", filter));
+        return Ok(());
+    }
+    
+    println!("{}", "Servers to remove:".bold());
+    println!();
+    for name in &names {
+        if let Some(server) = config.find_server(name) {
+            println!("  {}", server);
+        }
+    }
+    println!();
+    
+    if !force && !confirm_action(&format!("Remove {} server(s)?", names.len()))? {
+        print_info("Operation cancelled.");
+        return Ok(());
+    }
+    
+    let mut removed = Vec::new();
+    for name in &names {
+        removed.push(config.remove_server(name)?);
+    }
+    save_config(&config)?;
+    
+    print_success(&format!("Removed {} server(s).", names.len()));
+    clean_known_hosts(&config, &removed.iter().collect::<Vec<_>>(), known_hosts, force);
+    update_secrets(&config.settings, &names.iter().map(String::as_str).collect::<Vec<_>>(), None);
+    notify_change(&config, "remove", &removed.iter().collect::<Vec<_>>());
+    Ok(())
+}
+
+/// Offer to drop known_hosts entries for hosts no remaining server uses
+///
+/// With `always` the entries go without asking; with `force` (or without a
+/// terminal) hop does not ask and leaves them alone.
+fn clean_known_hosts(config: &config::Config, removed: &[&Server], always: bool, force: bool) {
+    let mut hosts: Vec<String> = removed
+        .iter()
+        .filter(|s| !config.servers_with_ip(&s.ip).iter().any(|other| other.ssh_port() == s.ssh_port()))
+        .map(|s| s.known_hosts_name())
+        .collect();
+    hosts.sort_unstable();
+    hosts.dedup();
+    hosts.retain(|host| host_keys::is_known(host));
+    if hosts.is_empty() {
+        return;
+    }
+    
+    if !always {
+        if force || !utils::is_interactive() {
+            return;
+        }
+        let message = format!("Also remove {} from ~/.ssh/known_hosts?", hosts.join(", "));
+        if !confirm_action(&message).unwrap_or(false) {
+            return;
+        }
+    }
+    for host in hosts {
+        match host_keys::remove_from_known_hosts(&host) {
+            Ok(()) => print_info(&format!("Removed {} from known_hosts", host)),
+            Err(e) => print_warning(&format!("{:#}", e)),
+        }
+    }
+}
+
+/// Warn about locked servers that a bulk operation will skip
+fn warn_locked(servers: &[&Server]) {
+    let locked: Vec<&str> = servers.iter().filter(|s| s.locked).map(|s| s.name.as_str()).collect();
+    if !locked.is_empty() {
+        print_warning(&format!("Skipping locked server(s): {}", locked.join(", ")));
+    }
+}
+
+fn handle_lock(identifier: String, lock: bool) -> Result<()> {
+    let mut config = load_config()?;
+    
+    let server = config.find_server_mut(&identifier)
+        .ok_or_else(|| server_not_found(&identifier))?;
+    
+    if server.locked == lock {
+        print_info(&format!("Server '{}' is already {}.", server.name, if lock { "locked" } else { "unlocked" }));
+        return Ok(());
+    }
+    
+    server.locked = lock;
+    let server = server.clone();
+    save_config(&config)?;
+    
+    print_success(&format!("{} server: {}", if lock { "Locked" } else { "Unlocked" }, server.name));
+    notify_change(&config, if lock { "lock" } else { "unlock" }, &[&server]);
+    Ok(())
+}
+
+fn handle_group(action: GroupAction) -> Result<()> {
+    match action {
+        GroupAction::List => handle_group_list(),
+        GroupAction::Move { servers, to, ungroup: _ } => handle_group_move(servers, to),
+        GroupAction::Rename { from, to } => handle_group_rename(from, to),
+    }
+}
+
+fn handle_group_list() -> Result<()> {
+    let config = load_config()?;
+    let groups = config.groups();
+    if groups.is_empty() {
+        print_info("No groups yet. Use 'hop group move <server> --to <group>' to create one.");
+        return Ok(());
+    }
+    
+    // Every group and its parents, with the servers in all their subgroups
+    let mut totals: BTreeMap<&str, usize> = BTreeMap::new();
+    for (&group, &count) in &groups {
+        for (end, _) in group.match_indices('/').chain([(group.len(), "")]) {
+            *totals.entry(&group[..end]).or_insert(0) += count;
+        }
+    }
+    for (group, total) in totals {
+        let depth = group.matches('/').count();
+        let name = group.rsplit('/').next().unwrap_or(group);
+        println!("{}{} {}", "  ".repeat(depth), name.cyan().bold(), format!("({})", total).dimmed());
+    }
+    let ungrouped = config.list_servers().iter().filter(|s| s.group.is_none()).count();
+    if ungrouped > 0 {
+        println!("{} {}", "(ungrouped)".dimmed(), format!("({})", ungrouped).dimmed());
+    }
+    Ok(())
+}
+
+fn handle_group_move(identifiers: Vec<String>, to: Option<String>) -> Result<()> {
+    let mut config = load_config()?;
+    let group = models::normalize_group(to.as_deref().unwrap_or_default())?;
+    
+    let mut moves: Vec<(String, Server)> = Vec::new();
+    for identifier in &identifiers {
+        let server = config.find_server(identifier)
+            .ok_or_else(|| server_not_found(identifier))?;
+        server.ensure_unlocked()?;
+        if server.group == group {
+            continue;
+        }
+        let mut moved = server.clone();
+        moved.group = group.clone();
+        let key = moved.qualified_name();
+        if moves.iter().any(|(_, other)| other.qualified_name() == key) {
+            return Err(anyhow::anyhow!("Server with name '{}' already exists", key));
+        }
+        if !moves.iter().any(|(old, _)| *old == key) {
+            ensure_name_free(&config, &server.qualified_name(), &moved)?;
+        }
+        moves.push((server.qualified_name(), moved));
+    }
+    
+    if moves.is_empty() {
+        print_info("Nothing to move.");
+        return Ok(());
+    }
+    config.replace_servers(moves.clone());
+    save_config(&config)?;
+    for (old, new) in &moves {
+        update_secrets(&config.settings, &[], Some((old, &new.qualified_name())));
+    }
+    let moved: Vec<Server> = moves.into_iter().map(|(_, server)| server).collect();
+    let names: Vec<&str> = moved.iter().map(|s| s.name.as_str()).collect();
+    match &group {
+        Some(group) => print_success(&format!("Moved {} to {}", names.join(", "), group)),
+        None => print_success(&format!("Ungrouped: {}", names.join(", "))),
+    }
+    notify_change(&config, "move", &moved.iter().collect::<Vec<_>>());
+    Ok(())
+}
+
+fn handle_group_rename(from: String, to: String) -> Result<()> {
+    let mut config = load_config()?;
+    let empty = || anyhow::anyhow!("Group name cannot be empty.");
+    let from = models::normalize_group(&from)?.ok_or_else(empty)?;
+    let to = models::normalize_group(&to)?.ok_or_else(empty)?;
+    
+    let in_group: Vec<&Server> = config.list_servers().iter().filter(|s| s.in_group(&from)).collect();
+    if in_group.is_empty() {
+        return Err(anyhow::anyhow!("No servers in group '{}'", from));
+    }
+    warn_locked(&in_group);
+    
+    let moved = config.rename_group(&from, &to)?;
+    if moved.is_empty() {
+        return Ok(());
+    }
+    save_config(&config)?;
+    print_success(&format!("Renamed group {} to {} ({} servers)", from, to, moved.len()));
+    for (old, new) in &moved {
+        update_secrets(&config.settings, &[], Some((old, new)));
+    }
+    let servers: Vec<&Server> = moved.iter().filter_map(|(_, name)| config.find_server(name)).collect();
+    notify_change(&config, "move", &servers);
+    Ok(())
+}
+
+fn handle_edit(identifier: String, name: Option<String>, user: Option<String>, ip: Option<String>, port: Option<u16>, notes: Option<String>, tags: TagEdits) -> Result<()> {
+    let mut config = load_config()?;
+    
+    let mut changed = false;
+    let (old_name, updated_server) = {
+        let mut server = config.find_server(&identifier)
+            .ok_or_else(|| server_not_found(&identifier))?
+            .clone();
+        server.ensure_unlocked()?;
+        let old_name = server.qualified_name();
+        
+        if let Some(new_name) = name {
+            if !is_valid_server_name(&new_name) {
+                return Err(anyhow::anyhow!("Invalid server name. Use only alphanumeric characters, hyphens, and underscores."));
+            }
+            server.name = new_name;
+            changed = true;
+        }
+        
+        if let Some(new_user) = user {
+            server.user = new_user;
+            changed = true;
+        }
+        
+        if let Some(new_ip) = ip {
+            server.ip = utils::normalize_host(&new_ip).ok_or_else(|| models::invalid_host(&new_ip))?;
+            changed = true;
+        }
+        
+        if let Some(new_port) = port {
+            server.port = Some(new_port);
+            changed = true;
+        }
+        
+        if let Some(new_notes) = notes {
+            server.set_field("notes", &new_notes)?;
+            changed = true;
+        }
+        
+        for tag in &tags.add_tag {
+            changed |= server.add_tag(tag)?;
+        }
+        for tag in &tags.remove_tag {
+            changed |= server.remove_tag(tag);
+        }
+        
+        (old_name, server)
+    };
+    
+    if !changed && !tags.is_empty() {
+        print_info(&format!("Server '{}' already has those tags.", updated_server.name));
+        return Ok(());
+    }
+    if !changed {
+        print_warning("No changes specified. Use --name, --user, --ip, --port, --notes, --add-tag or --remove-tag to edit the server.");
+        return Ok(());
+    }
+    ensure_name_free(&config, &old_name, &updated_server)?;
+    if let Some(server) = config.find_server_mut(&old_name) {
+        *server = updated_server.clone();
+    }
+    
+    save_config(&config)?;
+    print_success(&format!("Updated server: {}", updated_server));
+    update_secrets(&config.settings, &[], Some((&old_name, &updated_server.qualified_name())));
+    notify_change(&config, "edit", &[&updated_server]);
+    Ok(())
+}
+
+fn handle_set_fields(identifier: String, changes: Vec<(String, String)>) -> Result<()> {
+    let mut config = load_config()?;
+    
+    let server = config.find_server(&identifier)
+        .ok_or_else(|| server_not_found(&identifier))?;
+    server.ensure_unlocked()?;
+    let old_name = server.qualified_name();
+    
+    // Apply to a copy so nothing changes if any value is invalid
+    let mut updated_server = server.clone();
+    for (key, value) in &changes {
+        updated_server.set_field(key, value)?;
+    }
+    if updated_server == *server {
+        print_info("No changes made.");
+        return Ok(());
+    }
+    ensure_name_free(&config, &old_name, &updated_server)?;
+    if let Some(server) = config.find_server_mut(&old_name) {
+        *server = updated_server.clone();
+    }
+    
+    save_config(&config)?;
+    print_success(&format!("Updated server: {}", updated_server));
+    update_secrets(&config.settings, &[], Some((&old_name, &updated_server.qualified_name())));
+    notify_change(&config, "edit", &[&updated_server]);
+    Ok(())
+}
+
+fn handle_bulk_edit(filter: ServerFilter, changes: Vec<(String, String)>) -> Result<()> {
+    let mut config = load_config()?;
+    
+    // Apply the changes to copies first so nothing is saved if any value is invalid
+    let matching = config.filter_servers(&filter);
+    warn_locked(&matching);
+    
+    let mut updated = Vec::new();
+    for server in matching.into_iter().filter(|s| !s.locked) {
+        let mut new_server = server.clone();
+        for (key, value) in &changes {
+            new_server.set_field(key, value)
+                .map_err(|e| anyhow::anyhow!("{}: {}", server.name, e))?;
+        }
+        if new_server != *server {
+            updated.push((server.clone(), new_server));
+        }
+    }
+    // Moving servers into one group could also make their names clash with each other
+    for (index, (old, new)) in updated.iter().enumerate() {
+        let key = new.qualified_name();
+        if updated[..index].iter().any(|(_, other)| other.qualified_name() == key) {
+            return Err(anyhow::anyhow!("Server with name '{}' already exists", key));
+        }
+        if !updated.iter().any(|(other, _)| other.qualified_name() == key) {
+            ensure_name_free(&config, &old.qualified_name(), new)?;
+        }
+    }
+    
+    if updated.is_empty() {
+        print_info(&format!("No servers with {} need changes.", filter));
+        return Ok(());
+    }
+    
+    println!("{}", "Pending changes:".bold());
+    println!();
+    for (old, new) in &updated {
+        println!("  {}", old.name.green().bold());
+        for (key, _) in &changes {
+            let (before, after) = (old.field(key), new.field(key));
+            if before != after {
+                println!("    {}: {} -> {}", key, before.unwrap_or_default().red(), after.unwrap_or_default().green());
+            }
+        }
+    }
+    println!();
+    
+    if !confirm_action(&format!("Apply changes to {} server(s)?", updated.len()))? {
+        print_info("Operation cancelled.");
+        return Ok(());
+    }
+    
+    config.replace_servers(updated.iter().map(|(old, new)| (old.qualified_name(), new.clone())).collect());
+    
+    save_config(&config)?;
+    print_success(&format!("Updated {} server(s).", updated.len()));
+    for (old, new) in &updated {
+        update_secrets(&config.settings, &[], Some((&old.qualified_name(), &new.qualified_name())));
+    }
+    notify_change(&config, "edit", &updated.iter().map(|(_, new)| new).collect::<Vec<_>>());
+    Ok(())
+}
+
+fn handle_edit_in_editor(identifier: String) -> Result<()> {
+    let mut config = load_config()?;
+    
+    let original = config.find_server(&identifier)
+        .ok_or_else(|| server_not_found(&identifier))?
+        .clone();
+    original.ensure_unlocked()?;
+    
+    // Created with a random name, O_EXCL and mode 0600, and removed when dropped
+    let mut file = tempfile::Builder::new()
+        .prefix(&format!("hop-edit-{}-", original.name))
+        .suffix(".json")
+        .tempfile()
+        .map_err(|e| anyhow::anyhow!("Failed to create temporary file: {}", e))?;
+    file.write_all(serde_json::to_string_pretty(&original)?.as_bytes())
+        .and_then(|_| file.flush())
+        .map_err(|e| anyhow::anyhow!("Failed to write temporary file '{}': {}", file.path().display(), e))?;
+    let path = file.path().to_path_buf();
+    
+    let result = loop {
+        if let Err(e) = open_in_editor(&path) {
+            break Err(e);
+        }
+        if signals::interrupted().is_some() {
+            break Err(anyhow::anyhow!("Edit aborted. No changes were saved."));
+        }
+        
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read temporary file '{}': {}", path.display(), e))?;
+        
+        match parse_edited_server(&contents, &config, &original.qualified_name()) {
+            Ok(server) => break Ok(server),
+            Err(e) => {
+                print_error(&format!("{}", e));
+                if !confirm_action("Re-open the editor to fix it?")? {
+                    break Err(anyhow::anyhow!("Edit aborted. No changes were saved."));
+                }
+            }
+        }
+    };
+    drop(file);
+    let updated_server = result?;
+    
+    if updated_server == original {
+        print_info("No changes made.");
+        return Ok(());
+    }
+    
+    if let Some(server) = config.find_server_mut(&original.qualified_name()) {
+        *server = updated_server.clone();
+    }
+    
+    save_config(&config)?;
+    print_success(&format!("Updated server: {}", updated_server));
+    update_secrets(&config.settings, &[], Some((&original.qualified_name(), &updated_server.qualified_name())));
+    notify_change(&config, "edit", &[&updated_server]);
+    Ok(())
+}
+
+/// Parse and validate a server record edited by the user
+///
+/// `original_name` is the qualified name the server had before the edit.
+fn parse_edited_server(contents: &str, config: &config::Config, original_name: &str) -> Result<Server> {
+    let mut server: Server = serde_json::from_str(contents)
+        .map_err(|e| anyhow::anyhow!("Invalid server record: {}", e))?;
+    
+    if !is_valid_server_name(&server.name) {
+        return Err(anyhow::anyhow!("Invalid server name. Use only alphanumeric characters, hyphens, and underscores."));
+    }
+    
+    server.ip = utils::normalize_host(&server.ip).ok_or_else(|| models::invalid_host(&server.ip))?;
+    server.group = models::normalize_group(server.group.as_deref().unwrap_or_default())?;
+    ensure_name_free(config, original_name, &server)?;
+    
+    Ok(server)
+}
+
+/// Fail when an edit gives a server a name already taken in its (new) group
+fn ensure_name_free(config: &config::Config, old_name: &str, server: &Server) -> Result<()> {
+    if server.qualified_name() != old_name && config.contains(server) {
+        return Err(anyhow::anyhow!("Server with name '{}' already exists", server.qualified_name()));
+    }
+    Ok(())
+}
+
+fn handle_config(path: bool, init: bool) -> Result<()> {
+    if init {
+        init_config()?;
+        print_success("Configuration initialized successfully.");
+    }
+    
+    if path {
+        let config_path = get_config_file_path()?;
+        println!("Configuration file: {}", config_path.display());
+    }
+    
+    if !path && !init {
+        let config_path = get_config_file_path()?;
+        println!("Configuration file: {}", config_path.display());
+        
+        if config_path.exists() {
+            let config = load_config()?;
+            println!("Servers configured: {}", config.list_servers().len());
+        } else {
+            println!("Configuration file does not exist. Use --init to create it.");
+        }
+    }
+    
+    Ok(())
+}
+
+fn handle_config_action(action: ConfigAction) -> Result<()> {
+    match action {
+        ConfigAction::Get { key: Some(key) } => {
+            let config = load_config()?;
+            println!("{}", config.settings.get(&key)?);
+        }
+        ConfigAction::Get { key: None } => {
+            let config = load_config()?;
+            for key in Settings::KEYS {
+                println!("{} = {}", key, config.settings.get(key)?);
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let mut config = load_config()?;
+            config.settings.set(&key, &value)?;
+            save_config(&config)?;
+            print_success(&format!("Set {} = {}", key, config.settings.get(&key)?));
+            if cfg!(not(feature = "webhooks")) && key == "webhook-url" && config.settings.webhook_url.is_some() {
+                print_warning("This hop was built without the 'webhooks' feature, so no webhooks will be sent.");
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+fn handle_copy(server_id: String, source: String, destination: String, from: bool) -> Result<()> {
+    let config = load_config()?;
+    
+    let server = select_server(&config, &server_id)?;
+    
+    let ssh_client = ssh_client_for(&config.settings, server)?;
+    
+    if from {
+        ssh_client.copy_file_from(server, &source, &destination)?;
+    } else {
+        ssh_client.copy_file(server, &source, &destination)?;
+    }
+    
+    Ok(())
+}
+
+fn handle_exec(server_ids: String, command: String, notify: bool, sudo: bool, override_reason: Option<String>, fail_fast: bool, rollout: Rollout) -> Result<()> {
+    let config = load_config()?;
+    let servers = select_servers(&config, &server_ids)?;
+    
+    // Check the policy everywhere before running anything
+    let policy = policy::load_policy(config.settings.policy_file.as_deref())?;
+    let overrides = servers
+        .iter()
+        .map(|server| exec_override(&policy, server, &command, override_reason.as_deref()))
+        .collect::<Result<Vec<_>>>()?;
+    
+    if let [server] = servers[..] {
+        if config.requires_exec_confirmation(server) {
+            let message = format!("About to run '{}' on protected server {}.", command, server.name.red().bold());
+            if !confirm_by_typing(&message, &server.name)? {
+                print_info("Operation cancelled.");
+                return Ok(());
+            }
+        }
+        
+        let sudo_password = if sudo { Some(sudo_password_for(&config.settings, server)?) } else { None };
+        let started = Instant::now();
+        let result = run_exec(&config, server, &command, sudo_password.as_deref(), overrides[0].clone());
+        if notify {
+            let status = match &result {
+                Ok(_) => "succeeded".to_string(),
+                Err(e) => format!("failed (exit {})", exit_codes::exit_code_for(e)),
+            };
+            let body = format!("'{}' {} after {}", command, status, format_duration(started.elapsed().as_secs()));
+            if let Err(e) = notify::send(&format!("hop: {}", server.name), &body) {
+                print_warning(&format!("Failed to show notification: {:#}", e));
+            }
+        }
+        
+        print!("{}", result?);
+        return Ok(());
+    }
+    
+    // Ask every question before servers start running in parallel
+    let mut confirmed = Vec::new();
+    let mut sudo_passwords = Vec::new();
+    for server in &servers {
+        let message = format!("About to run '{}' on protected server {}.", command, server.name.red().bold());
+        let confirm = !config.requires_exec_confirmation(server) || confirm_by_typing(&message, &server.name)?;
+        confirmed.push(confirm);
+        sudo_passwords.push(if sudo && confirm { Some(sudo_password_for(&config.settings, server)?) } else { None });
+    }
+    
+    let width = servers.iter().map(|s| s.name.chars().count()).max().unwrap_or(0);
+    let started = Instant::now();
+    let executor = Executor::new(rollout);
+    let outcomes: Vec<Option<Result<String>>> = executor.run(&servers, |index, server| {
+        let prefix = utils::server_prefix(&server.name, index, width);
+        if !confirmed[index] {
+            println!("{} {}", prefix, "skipped".yellow());
+            return None;
+        }
+        
+        let result = run_exec(&config, server, &command, sudo_passwords[index].as_deref(), overrides[index].clone());
+        match &result {
+            Ok(output) => {
+                let mut stdout = std::io::stdout().lock();
+                for line in output.lines() {
+                    let _ = writeln!(stdout, "{} {}", prefix, line);
+                }
+            }
+            Err(e) => {
+                if fail_fast {
+                    executor.cancel();
+                }
+                let mut stderr = std::io::stderr().lock();
+                for line in format!("{:#}", e).lines().filter(|line| !line.trim().is_empty()) {
+                    let _ = writeln!(stderr, "{} {}", prefix, line.red());
+                }
+            }
+        }
+        Some(result)
+    }).into_iter().map(Option::flatten).collect();
+    if executor.is_cancelled() {
+        for (index, server) in servers.iter().enumerate().filter(|(index, _)| outcomes[*index].is_none() && confirmed[*index]) {
+            println!("{} {}", utils::server_prefix(&server.name, index, width), "skipped".yellow());
+        }
+    }
+    
+    let ok = outcomes.iter().filter(|o| matches!(o, Some(Ok(_)))).count();
+    let unreachable = outcomes
+        .iter()
+        .filter(|o| matches!(o, Some(Err(e)) if HopError::find(e).is_some_and(HopError::is_unreachable)))
+        .count();
+    let failed = outcomes.iter().filter(|o| matches!(o, Some(Err(_)))).count() - unreachable;
+    let skipped = outcomes.iter().filter(|o| o.is_none()).count();
+    
+    let mut summary = format!("{} ok, {} failed, {} unreachable", ok, failed, unreachable);
+    if skipped > 0 {
+        summary.push_str(&format!(", {} skipped", skipped));
+    }
+    println!();
+    println!("{}", summary.bold());
+    if notify {
+        let body = format!("'{}': {} after {}", command, summary, format_duration(started.elapsed().as_secs()));
+        if let Err(e) = notify::send(&format!("hop: {} servers", servers.len()), &body) {
+            print_warning(&format!("Failed to show notification: {:#}", e));
+        }
+    }
+    
+    if failed > 0 {
+        return Err(HopError::Failed(format!("'{}' failed on {} server(s).", command, failed + unreachable)).into());
+    }
+    if unreachable > 0 {
+        return Err(HopError::SshFailure { code: None, message: format!("{} server(s) unreachable.", unreachable) }.into());
+    }
+    Ok(())
+}
+
+/// Resolve a comma-separated list of server names or list numbers
+fn select_servers<'a>(config: &'a config::Config, ids: &str) -> Result<Vec<&'a Server>> {
+    let servers = ids
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| select_server(config, id))
+        .collect::<Result<Vec<_>>>()?;
+    if servers.is_empty() {
+        return Err(HopError::UsageError("No server given".to_string()).into());
+    }
+    Ok(servers)
+}
+
+/// Check `command` against the exec policy for `server`
+///
+/// Returns the justification to record when a denied command is overridden.
+fn exec_override(policy: &policy::Policy, server: &Server, command: &str, override_reason: Option<&str>) -> Result<Option<String>> {
+    match (policy.violation(server, command), override_reason) {
+        (None, _) => Ok(None),
+        (Some(rule), None) => {
+            let reason = rule.reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default();
+            Err(HopError::UsageError(
+                format!("The exec policy denies '{}' on {}: matches '{}'{}. Pass --override \"<justification>\" to run it anyway.", command, server.name, rule.pattern, reason),
+            ).into())
+        }
+        (Some(_), Some(justification)) if justification.trim().is_empty() => {
+            Err(HopError::UsageError("--override needs a justification".to_string()).into())
+        }
+        (Some(rule), Some(justification)) => {
+            print_warning(&format!("Overriding the exec policy rule '{}' on {}: {}", rule.pattern, server.name, justification));
+            Ok(Some(justification.to_string()))
+        }
+    }
+}
+
+/// Run `command` on one server (through sudo if asked) and record it in the audit log
+fn run_exec(config: &config::Config, server: &Server, command: &str, sudo_password: Option<&str>, override_reason: Option<String>) -> Result<String> {
+    let ssh_client = ssh_client_for(&config.settings, server)?;
+    let started = Instant::now();
+    let result = match sudo_password {
+        Some(password) => ssh_client.execute_sudo_command(server, command, password),
+        None => ssh_client.execute_command(server, command),
+    };
+    if sudo_password.is_some() && result.is_ok() {
+        save_sudo_password(server);
+    }
+    
+    let exit_code = match &result {
+        Ok(_) => 0,
+        Err(e) => exit_codes::exit_code_for(e),
+    };
+    let mut entry = audit::AuditEntry::new(&server.qualified_name(), command, exit_code, started.elapsed());
+    entry.override_reason = override_reason;
+    entry.annotations = hooks::after_exec(server, command, exit_code);
+    if let Err(e) = audit::record(&entry, config.settings.audit_syslog) {
+        print_warning(&format!("Failed to write audit log: {:#}", e));
+    }
+    result
+}
+
+fn handle_service(server_ids: String, action: service::ServiceAction, name: String, lines: u32, sudo: bool) -> Result<()> {
+    service::validate_name(&name)?;
+    let config = load_config()?;
+    let servers = select_servers(&config, &server_ids)?;
+    let command = service::command(action, &name, lines);
+    let policy = policy::load_policy(config.settings.policy_file.as_deref())?;
+    
+    let mut failed = 0;
+    for server in &servers {
+        if servers.len() > 1 {
+            println!("{}", format!("── {} ──", server.name).bold());
+        }
+        
+        if let Some(rule) = policy.violation(server, &command) {
+            let reason = rule.reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default();
+            print_error(&format!("The exec policy denies this on {}: matches '{}'{}", server.name, rule.pattern, reason));
+            failed += 1;
+            continue;
+        }
+        if action.is_mutating() && config.requires_exec_confirmation(server) {
+            let message = format!("About to {} {} on protected server {}.", action.name(), name, server.name.red().bold());
+            if !confirm_by_typing(&message, &server.name)? {
+                print_info("Skipped.");
+                continue;
+            }
+        }
+        
+        let ssh_client = ssh_client_for(&config.settings, server)?;
+        let started = Instant::now();
+        let result = if sudo {
+            let result = ssh_client.execute_sudo_command(server, &command, &sudo_password_for(&config.settings, server)?);
+            if result.is_ok() {
+                save_sudo_password(server);
+            }
+            result
+        } else {
+            ssh_client.execute_command(server, &command)
+        };
+        
+        let exit_code = match &result {
+            Ok(_) => 0,
+            Err(e) => exit_codes::exit_code_for(e),
+        };
+        let entry = audit::AuditEntry::new(&server.qualified_name(), &format!("service {} {}", action.name(), name), exit_code, started.elapsed());
+        if let Err(e) = audit::record(&entry, config.settings.audit_syslog) {
+            print_warning(&format!("Failed to write audit log: {:#}", e));
+        }
+        
+        match result {
+            Ok(output) => {
+                print!("{}", output);
+                if action.is_mutating() {
+                    print_success(&format!("{} {} on {}", action.name(), name, server.name));
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                print_error(&format!("{}: {:#}", server.name, e));
+            }
+        }
+    }
+    
+    if failed > 0 {
+        return Err(HopError::Failed(format!("'{} {}' failed on {} server(s).", action.name(), name, failed)).into());
+    }
+    Ok(())
+}
+
+/// A sudo password typed during this run, and whether to keep it in the keyring
+struct PromptedSudo {
+    password: String,
+    save: bool,
+}
+
+/// Passwords asked for during this run, by `user@host`
+static SUDO_PASSWORDS: Mutex<BTreeMap<String, PromptedSudo>> = Mutex::new(BTreeMap::new());
+
+fn sudo_login(server: &Server) -> String {
+    format!("{}@{}", server.user, server.ip)
+}
+
+/// The sudo password for `server`: the sudo-password secret, or a prompt
+///
+/// The prompt comes once per `user@host` in a run, so servers that share a
+/// login share the answer. Whether to keep it in the OS keyring is asked up
+/// front; `save_sudo_password` stores it once sudo has accepted it.
+fn sudo_password_for(settings: &Settings, server: &Server) -> Result<String> {
+    if let Some(password) = secrets::lookup(settings, &server.qualified_name(), secrets::SUDO_PASSWORD)? {
+        return Ok(password);
+    }
+    
+    let mut prompted = SUDO_PASSWORDS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(entry) = prompted.get(&sudo_login(server)) {
+        return Ok(entry.password.clone());
+    }
+    let password = prompt_secret(&format!("sudo password for {}@{}", server.user, server.name))?;
+    let save = cfg!(feature = "keyring") && confirm_action("Save it in the OS keyring for next time?")?;
+    prompted.insert(sudo_login(server), PromptedSudo { password: password.clone(), save });
+    Ok(password)
+}
+
+/// Keep the prompted sudo password for `server` in the OS keyring, if asked to
+///
+/// Called after a sudo command succeeded there, so a mistyped password is
+/// never saved.
+fn save_sudo_password(server: &Server) {
+    let prompted = SUDO_PASSWORDS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(entry) = prompted.get(&sudo_login(server)).filter(|entry| entry.save) else {
+        return;
+    };
+    match secrets::keyring_set(&server.qualified_name(), secrets::SUDO_PASSWORD, &entry.password) {
+        Ok(()) => print_success(&format!("Stored secret '{}' for {} in the OS keyring", secrets::SUDO_PASSWORD, server.name)),
+        Err(e) => print_warning(&format!("{:#}", e)),
+    }
+}
+
+/// Run `hop-<name>` from PATH when `name` is neither a subcommand nor a server name
+///
+/// Exact server names keep the `hop <server>` shortcut; list numbers and
+/// name prefixes only apply when no plugin matches.
+fn run_plugin() -> Result<Option<i32>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(name) = args.first() else {
+        return Ok(None);
+    };
+    if !plugin::is_plugin_name(name) || name == "help" || Cli::command().find_subcommand(name).is_some() {
+        return Ok(None);
+    }
+    
+    let config = load_config().ok();
+    if config.as_ref().is_some_and(|config| config.find_server(name).is_some()) {
+        return Ok(None);
+    }
+    let Some(path) = plugin::find(name) else {
+        return Ok(None);
+    };
+    
+    let server = match (&config, args.get(1)) {
+        (Some(config), Some(identifier)) => match config.resolve_server(identifier) {
+            Resolution::Found(server) => Some(server),
+            _ => None,
+        },
+        _ => None,
+    };
+    plugin::run(&path, &args[1..], &get_config_file_path()?, server).map(Some)
+}
+
+fn handle_import(file: String, merge: bool, allow_hooks: bool) -> Result<()> {
+    let reader = fs::File::open(&file)
+        .map(BufReader::new)
+        .map_err(|e| anyhow::anyhow!("Failed to read file '{}': {}", file, e))?;
+    
+    // Nothing is saved until the whole file has parsed, so a broken file changes nothing
+    let mut importer = Importer::start(merge, allow_hooks)?;
+    stream::read_servers(reader, |server| importer.add(server))?;
+    importer.finish()
+}
+
+fn handle_onepassword_import(vault: Option<String>) -> Result<()> {
+    let servers = onepassword::import_servers(vault.as_deref())?;
+    if servers.is_empty() {
+        print_info("No Server items with a host found in 1Password.");
+        return Ok(());
+    }
+    let mut importer = Importer::start(true, false)?;
+    for server in servers {
+        importer.add(server);
+    }
+    importer.finish()
+}
+
+/// Servers reported one by one before the import switches to progress lines
+const IMPORT_DETAIL_LIMIT: usize = 1000;
+
+/// Adds servers to the config as they are read; nothing is saved until `finish`
+///
+/// pre_connect/post_disconnect commands run on this machine at the next
+/// connect, so they are dropped unless `allow_hooks` is set and the user
+/// confirms them in `finish`.
+struct Importer {
+    config: config::Config,
+    imported: Vec<String>,
+    skipped: usize,
+    allow_hooks: bool,
+    /// Servers that came with local commands, and those commands
+    hooks: Vec<(String, Vec<String>)>,
+}
+
+impl Importer {
+    fn start(merge: bool, allow_hooks: bool) -> Result<Self> {
+        // Replacing the servers keeps the settings, including the webhook,
+        // and the locked servers; imported servers with their names are skipped
+        let mut config = load_config()?;
+        if !merge {
+            warn_locked(&config.list_servers().iter().collect::<Vec<_>>());
+            config.clear_unlocked_servers();
+        }
+        hooks::check()?;
+        Ok(Importer { config, imported: Vec::new(), skipped: 0, allow_hooks, hooks: Vec::new() })
+    }
+
+    fn add(&mut self, mut server: Server) {
+        let description = server.to_string();
+        // Stripped before hooks.rhai, which is the user's own and may add commands
+        if !self.allow_hooks && !server.strip_local_hooks().is_empty() {
+            print_warning(&format!("Dropped pre_connect/post_disconnect of {} (use --allow-hooks to keep them)", server.name));
+        }
+        let mut server = match hooks::on_import(server) {
+            Ok(Some(server)) => server,
+            Ok(None) => {
+                self.skipped += 1;
+                print_warning(&format!("Skipped (by hooks.rhai): {}", description));
+                return;
+            }
+            Err(e) => {
+                self.skipped += 1;
+                print_warning(&format!("Skipped {}: {:#}", description, e));
+                return;
+            }
+        };
+        server.added_at.get_or_insert(now_unix());
+        let description = server.to_string();
+        let name = server.qualified_name();
+        let commands = server.local_hook_commands();
+        if self.config.add_server(server).is_err() {
+            self.skipped += 1;
+            print_warning(&format!("Skipped (already exists): {}", description));
+            return;
+        }
+        
+        if self.allow_hooks && !commands.is_empty() {
+            self.hooks.push((name.clone(), commands));
+        }
+        self.imported.push(name);
+        let added = self.imported.len();
+        if added <= IMPORT_DETAIL_LIMIT {
+            print_success(&format!("Imported: {}", description));
+        } else if added.is_multiple_of(IMPORT_DETAIL_LIMIT) {
+            print_info(&format!("Imported {} servers so far...", added));
+        }
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if !self.hooks.is_empty() {
+            println!("These servers run local commands on this machine when you connect:");
+            for (name, commands) in &self.hooks {
+                for command in commands {
+                    println!("  {}: {}", name.bold(), command.yellow());
+                }
+            }
+            if !confirm_action("Keep these commands?")? {
+                for (name, _) in &self.hooks {
+                    if let Some(server) = self.config.find_server_mut(name) {
+                        server.strip_local_hooks();
+                    }
+                }
+                print_info("Dropped the commands; the servers are still imported.");
+            }
+        }
+        save_config(&self.config)?;
+        
+        print_success(&format!("Import complete. Added: {}, Skipped: {}", self.imported.len(), self.skipped));
+        let imported: Vec<&Server> = self.imported.iter().filter_map(|name| self.config.find_server(name)).collect();
+        notify_change(&self.config, "import", &imported);
+        Ok(())
+    }
+}
+
+/// Report changed servers to the webhook and the change-hook command
+fn notify_change(config: &config::Config, command: &str, servers: &[&Server]) {
+    let names: Vec<String> = servers.iter().map(|server| server.qualified_name()).collect();
+    notify_webhook(config, command, &names.iter().map(String::as_str).collect::<Vec<_>>());
+    if let Some(hook) = &config.settings.change_hook {
+        for server in servers {
+            local_hooks::run_change_hook(hook, command, server);
+        }
+    }
+}
+
+/// Send one webhook event per changed server; failures only produce a warning
+fn notify_webhook(config: &config::Config, command: &str, servers: &[&str]) {
+    let Some(url) = &config.settings.webhook_url else {
+        return;
+    };
+    for server in servers {
+        if let Err(e) = webhook::send(url, &webhook::Event::new(command, server), &config.settings) {
+            print_warning(&format!("{:#}", e));
+        }
+    }
+}
+
+fn handle_export(file: String, pretty: bool, format: ExportFormat) -> Result<()> {
+    let config = load_config()?;
+    let servers = config.list_servers();
+    
+    let report = match format {
+        ExportFormat::Json | ExportFormat::Jsonl => None,
+        ExportFormat::Markdown | ExportFormat::Html => {
+            let usage = history::usage_by_server(&history::load_history()?);
+            let health = health::load_health()?;
+            let rows = export::InventoryRow::collect(servers, &usage, &health);
+            if format == ExportFormat::Markdown {
+                Some(export::render_markdown(&rows, now_unix()))
+            } else {
+                Some(export::render_html(&rows, now_unix()))
+            }
+        }
+    };
+    
+    // Server lists are serialized straight into the file rather than built up as one string
+    let written = fs::File::create(&file).map_err(anyhow::Error::from).and_then(|out| {
+        let mut writer = std::io::BufWriter::new(out);
+        match report {
+            Some(report) => writer.write_all(report.as_bytes())?,
+            None if format == ExportFormat::Jsonl => stream::write_lines(&mut writer, servers)?,
+            None if pretty => serde_json::to_writer_pretty(&mut writer, servers)?,
+            None => serde_json::to_writer(&mut writer, servers)?,
+        }
+        writer.flush()?;
+        Ok(())
+    });
+    written.map_err(|e| anyhow::anyhow!("Failed to write file '{}': {}", file, e))?;
+    
+    print_success(&format!("Exported {} servers to '{}'", servers.len(), file));
+    Ok(())
+}
+
+fn handle_shell_init(shell: shell_init::Shell) -> Result<()> {
+    let config = load_config()?;
+    let names: Vec<&str> = config.list_servers().iter().map(|s| s.name.as_str()).collect();
+    
+    print!("{}", shell_init::render(shell, &names));
+    Ok(())
+}
+
+/// Reads only the server index, so it stays fast with a large or remote inventory
+fn handle_recent(count: usize) -> Result<()> {
+    let entries: Vec<index::IndexEntry> = index::by_recent_use(index::load_index()?)
+        .into_iter()
+        .filter(|entry| entry.last_used.is_some())
+        .take(count)
+        .collect();
+    if entries.is_empty() {
+        print_info("No connections yet. Use 'hop connect <server>' to connect to one.");
+        return Ok(());
+    }
+    
+    let width = entries.iter().map(|e| e.name.chars().count()).max().unwrap_or(0);
+    for entry in &entries {
+        let ago = entry.last_used.map(format_time_ago).unwrap_or_default();
+        let tags = if entry.tags.is_empty() { String::new() } else { format!("[{}]", entry.tags.join(", ")) };
+        println!("  {:<width$}  {:<8}  {}", entry.name.bold(), ago.dimmed(), tags.cyan());
+    }
+    Ok(())
+}
+
+/// Used by the shell completion and the Alt-h picker from `hop shell-init`
+fn handle_complete(prefix: &str) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    for entry in index::by_recent_use(index::load_index()?) {
+        if entry.name.starts_with(prefix) {
+            writeln!(stdout, "{}", entry.name)?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_key(action: KeyAction) -> Result<()> {
+    match action {
+        KeyAction::Gen { key_type, file, comment, resident, verify_required } => {
+            if (resident || verify_required) && !key_type.is_security_key() {
+                return Err(HopError::UsageError(
+                    "--resident and --verify-required only apply to ed25519-sk and ecdsa-sk keys".to_string(),
+                ).into());
+            }
+            let file = match file {
+                Some(file) => file,
+                None => {
+                    let home = dirs::home_dir()
+                        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+                    key_type.default_file(&home.join(".ssh"))
+                }
+            };
+            if file.exists() {
+                return Err(anyhow::anyhow!("{} already exists; pick another file with --file", file.display()));
+            }
+            if let Some(parent) = file.parent().filter(|p| !p.as_os_str().is_empty()) {
+                utils::ensure_dir_exists(parent)?;
+            }
+            
+            if key_type.is_security_key() {
+                print_info("Touch your security key when it blinks; you may be asked for its PIN first.");
+            }
+            let options = keys::KeygenOptions { key_type, file, comment, resident, verify_required };
+            let mut command = keys::keygen_command(&options);
+            debug!("argv: {:?}", command);
+            let status = signals::status(&mut command)
+                .map_err(|e| exit_codes::spawn_failure("ssh-keygen", e))?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("ssh-keygen failed with exit code: {}", status.code().unwrap_or(-1)));
+            }
+            
+            print_success(&format!("Created {}.pub", options.file.display()));
+            if resident {
+                print_hint("Load this key on another machine with: ssh-add -K");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_secret(action: SecretAction) -> Result<()> {
+    let config = load_config()?;
+    let mut store = secrets::load_secrets(&config.settings)?;
+    
+    match action {
+        SecretAction::Set { server, name, stdin, keyring } => {
+            let server = select_server(&config, &server)?;
+            let key = server.qualified_name();
+            let value = if stdin {
+                let mut value = String::new();
+                std::io::stdin().read_to_string(&mut value)?;
+                value.trim_end_matches(['\r', '\n']).to_string()
+            } else {
+                prompt_secret(&format!("Value for {} on {}", name, server.name))?
+            };
+            if name == secrets::TOTP_SEED {
+                totp::decode_seed(&value)?;
+            }
+            if keyring {
+                secrets::keyring_set(&key, &name, &value)?;
+                print_success(&format!("Stored secret '{}' for {} in the OS keyring", name, server.name));
+                return Ok(());
+            }
+            let cipher = secrets::unlock(&mut store)?;
+            store.set(&cipher, &key, &name, &value)?;
+            secrets::save_secrets(&store)?;
+            print_success(&format!("Stored secret '{}' for {}", name, server.name));
+        }
+        SecretAction::Get { server, name } => {
+            let server = select_server(&config, &server)?;
+            let value = secrets::lookup(&config.settings, &server.qualified_name(), &name)?
+                .ok_or_else(|| anyhow::anyhow!("No secret '{}' for {}", name, server.name))?;
+            println!("{}", value);
+        }
+        SecretAction::List { server } => {
+            let servers = match &server {
+                Some(id) => vec![select_server(&config, id)?.qualified_name()],
+                None => store.servers().into_iter().map(String::from).collect(),
+            };
+            for server in &servers {
+                let names = store.names(server);
+                if !names.is_empty() {
+                    println!("{}: {}", server.bold(), names.join(", "));
+                }
+            }
+        }
+        SecretAction::Remove { server, name } => {
+            let server = select_server(&config, &server)?;
+            let key = server.qualified_name();
+            let in_keyring = secrets::keyring_remove(&key, &name).unwrap_or(false);
+            if store.remove(&key, &name) {
+                secrets::save_secrets(&store)?;
+            } else if !in_keyring {
+                return Err(anyhow::anyhow!("No secret '{}' for {}", name, server.name));
+            }
+            print_success(&format!("Removed secret '{}' from {}", name, server.name));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Keep secrets in step with a removed or renamed server; failures only warn
+///
+/// The OS keyring cannot be enumerated, so only the secrets hop knows by name
+/// are moved there.
+fn update_secrets(settings: &Settings, removed: &[&str], renamed: Option<(&str, &str)>) {
+    for secret in secrets::KNOWN {
+        for name in removed {
+            let _ = secrets::keyring_remove(name, secret);
+        }
+        if let Some((old, new)) = renamed.filter(|(old, new)| old != new) {
+            if let Ok(Some(value)) = secrets::keyring_get(old, secret) {
+                if secrets::keyring_set(new, secret, &value).is_ok() {
+                    let _ = secrets::keyring_remove(old, secret);
+                }
+            }
+        }
+    }
+    
+    let result = secrets::load_secrets(settings).and_then(|mut store| {
+        let mut changed = false;
+        for name in removed {
+            changed |= store.forget_server(name);
+        }
+        if let Some((old, new)) = renamed {
+            changed |= old != new && store.rename_server(old, new);
+        }
+        if changed {
+            secrets::save_secrets(&store)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        print_warning(&format!("Failed to update secrets: {:#}", e));
+    }
+}
+
+fn handle_man(command: Option<String>) -> Result<()> {
+    let mut cmd = Cli::command();
+    cmd.build();
+    
+    let page = match &command {
+        Some(name) => {
+            let sub = cmd.find_subcommand(name)
+                .ok_or_else(|| HopError::UsageError(format!("Unknown command '{}'", name)))?;
+            sub.clone().name(format!("hop-{}", sub.get_name()))
+        }
+        None => cmd,
+    };
+    
+    docs::render_man_page(&page, &mut std::io::stdout().lock())
+}
+
+fn handle_docs_generate(dir: &Path) -> Result<()> {
+    let mut cmd = Cli::command();
+    cmd.build();
+    
+    let man_dir = dir.join("man1");
+    fs::create_dir_all(&man_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create '{}': {}", man_dir.display(), e))?;
+    let pages = docs::write_man_pages(&cmd, &man_dir)?;
+    
+    let reference = dir.join("reference.md");
+    fs::write(&reference, docs::render_markdown(&cmd))
+        .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", reference.display(), e))?;
+    
+    print_success(&format!("Wrote {} man pages to '{}' and '{}'", pages.len(), man_dir.display(), reference.display()));
+    Ok(())
+}
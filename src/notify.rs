@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::process::Command;
+
+/// Show a desktop notification using the platform's own tooling
+///
+/// Linux uses `notify-send`, macOS `osascript` and Windows a PowerShell toast.
+pub fn send(title: &str, body: &str) -> Result<()> {
+    let mut command = notification_command(title, body);
+    debug!("argv: {:?}", command);
+    
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run {:?}", command.get_program()))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{:?} exited with {}", command.get_program(), status));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn notification_command(title: &str, body: &str) -> Command {
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!(
+        "display notification {} with title {}",
+        applescript_quote(body),
+        applescript_quote(title)
+    ));
+    command
+}
+
+#[cfg(windows)]
+fn notification_command(title: &str, body: &str) -> Command {
+    let script = format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null; \
+         $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $text = $xml.GetElementsByTagName('text'); \
+         $text.Item(0).AppendChild($xml.CreateTextNode({})) | Out-Null; \
+         $text.Item(1).AppendChild($xml.CreateTextNode({})) | Out-Null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('hop').Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+        powershell_quote(title),
+        powershell_quote(body)
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn notification_command(title: &str, body: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name=hop", title, body]);
+    command
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn applescript_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(applescript_quote(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+        assert_eq!(powershell_quote("it's done"), "'it''s done'");
+    }
+}
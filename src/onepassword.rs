@@ -0,0 +1,120 @@
+use crate::models::{Server, ONEPASSWORD_AGENT};
+use anyhow::{Context, Result};
+use log::debug;
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Fetch the Server items from 1Password through the `op` CLI
+///
+/// Each item becomes a hop server that authenticates through the 1Password
+/// SSH agent. Items without a host are skipped.
+pub fn import_servers(vault: Option<&str>) -> Result<Vec<Server>> {
+    let mut list = Command::new("op");
+    list.args(["item", "list", "--categories", "Server", "--format", "json"]);
+    if let Some(vault) = vault {
+        list.args(["--vault", vault]);
+    }
+    let items = run_op(&mut list, None)?;
+    
+    // `op item get -` reads the list from stdin and prints every full item
+    let details = run_op(Command::new("op").args(["item", "get", "-", "--format", "json"]), Some(&items))?;
+    
+    let mut servers = Vec::new();
+    for item in serde_json::Deserializer::from_str(&details).into_iter::<Value>() {
+        let item = item.context("Failed to parse 1Password output")?;
+        match server_from_item(&item) {
+            Some(server) => servers.push(server),
+            None => debug!("1password: skipping item without host: {}", item["title"]),
+        }
+    }
+    Ok(servers)
+}
+
+fn run_op(command: &mut Command, input: Option<&str>) -> Result<String> {
+    debug!("argv: {:?}", command);
+    let mut child = command
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run the 1Password CLI (`op`); is it installed and signed in?")?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("op failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Map a 1Password item to a server: title -> name, URL/hostname -> host, username -> user
+fn server_from_item(item: &Value) -> Option<Server> {
+    let fields = item["fields"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let field = |names: &[&str]| {
+        fields.iter().find_map(|field| {
+            let id = field["id"].as_str().unwrap_or_default();
+            let label = field["label"].as_str().unwrap_or_default();
+            let matches = names.iter().any(|n| id.eq_ignore_ascii_case(n) || label.eq_ignore_ascii_case(n));
+            field["value"].as_str().filter(|v| matches && !v.is_empty())
+        })
+    };
+    
+    let address = field(&["url", "hostname", "host", "server"])
+        .or_else(|| item["urls"][0]["href"].as_str())?;
+    let host = host_from_address(address)?;
+    let user = field(&["username", "user"]).unwrap_or("root");
+    let name = server_name(item["title"].as_str().unwrap_or(host));
+    
+    let mut server = Server::new(name, user.to_string(), host.to_string());
+    server.identity_agent = Some(ONEPASSWORD_AGENT.to_string());
+    Some(server)
+}
+
+/// Strip scheme, credentials, port and path from a URL-ish address
+fn host_from_address(address: &str) -> Option<&str> {
+    let rest = address.split_once("://").map_or(address, |(_, rest)| rest);
+    let rest = rest.split('/').next()?;
+    let rest = rest.rsplit_once('@').map_or(rest, |(_, host)| host);
+    let host = rest.split(':').next()?.trim();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Turn an item title into a valid server name, e.g. "Prod DB (EU)" -> "prod-db-eu"
+fn server_name(title: &str) -> String {
+    let name: String = title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' })
+        .collect();
+    name.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_from_item() {
+        let item: Value = serde_json::from_str(r#"{
+            "title": "Prod DB (EU)",
+            "category": "SERVER",
+            "fields": [
+                {"id": "url", "label": "URL", "value": "ssh://10.0.0.5:2222"},
+                {"id": "username", "label": "username", "value": "postgres"}
+            ]
+        }"#).unwrap();
+
+        let server = server_from_item(&item).unwrap();
+        assert_eq!(server.name, "prod-db-eu");
+        assert_eq!(server.user, "postgres");
+        assert_eq!(server.ip, "10.0.0.5");
+        assert_eq!(server.identity_agent.as_deref(), Some(ONEPASSWORD_AGENT));
+
+        let empty: Value = serde_json::from_str(r#"{"title": "No host", "fields": []}"#).unwrap();
+        assert!(server_from_item(&empty).is_none());
+    }
+}
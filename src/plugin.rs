@@ -0,0 +1,85 @@
+use crate::models::Server;
+use crate::signals;
+use anyhow::Result;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Executables named `hop-<name>` on PATH provide `hop <name>`
+pub const PREFIX: &str = "hop-";
+
+/// Whether `name` could be a plugin subcommand rather than a flag or a path
+pub fn is_plugin_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('-') && !name.contains(['/', '\\'])
+}
+
+/// Find the `hop-<name>` executable on PATH
+pub fn find(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    find_in(env::split_paths(&path), name)
+}
+
+fn find_in(dirs: impl IntoIterator<Item = PathBuf>, name: &str) -> Option<PathBuf> {
+    let file = format!("{}{}{}", PREFIX, name, env::consts::EXE_SUFFIX);
+    dirs.into_iter().map(|dir| dir.join(&file)).find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Run a plugin with `args` and return its exit code
+///
+/// The plugin gets HOP_BIN (this executable) and HOP_CONFIG (servers.json)
+/// in its environment, plus HOP_SERVER and HOP_SERVER_JSON when its first
+/// argument names a configured server.
+pub fn run(plugin: &Path, args: &[String], config_path: &Path, server: Option<&Server>) -> Result<i32> {
+    let mut command = Command::new(plugin);
+    command.args(args).env("HOP_CONFIG", config_path);
+    if let Ok(exe) = env::current_exe() {
+        command.env("HOP_BIN", exe);
+    }
+    if let Some(server) = server {
+        command.env("HOP_SERVER", &server.name).env("HOP_SERVER_JSON", serde_json::to_string(server)?);
+    }
+
+    let status = signals::status(&mut command)
+        .map_err(|e| anyhow::anyhow!("Failed to run plugin {}: {}", plugin.display(), e))?;
+    // A plugin killed by a signal reports no code
+    Ok(status.code().unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_plugin_name() {
+        assert!(is_plugin_name("deploy"));
+        assert!(!is_plugin_name("--version"));
+        assert!(!is_plugin_name("../deploy"));
+        assert!(!is_plugin_name(""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_in_needs_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = dir.path().join("hop-deploy");
+        std::fs::write(&plugin, "#!/bin/sh\n").unwrap();
+        assert_eq!(find_in([dir.path().to_path_buf()], "deploy"), None);
+
+        std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(find_in([dir.path().to_path_buf()], "deploy"), Some(plugin));
+        assert_eq!(find_in([dir.path().to_path_buf()], "other"), None);
+    }
+}
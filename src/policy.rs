@@ -0,0 +1,96 @@
+use crate::models::Server;
+use crate::utils::glob_match;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// System-wide policy that admins can ship with hop
+const SYSTEM_POLICY: &str = "/etc/hop/policy.json";
+
+/// Commands `hop exec` refuses to run without `--override`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    #[serde(default)]
+    pub deny: Vec<Rule>,
+}
+
+/// One denied command pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Glob matched against the whole command, e.g. `*rm -rf /*` or `mkfs*`
+    pub pattern: String,
+    /// Only applies to servers with any of these tags; empty means every server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Shown when the rule blocks a command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Rule {
+    fn applies(&self, server: &Server, command: &str) -> bool {
+        (self.tags.is_empty() || self.tags.iter().any(|tag| server.has_tag(tag)))
+            && glob_match(&self.pattern, command.trim())
+    }
+}
+
+impl Policy {
+    /// The first rule that denies running `command` on `server`
+    pub fn violation(&self, server: &Server, command: &str) -> Option<&Rule> {
+        self.deny.iter().find(|rule| rule.applies(server, command))
+    }
+}
+
+/// Find the policy file: the `policy-file` setting, then /etc/hop/policy.json,
+/// then policy.json in hop's config directory
+pub fn get_policy_file_path(configured: Option<&str>) -> Result<Option<PathBuf>> {
+    if let Some(path) = configured {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    let system = PathBuf::from(SYSTEM_POLICY);
+    if system.exists() {
+        return Ok(Some(system));
+    }
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+    let user = config_dir.join("hop").join("policy.json");
+    Ok(user.exists().then_some(user))
+}
+
+/// Load the policy, or an empty one when there is no policy file
+///
+/// A configured file that is missing or broken is an error, so a typo never
+/// silently disables the policy.
+pub fn load_policy(configured: Option<&str>) -> Result<Policy> {
+    let Some(path) = get_policy_file_path(configured)? else {
+        return Ok(Policy::default());
+    };
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read policy file: {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse policy file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violation() {
+        let policy: Policy = serde_json::from_str(r#"{"deny": [
+            {"pattern": "*rm -rf /", "reason": "wipes the root filesystem"},
+            {"pattern": "mkfs*"},
+            {"pattern": "*reboot*", "tags": ["prod"]}
+        ]}"#).unwrap();
+
+        let mut server = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        assert_eq!(policy.violation(&server, "sudo rm -rf /").unwrap().reason.as_deref(), Some("wipes the root filesystem"));
+        assert!(policy.violation(&server, "rm -rf /tmp/cache").is_none());
+        assert!(policy.violation(&server, "mkfs.ext4 /dev/sdb1").is_some());
+        assert!(policy.violation(&server, "sudo reboot").is_none());
+
+        server.tags.push("prod".to_string());
+        assert!(policy.violation(&server, "sudo reboot").is_some());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::health;
+use crate::utils::shell_quote;
+use anyhow::Result;
+
+/// Remote probe: POSIX df in 1K blocks, skipping pseudo filesystems where GNU df supports it
+pub const DISK_COMMAND: &str =
+    "df -P -k -x tmpfs -x devtmpfs -x squashfs -x overlay 2>/dev/null || df -P -k";
+
+/// One mounted filesystem as reported by `df -P`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filesystem {
+    pub source: String,
+    pub size_kb: u64,
+    pub used_kb: u64,
+    pub available_kb: u64,
+    pub percent: u8,
+    pub mount: String,
+}
+
+/// Parse the output of `DISK_COMMAND`, skipping the header and filesystems without a size
+pub fn parse_df(output: &str) -> Result<Vec<Filesystem>> {
+    let mut lines = output.lines();
+    let header = lines.next().unwrap_or_default();
+    if !header.starts_with("Filesystem") {
+        return Err(anyhow::anyhow!("Unexpected df output"));
+    }
+
+    let mut filesystems = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            continue;
+        }
+        let number = |i: usize| fields[i].parse::<u64>().ok();
+        let (Some(size_kb), Some(used_kb), Some(available_kb)) = (number(1), number(2), number(3)) else {
+            continue;
+        };
+        let Ok(percent) = fields[4].trim_end_matches('%').parse() else {
+            continue;
+        };
+        if size_kb == 0 {
+            continue;
+        }
+
+        filesystems.push(Filesystem {
+            source: fields[0].to_string(),
+            size_kb,
+            used_kb,
+            available_kb,
+            percent,
+            // Mount points may contain spaces
+            mount: fields[5..].join(" "),
+        });
+    }
+    Ok(filesystems)
+}
+
+/// Remote probe: count pending package updates with whichever package manager
+/// the server has, printing `<manager> <total> <security>` (`-` when unknown)
+///
+/// Nothing is refreshed; the counts reflect the last metadata update
+/// (e.g. `apt-get update`) on the server.
+const UPDATES_COMMAND: &str = "\
+if command -v apt-get >/dev/null 2>&1; then \
+u=$(apt-get -s upgrade 2>/dev/null | grep '^Inst '); \
+echo \"apt $(printf '%s' \"$u\" | grep -c .) $(printf '%s' \"$u\" | grep -ci security)\"; \
+elif command -v dnf >/dev/null 2>&1; then \
+echo \"dnf $(dnf -q check-update 2>/dev/null | grep -c '^[[:alnum:]].*\\.') $(dnf -q updateinfo list --security 2>/dev/null | grep -c .)\"; \
+elif command -v yum >/dev/null 2>&1; then \
+echo \"yum $(yum -q check-update 2>/dev/null | grep -c '^[[:alnum:]].*\\.') $(yum -q updateinfo list security 2>/dev/null | grep -c .)\"; \
+elif command -v pacman >/dev/null 2>&1; then \
+echo \"pacman $( (checkupdates 2>/dev/null || pacman -Qu 2>/dev/null) | grep -c .) -\"; \
+elif command -v apk >/dev/null 2>&1; then \
+echo \"apk $(apk -u list 2>/dev/null | grep -c .) -\"; \
+else echo unknown; fi";
+
+/// Pending package updates on one server
+#[derive(Debug, Clone, PartialEq)]
+pub struct Updates {
+    pub manager: String,
+    pub total: u32,
+    /// Security updates, when the package manager can tell them apart
+    pub security: Option<u32>,
+    /// Whether a reboot is pending, when the distribution gives a hint
+    pub reboot_required: Option<bool>,
+}
+
+/// `UPDATES_COMMAND` followed by the pending-reboot check
+pub fn updates_command() -> String {
+    format!("{}; {}", UPDATES_COMMAND, health::REBOOT_CHECK)
+}
+
+/// Parse the output of `updates_command`
+pub fn parse_updates(output: &str) -> Result<Updates> {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    if line == "unknown" {
+        return Err(anyhow::anyhow!("No supported package manager (apt, dnf, yum, pacman, apk)"));
+    }
+    let invalid = || anyhow::anyhow!("Unexpected output from the update check: {}", line);
+    
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [manager, total, security] = fields[..] else {
+        return Err(invalid());
+    };
+    Ok(Updates {
+        manager: manager.to_string(),
+        total: total.parse().map_err(|_| invalid())?,
+        security: match security {
+            "-" => None,
+            count => Some(count.parse().map_err(|_| invalid())?),
+        },
+        reboot_required: health::parse_reboot(output),
+    })
+}
+
+/// A certificate to check: one served on a port, or a PEM file on the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum CertTarget {
+    Port(u16),
+    File(String),
+}
+
+impl std::fmt::Display for CertTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CertTarget::Port(port) => write!(f, ":{}", port),
+            CertTarget::File(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+/// Remote probe: print `== <target>` followed by the certificate's notAfter
+/// and subject lines for each target, using openssl on the server
+///
+/// Ports are checked on localhost with `server_name` for SNI.
+pub fn certs_command(targets: &[CertTarget], server_name: &str) -> String {
+    targets
+        .iter()
+        .map(|target| {
+            let read = match target {
+                CertTarget::Port(port) => format!(
+                    "echo | openssl s_client -connect localhost:{} -servername {} 2>/dev/null | openssl x509 -noout -enddate -subject 2>/dev/null",
+                    port,
+                    shell_quote(server_name)
+                ),
+                CertTarget::File(path) => format!("openssl x509 -noout -enddate -subject -in {} 2>/dev/null", shell_quote(path)),
+            };
+            // A missing certificate is reported per target, not as a failed command
+            format!("echo {}; {} || true", shell_quote(&format!("== {}", target)), read)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Expiry of one certificate
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertInfo {
+    pub target: String,
+    pub subject: String,
+    /// Unix timestamp of notAfter
+    pub expires: u64,
+}
+
+/// Parse the output of `certs_command`; targets without a readable
+/// certificate come back as errors
+pub fn parse_certs(output: &str) -> Vec<(String, Result<CertInfo>)> {
+    let mut results = Vec::new();
+    let mut current: Option<(String, Option<u64>, String)> = None;
+    let finish = |entry: (String, Option<u64>, String)| {
+        let (target, expires, subject) = entry;
+        let result = match expires {
+            Some(expires) => Ok(CertInfo { target: target.clone(), subject, expires }),
+            None => Err(anyhow::anyhow!("No certificate found (is openssl installed and the port serving TLS?)")),
+        };
+        (target, result)
+    };
+
+    for line in output.lines().map(str::trim) {
+        if let Some(target) = line.strip_prefix("== ") {
+            results.extend(current.take().map(finish));
+            current = Some((target.to_string(), None, String::new()));
+        } else if let Some((_, expires, subject)) = current.as_mut() {
+            if let Some(date) = line.strip_prefix("notAfter=") {
+                *expires = parse_openssl_date(date);
+            } else if let Some(value) = line.strip_prefix("subject=") {
+                *subject = value.trim().to_string();
+            }
+        }
+    }
+    results.extend(current.map(finish));
+    results
+}
+
+/// Parse an openssl date such as "Jan  1 00:00:00 2027 GMT" into a Unix timestamp
+fn parse_openssl_date(date: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let fields: Vec<&str> = date.split_whitespace().collect();
+    let [month, day, time, year, ..] = fields[..] else {
+        return None;
+    };
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+    let day: i64 = day.parse().ok()?;
+    let year: i64 = year.parse().ok()?;
+    let mut clock = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (clock.next()??, clock.next()??, clock.next()??);
+
+    // Days-from-civil conversion (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = u64::try_from(era * 146_097 + day_of_era - 719_468).ok()?;
+
+    Some(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Filesystem     1024-blocks     Used Available Capacity Mounted on\n\
+                      /dev/sda1         41152736 36000000   3040000      93% /\n\
+                      /dev/sdb1        103081248 20000000  77800000      21% /mnt/my data\n\
+                      proc                     0        0         0       - /proc\n";
+        let filesystems = parse_df(output).unwrap();
+        assert_eq!(filesystems.len(), 2);
+        assert_eq!(filesystems[0].percent, 93);
+        assert_eq!(filesystems[0].mount, "/");
+        assert_eq!(filesystems[1].mount, "/mnt/my data");
+        assert_eq!(filesystems[1].available_kb, 77800000);
+
+        assert!(parse_df("df: unknown option\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_updates() {
+        let apt = parse_updates("apt 12 3\nreboot-required\n").unwrap();
+        assert_eq!(apt, Updates { manager: "apt".to_string(), total: 12, security: Some(3), reboot_required: Some(true) });
+
+        let pacman = parse_updates("pacman 40 -\n").unwrap();
+        assert_eq!(pacman.security, None);
+        assert_eq!(pacman.reboot_required, None);
+
+        assert!(parse_updates("unknown\n").is_err());
+        assert!(parse_updates("apt twelve 3\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_certs() {
+        let output = "== :443\nnotAfter=Jan  1 00:00:00 2027 GMT\nsubject=CN = example.com\n\
+                      == :8443\n\
+                      == /etc/ssl/app.pem\nnotAfter=Mar 15 12:30:00 2024 GMT\nsubject=CN=app\n";
+        let certs = parse_certs(output);
+        assert_eq!(certs.len(), 3);
+
+        let first = certs[0].1.as_ref().unwrap();
+        assert_eq!(first.expires, 1_798_761_600);
+        assert_eq!(first.subject, "CN = example.com");
+        assert!(certs[1].1.is_err());
+        assert_eq!(certs[2].0, "/etc/ssl/app.pem");
+        assert_eq!(certs[2].1.as_ref().unwrap().expires, 1_710_505_800);
+
+        let command = certs_command(&[CertTarget::Port(443), CertTarget::File("/etc/my cert.pem".to_string())], "web.example.com");
+        assert!(command.contains("-connect localhost:443 -servername 'web.example.com'"));
+        assert!(command.contains("-in '/etc/my cert.pem'"));
+    }
+}
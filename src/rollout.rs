@@ -0,0 +1,57 @@
+use crate::signals;
+use crate::utils::{format_duration, print_info};
+use std::ops::Range;
+use std::time::Duration;
+
+/// How a fleet command spreads its work over servers: how many at once,
+/// optionally in batches with a pause between them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rollout {
+    /// Servers worked on at the same time
+    pub parallel: usize,
+    /// Servers per batch; all servers form one batch when unset
+    pub batch: Option<usize>,
+    /// Pause after each batch but the last
+    pub batch_delay: Duration,
+}
+
+impl Rollout {
+    /// All servers in one batch, `parallel` at a time
+    pub fn parallel(parallel: usize) -> Self {
+        Rollout { parallel, batch: None, batch_delay: Duration::ZERO }
+    }
+
+    /// Index ranges of the batches for `count` servers
+    pub fn batches(&self, count: usize) -> Vec<Range<usize>> {
+        let size = self.batch.unwrap_or(count).max(1);
+        (0..count).step_by(size).map(|start| start..(start + size).min(count)).collect()
+    }
+
+    /// Wait between batches, after `done` of `total` batches finished
+    pub fn pause(&self, done: usize, total: usize) {
+        if self.batch_delay.is_zero() {
+            return;
+        }
+        print_info(&format!(
+            "Batch {}/{} done, waiting {} before the next one...",
+            done,
+            total,
+            format_duration(self.batch_delay.as_secs())
+        ));
+        signals::sleep(self.batch_delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches() {
+        assert_eq!(Rollout::parallel(8).batches(5), vec![0..5]);
+        assert!(Rollout::parallel(8).batches(0).is_empty());
+
+        let rolling = Rollout { parallel: 2, batch: Some(2), batch_delay: Duration::from_secs(30) };
+        assert_eq!(rolling.batches(5), vec![0..2, 2..4, 4..5]);
+    }
+}
@@ -0,0 +1,393 @@
+use crate::config::{check_permissions, Settings};
+use crate::utils::{ensure_dir_exists, write_private};
+#[cfg(feature = "encryption")]
+use crate::utils::{is_interactive, prompt_secret};
+use anyhow::{Context, Result};
+use log::debug;
+#[cfg(feature = "encryption")]
+use argon2::Argon2;
+#[cfg(feature = "encryption")]
+use base64::engine::general_purpose::STANDARD as BASE64;
+#[cfg(feature = "encryption")]
+use base64::Engine;
+#[cfg(feature = "encryption")]
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(feature = "encryption")]
+use std::sync::Mutex;
+
+/// Environment variable that supplies the passphrase non-interactively
+#[cfg(feature = "encryption")]
+pub const PASSPHRASE_ENV: &str = "HOP_SECRETS_PASSPHRASE";
+/// Secret holding the login password for servers that only allow password auth
+pub const PASSWORD: &str = "password";
+/// Secret holding the base32 seed for servers that ask for a TOTP code at login
+pub const TOTP_SEED: &str = "totp-seed";
+/// Secret holding the password `hop exec --sudo` gives to sudo
+pub const SUDO_PASSWORD: &str = "sudo-password";
+/// The secrets hop itself reads, the only ones it can find in the OS keyring
+pub const KNOWN: &[&str] = &[PASSWORD, TOTP_SEED, SUDO_PASSWORD];
+/// Service name for hop's entries in the OS keyring
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "hop";
+
+#[cfg(feature = "encryption")]
+const SALT_LEN: usize = 16;
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+/// Known plaintext encrypted into the store to detect a wrong passphrase
+#[cfg(feature = "encryption")]
+const VERIFIER: &[u8] = b"hop-secrets";
+
+/// Per-server secrets, each value encrypted with a key derived from the passphrase
+///
+/// Secret names are stored in the clear so they can be listed without unlocking.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretStore {
+    /// Base64 Argon2 salt; empty until the first secret is stored
+    #[serde(default)]
+    salt: String,
+    #[serde(default)]
+    verifier: String,
+    #[serde(default)]
+    servers: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Key derived from the passphrase, needed to read or write secret values
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+pub struct Cipher(XChaCha20Poly1305);
+
+/// Without the `encryption` feature no key can be derived, so no cipher exists
+#[cfg(not(feature = "encryption"))]
+#[derive(Clone)]
+pub struct Cipher(std::convert::Infallible);
+
+/// The key once `unlock` succeeded, so one run derives it (and asks) only once
+#[cfg(feature = "encryption")]
+static UNLOCKED: Mutex<Option<Cipher>> = Mutex::new(None);
+
+impl SecretStore {
+    /// Derive the key for this store, initialising it if it has never been used
+    #[cfg(feature = "encryption")]
+    pub fn unlock(&mut self, passphrase: &str) -> Result<Cipher> {
+        if self.salt.is_empty() {
+            let salt: [u8; SALT_LEN] = rand_bytes();
+            self.salt = BASE64.encode(salt);
+            let cipher = Cipher::derive(passphrase, &salt)?;
+            self.verifier = cipher.encrypt(VERIFIER)?;
+            return Ok(cipher);
+        }
+        
+        let salt = BASE64.decode(&self.salt).context("Corrupt secrets file: invalid salt")?;
+        let cipher = Cipher::derive(passphrase, &salt)?;
+        if cipher.decrypt(&self.verifier).ok().as_deref() != Some(VERIFIER) {
+            return Err(anyhow::anyhow!("Wrong secrets passphrase"));
+        }
+        Ok(cipher)
+    }
+
+    /// Whether a passphrase has been chosen yet
+    #[cfg(feature = "encryption")]
+    pub fn is_initialized(&self) -> bool {
+        !self.salt.is_empty()
+    }
+
+    pub fn names(&self, server: &str) -> Vec<&str> {
+        self.servers
+            .get(server)
+            .map(|secrets| secrets.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Servers that have at least one secret
+    pub fn servers(&self) -> Vec<&str> {
+        self.servers.keys().map(String::as_str).collect()
+    }
+
+    pub fn get(&self, cipher: &Cipher, server: &str, name: &str) -> Result<Option<String>> {
+        let Some(encrypted) = self.servers.get(server).and_then(|s| s.get(name)) else {
+            return Ok(None);
+        };
+        let plaintext = cipher.decrypt(encrypted)?;
+        Ok(Some(String::from_utf8(plaintext).context("Secret is not valid UTF-8")?))
+    }
+
+    pub fn set(&mut self, cipher: &Cipher, server: &str, name: &str, value: &str) -> Result<()> {
+        let encrypted = cipher.encrypt(value.as_bytes())?;
+        self.servers
+            .entry(server.to_string())
+            .or_default()
+            .insert(name.to_string(), encrypted);
+        Ok(())
+    }
+
+    /// Remove one secret; returns whether it existed
+    pub fn remove(&mut self, server: &str, name: &str) -> bool {
+        let Some(secrets) = self.servers.get_mut(server) else {
+            return false;
+        };
+        let removed = secrets.remove(name).is_some();
+        if secrets.is_empty() {
+            self.servers.remove(server);
+        }
+        removed
+    }
+
+    /// Drop every secret of a server; returns whether it had any
+    pub fn forget_server(&mut self, server: &str) -> bool {
+        self.servers.remove(server).is_some()
+    }
+
+    /// Move secrets along when a server is renamed
+    pub fn rename_server(&mut self, old: &str, new: &str) -> bool {
+        match self.servers.remove(old) {
+            Some(secrets) => {
+                self.servers.insert(new.to_string(), secrets);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl Cipher {
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Cipher> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("Failed to derive key: {}", e))?;
+        Ok(Cipher(XChaCha20Poly1305::new(Key::from_slice(&key))))
+    }
+
+    /// Encrypt to base64(nonce || ciphertext)
+    fn encrypt(&self, plaintext: &[u8]) -> Result<String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(self.0.encrypt(&nonce, plaintext).map_err(|_| anyhow::anyhow!("Encryption failed"))?);
+        Ok(BASE64.encode(sealed))
+    }
+
+    fn decrypt(&self, encoded: &str) -> Result<Vec<u8>> {
+        let sealed = BASE64.decode(encoded).context("Corrupt secret: invalid base64")?;
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("Corrupt secret: too short"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.0
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt secret (wrong passphrase or corrupt file)"))
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+impl Cipher {
+    fn encrypt(&self, _plaintext: &[u8]) -> Result<String> {
+        match self.0 {}
+    }
+
+    fn decrypt(&self, _encoded: &str) -> Result<Vec<u8>> {
+        match self.0 {}
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn rand_bytes<const N: usize>() -> [u8; N] {
+    use chacha20poly1305::aead::rand_core::RngCore;
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Get the path to the encrypted secrets file
+pub fn get_secrets_file_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("secrets.json"))
+}
+
+/// Load the secret store, returning an empty one if none exists
+pub fn load_secrets(settings: &Settings) -> Result<SecretStore> {
+    let path = get_secrets_file_path()?;
+    if !path.exists() {
+        return Ok(SecretStore::default());
+    }
+
+    check_permissions(&path, settings.strict_permissions)?;
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read secrets file: {}", path.display()))?;
+
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse secrets file: {}", path.display()))
+}
+
+/// Save the secret store, readable by the current user only
+pub fn save_secrets(store: &SecretStore) -> Result<()> {
+    let path = get_secrets_file_path()?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+
+    let contents = serde_json::to_string_pretty(store)?;
+    write_private(&path, &contents)
+        .with_context(|| format!("Failed to write secrets file: {}", path.display()))
+}
+
+/// Unlock the store with the passphrase from HOP_SECRETS_PASSPHRASE or a prompt
+///
+/// A new store asks for the passphrase twice. The key is kept for the rest
+/// of the run, so later calls neither prompt nor re-run Argon2.
+#[cfg(feature = "encryption")]
+pub fn unlock(store: &mut SecretStore) -> Result<Cipher> {
+    let mut unlocked = UNLOCKED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cipher) = unlocked.as_ref().filter(|_| store.is_initialized()) {
+        return Ok(cipher.clone());
+    }
+    let cipher = derive(store)?;
+    *unlocked = Some(cipher.clone());
+    Ok(cipher)
+}
+
+/// Without the `encryption` feature secrets.json can be listed and cleaned up, not read or written
+#[cfg(not(feature = "encryption"))]
+pub fn unlock(_store: &mut SecretStore) -> Result<Cipher> {
+    Err(anyhow::anyhow!("hop was built without the 'encryption' feature (cargo build --features encryption)"))
+}
+
+#[cfg(feature = "encryption")]
+fn derive(store: &mut SecretStore) -> Result<Cipher> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return store.unlock(&passphrase);
+    }
+    if !is_interactive() {
+        return Err(anyhow::anyhow!("Secrets are locked; set {} or run hop in a terminal", PASSPHRASE_ENV));
+    }
+    
+    if store.is_initialized() {
+        return store.unlock(&prompt_secret("Secrets passphrase")?);
+    }
+    let passphrase = prompt_secret("New secrets passphrase")?;
+    if passphrase.is_empty() {
+        return Err(anyhow::anyhow!("The passphrase cannot be empty"));
+    }
+    if prompt_secret("Repeat passphrase")? != passphrase {
+        return Err(anyhow::anyhow!("Passphrases do not match"));
+    }
+    store.unlock(&passphrase)
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(server: &str, name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("{}/{}", server, name))
+        .map_err(|e| anyhow::anyhow!("OS keyring unavailable: {}", e))
+}
+
+/// Read a secret from the OS keyring
+#[cfg(feature = "keyring")]
+pub fn keyring_get(server: &str, name: &str) -> Result<Option<String>> {
+    match keyring_entry(server, name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow::anyhow!("OS keyring unavailable: {}", e)),
+    }
+}
+
+/// Store a secret in the OS keyring
+#[cfg(feature = "keyring")]
+pub fn keyring_set(server: &str, name: &str, value: &str) -> Result<()> {
+    keyring_entry(server, name)?
+        .set_password(value)
+        .map_err(|e| anyhow::anyhow!("OS keyring unavailable: {}", e))
+}
+
+/// Delete a secret from the OS keyring; returns whether it existed
+#[cfg(feature = "keyring")]
+pub fn keyring_remove(server: &str, name: &str) -> Result<bool> {
+    match keyring_entry(server, name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(anyhow::anyhow!("OS keyring unavailable: {}", e)),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn keyring_get(_server: &str, _name: &str) -> Result<Option<String>> {
+    Err(keyring_disabled())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn keyring_set(_server: &str, _name: &str, _value: &str) -> Result<()> {
+    Err(keyring_disabled())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn keyring_remove(_server: &str, _name: &str) -> Result<bool> {
+    Err(keyring_disabled())
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_disabled() -> anyhow::Error {
+    anyhow::anyhow!("OS keyring unavailable: hop was built without the 'keyring' feature (cargo build --features keyring)")
+}
+
+/// Find a secret in the OS keyring, then in the encrypted store
+///
+/// An unavailable keyring is skipped; the store is only unlocked (which may
+/// prompt for the passphrase) when it actually holds the secret.
+pub fn lookup(settings: &Settings, server: &str, name: &str) -> Result<Option<String>> {
+    match keyring_get(server, name) {
+        Ok(Some(value)) => return Ok(Some(value)),
+        Ok(None) => {}
+        Err(e) => debug!("{}", e),
+    }
+    
+    let mut store = load_secrets(settings)?;
+    if !store.names(server).contains(&name) {
+        return Ok(None);
+    }
+    let cipher = unlock(&mut store)?;
+    store.get(&cipher, server, name)
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_roundtrip() {
+        let mut store = SecretStore::default();
+        let cipher = store.unlock("correct horse").unwrap();
+        store.set(&cipher, "web", "sudo-password", "hunter2").unwrap();
+
+        let json = serde_json::to_string(&store).unwrap();
+        assert!(!json.contains("hunter2"));
+
+        let mut loaded: SecretStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.names("web"), vec!["sudo-password"]);
+        let cipher = loaded.unlock("correct horse").unwrap();
+        assert_eq!(loaded.get(&cipher, "web", "sudo-password").unwrap().as_deref(), Some("hunter2"));
+        assert_eq!(loaded.get(&cipher, "web", "missing").unwrap(), None);
+
+        assert!(loaded.unlock("wrong").is_err());
+    }
+
+    #[test]
+    fn test_rename_and_remove() {
+        let mut store = SecretStore::default();
+        let cipher = store.unlock("pw").unwrap();
+        store.set(&cipher, "web", "token", "x").unwrap();
+
+        assert!(store.rename_server("web", "web-1"));
+        assert_eq!(store.servers(), vec!["web-1"]);
+        assert!(store.remove("web-1", "token"));
+        assert!(store.servers().is_empty());
+        assert!(!store.forget_server("web-1"));
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use clap::ValueEnum;
+
+/// What `hop service` can do with a remote service
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ServiceAction {
+    Status,
+    Start,
+    Stop,
+    Restart,
+    Reload,
+    /// Show the most recent log lines
+    Logs,
+}
+
+impl ServiceAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            ServiceAction::Status => "status",
+            ServiceAction::Start => "start",
+            ServiceAction::Stop => "stop",
+            ServiceAction::Restart => "restart",
+            ServiceAction::Reload => "reload",
+            ServiceAction::Logs => "logs",
+        }
+    }
+
+    /// Whether the action changes the state of the service
+    pub fn is_mutating(self) -> bool {
+        !matches!(self, ServiceAction::Status | ServiceAction::Logs)
+    }
+}
+
+/// Service names are interpolated into a shell script, so only allow what
+/// init systems use: letters, digits and `@._:-`
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "@._:-".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Invalid service name '{}'", name))
+    }
+}
+
+/// Remote script that runs `action` with systemd, OpenRC or SysV init,
+/// whichever the server uses
+pub fn command(action: ServiceAction, name: &str, lines: u32) -> String {
+    let (systemd, openrc, sysv) = match action {
+        ServiceAction::Logs => (
+            format!("journalctl -u {name} -n {lines} --no-pager"),
+            format!("tail -n {lines} /var/log/{name}.log /var/log/{name}/*.log"),
+            format!("tail -n {lines} /var/log/{name}.log /var/log/{name}/*.log"),
+        ),
+        // A stopped service exits non-zero; still show its status
+        ServiceAction::Status => (
+            format!("systemctl status {name} --no-pager 2>&1 || true"),
+            format!("rc-service {name} status 2>&1 || true"),
+            format!("service {name} status 2>&1 || true"),
+        ),
+        _ => {
+            let verb = action.name();
+            (
+                format!("systemctl {verb} {name}"),
+                format!("rc-service {name} {verb}"),
+                format!("service {name} {verb}"),
+            )
+        }
+    };
+
+    format!(
+        "if [ -d /run/systemd/system ] && command -v systemctl >/dev/null 2>&1; then {systemd}; \
+         elif command -v rc-service >/dev/null 2>&1; then {openrc}; \
+         else {sysv}; fi"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let restart = command(ServiceAction::Restart, "nginx", 50);
+        assert!(restart.contains("then systemctl restart nginx;"));
+        assert!(restart.contains("then rc-service nginx restart;"));
+        assert!(restart.contains("else service nginx restart;"));
+
+        let logs = command(ServiceAction::Logs, "php8.2-fpm", 20);
+        assert!(logs.contains("journalctl -u php8.2-fpm -n 20 --no-pager"));
+
+        assert!(ServiceAction::Reload.is_mutating());
+        assert!(!ServiceAction::Logs.is_mutating());
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("nginx").is_ok());
+        assert!(validate_name("getty@tty1.service").is_ok());
+        assert!(validate_name("nginx; rm -rf /").is_err());
+        assert!(validate_name("--help").is_err());
+        assert!(validate_name("").is_err());
+    }
+}
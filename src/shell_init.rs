@@ -0,0 +1,191 @@
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Names that can be used verbatim as a shell alias or function name
+///
+/// Entries with other characters (older configs predate name validation)
+/// are left out rather than quoted.
+fn alias_names<'a>(names: &[&'a str]) -> Vec<&'a str> {
+    names
+        .iter()
+        .copied()
+        .filter(|name| {
+            !name.is_empty()
+                && !name.starts_with('-')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .collect()
+}
+
+/// Subcommands whose first argument is a server name
+const SERVER_SUBCOMMANDS: &[&str] = &["connect", "show", "edit", "remove", "exec", "copy", "lock"];
+
+/// Render the integration script for `shell`, with one shortcut per server
+///
+/// Shortcuts never shadow an existing command, alias or function.
+pub fn render(shell: Shell, names: &[&str]) -> String {
+    let names = alias_names(names).join(" ");
+    let script = match shell {
+        Shell::Bash => BASH,
+        Shell::Zsh => ZSH,
+        Shell::Fish => FISH,
+    };
+    script.replace("{servers}", &names).replace("{subcommands}", &SERVER_SUBCOMMANDS.join(" "))
+}
+
+const BASH: &str = r#"# hop shell integration. Load it from ~/.bashrc with:
+#   eval "$(hop shell-init bash)"
+__hop_servers=({servers})
+for __hop_name in "${__hop_servers[@]}"; do
+    type "$__hop_name" >/dev/null 2>&1 || alias "$__hop_name=hop connect $__hop_name"
+done
+unset __hop_name
+
+# Open a shell on a server inside a remote directory
+hopcd() {
+    if [ $# -ne 2 ]; then
+        echo "usage: hopcd <server> <remote-dir>" >&2
+        return 64
+    fi
+    hop connect "$1" --cd "$2"
+}
+
+# Alt-h: pick a server and put `hop connect <server>` on the command line
+__hop_pick() {
+    local name=
+    if command -v fzf >/dev/null 2>&1; then
+        name=$(hop complete | fzf --height 40% --reverse --prompt 'hop> ')
+    fi
+    READLINE_LINE="hop connect $name"
+    READLINE_POINT=${#READLINE_LINE}
+}
+if [[ $- == *i* ]]; then
+    bind -x '"\eh": __hop_pick'
+fi
+
+# Complete server names after `hop` and its server subcommands
+__hop_complete() {
+    local cur=${COMP_WORDS[COMP_CWORD]}
+    if [ "$COMP_CWORD" -eq 1 ] || { [ "$COMP_CWORD" -eq 2 ] && [[ " {subcommands} " == *" ${COMP_WORDS[1]} "* ]]; }; then
+        COMPREPLY=($(hop complete "$cur" 2>/dev/null))
+    fi
+}
+complete -F __hop_complete hop
+"#;
+
+const ZSH: &str = r#"# hop shell integration. Load it from ~/.zshrc with:
+#   eval "$(hop shell-init zsh)"
+__hop_servers=({servers})
+for __hop_name in $__hop_servers; do
+    (( $+commands[$__hop_name] || $+aliases[$__hop_name] || $+functions[$__hop_name] )) ||
+        alias "$__hop_name=hop connect $__hop_name"
+done
+unset __hop_name
+
+# Open a shell on a server inside a remote directory
+hopcd() {
+    if (( $# != 2 )); then
+        echo "usage: hopcd <server> <remote-dir>" >&2
+        return 64
+    fi
+    hop connect "$1" --cd "$2"
+}
+
+# Alt-h: pick a server and put `hop connect <server>` on the command line
+__hop_pick() {
+    local name=
+    if (( $+commands[fzf] )); then
+        name=$(hop complete | fzf --height 40% --reverse --prompt 'hop> ')
+    fi
+    BUFFER="hop connect $name"
+    CURSOR=$#BUFFER
+    zle reset-prompt
+}
+if [[ -o interactive ]]; then
+    zle -N __hop_pick
+    bindkey '^[h' __hop_pick
+fi
+
+# Complete server names after `hop` and its server subcommands
+__hop_complete() {
+    if (( CURRENT == 2 )) || { (( CURRENT == 3 )) && (( ${__hop_subcommands[(Ie)$words[2]]} )); }; then
+        compadd -- ${(f)"$(hop complete "$PREFIX" 2>/dev/null)"}
+    fi
+}
+__hop_subcommands=({subcommands})
+(( $+functions[compdef] )) && compdef __hop_complete hop
+"#;
+
+const FISH: &str = r#"# hop shell integration. Load it from ~/.config/fish/config.fish with:
+#   hop shell-init fish | source
+set -g __hop_servers {servers}
+for __hop_name in $__hop_servers
+    type -q $__hop_name; and continue
+    function $__hop_name --inherit-variable __hop_name
+        hop connect $__hop_name $argv
+    end
+end
+set -e __hop_name
+
+# Open a shell on a server inside a remote directory
+function hopcd
+    if test (count $argv) -ne 2
+        echo "usage: hopcd <server> <remote-dir>" >&2
+        return 64
+    end
+    hop connect $argv[1] --cd $argv[2]
+end
+
+# Alt-h: pick a server and put `hop connect <server>` on the command line
+function __hop_pick
+    set -l name
+    if type -q fzf
+        set name (hop complete | fzf --height 40% --reverse --prompt 'hop> ')
+    end
+    commandline -r "hop connect $name"
+    commandline -f repaint
+end
+if status is-interactive
+    bind \eh __hop_pick
+end
+
+# Complete server names after `hop` and its server subcommands
+complete -c hop -f -n __fish_use_subcommand -a '(hop complete 2>/dev/null)'
+complete -c hop -f -n '__fish_seen_subcommand_from {subcommands}' -a '(hop complete 2>/dev/null)'
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_lists_servers() {
+        let script = render(Shell::Bash, &["web01", "db-main"]);
+        assert!(script.contains("__hop_servers=(web01 db-main)"));
+
+        let script = render(Shell::Fish, &["web01"]);
+        assert!(script.contains("set -g __hop_servers web01\n"));
+    }
+
+    #[test]
+    fn test_render_completes_from_index() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = render(shell, &["web01"]);
+            assert!(script.contains("hop complete"));
+            assert!(!script.contains("{subcommands}"));
+            assert!(script.contains("connect show edit"));
+        }
+    }
+
+    #[test]
+    fn test_render_skips_unsafe_names() {
+        let script = render(Shell::Zsh, &["web01", "rm -rf", "$(x)", "-n"]);
+        assert!(script.contains("__hop_servers=(web01)"));
+    }
+}
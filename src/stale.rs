@@ -0,0 +1,105 @@
+use crate::config::Settings;
+use crate::health::HealthStatus;
+use crate::history::Usage;
+use crate::models::Server;
+use crate::utils::{ensure_dir_exists, write_private};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+const DAY: u64 = 86_400;
+/// Months are counted as 30 days
+const MONTH: u64 = 30 * DAY;
+
+/// Why a server counts as stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleReason {
+    /// Every health check since this timestamp failed
+    Unreachable { since: u64 },
+    /// No connection since this timestamp: the last one, or when the server was added
+    Unused { since: u64 },
+}
+
+/// Whether a server is stale: unreachable in every health check for
+/// `stale_days`, or not connected to for `stale_months`
+///
+/// Servers never connected to are only flagged when hop knows when they were
+/// added, which older entries don't record.
+pub fn stale_reason(server: &Server, health: Option<&HealthStatus>, usage: Usage, settings: &Settings, now: u64) -> Option<StaleReason> {
+    let unreachable_since = health.filter(|h| !h.reachable).and_then(|h| h.unreachable_since);
+    if let Some(since) = unreachable_since.filter(|&since| now.saturating_sub(since) >= settings.stale_days * DAY) {
+        return Some(StaleReason::Unreachable { since });
+    }
+    
+    let last_used = if usage.count > 0 { Some(usage.last_used) } else { server.added_at };
+    last_used
+        .filter(|&since| now.saturating_sub(since) >= settings.stale_months * MONTH)
+        .map(|since| StaleReason::Unused { since })
+}
+
+/// Get the path to the file holding servers removed with `hop list --archive-stale`
+pub fn get_archive_file_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("archive.json"))
+}
+
+/// Append servers to the archive, which uses the `hop import` format
+pub fn archive(servers: &[Server]) -> Result<PathBuf> {
+    let path = get_archive_file_path()?;
+    let mut archived: Vec<Server> = if path.exists() {
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read archive: {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse archive: {}", path.display()))?
+    } else {
+        Vec::new()
+    };
+    archived.extend_from_slice(servers);
+    
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    write_private(&path, &serde_json::to_string_pretty(&archived)?)
+        .with_context(|| format!("Failed to write archive: {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_reason() {
+        let now = 400 * DAY;
+        let settings = Settings::default();
+        let mut server = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        let used = |days_ago: u64| Usage { last_used: now - days_ago * DAY, count: 3 };
+        let down = |days: u64| HealthStatus {
+            reachable: false,
+            checked_at: now,
+            error: Some("timeout".to_string()),
+            tcp_ms: None,
+            ssh_ms: None,
+            reboot_required: None,
+            unreachable_since: Some(now - days * DAY),
+        };
+
+        assert_eq!(stale_reason(&server, None, used(1), &settings, now), None);
+        assert_eq!(stale_reason(&server, Some(&down(10)), used(1), &settings, now), None);
+        assert_eq!(
+            stale_reason(&server, Some(&down(30)), used(1), &settings, now),
+            Some(StaleReason::Unreachable { since: now - 30 * DAY })
+        );
+        assert_eq!(
+            stale_reason(&server, None, used(200), &settings, now),
+            Some(StaleReason::Unused { since: now - 200 * DAY })
+        );
+
+        // Never connected: only known to be stale with an added date
+        assert_eq!(stale_reason(&server, None, Usage::default(), &settings, now), None);
+        server.added_at = Some(now - 365 * DAY);
+        assert!(stale_reason(&server, None, Usage::default(), &settings, now).is_some());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::models::Server;
+use anyhow::Result;
+use serde::de::{Deserializer as _, SeqAccess, Visitor};
+use std::fmt;
+use std::io::{BufRead, Write};
+
+/// Read servers one at a time from either a JSON array or JSON Lines
+/// (one server object per line), without holding the whole file in memory
+pub fn read_servers<R: BufRead>(mut reader: R, mut on_server: impl FnMut(Server)) -> Result<usize> {
+    match first_byte(&mut reader)? {
+        None => Ok(0),
+        Some(b'[') => read_array(reader, &mut on_server),
+        Some(_) => read_lines(reader, &mut on_server),
+    }
+}
+
+/// Skip leading whitespace and peek at the first byte after it
+fn first_byte<R: BufRead>(reader: &mut R) -> Result<Option<u8>> {
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(pos) => {
+                let byte = buf[pos];
+                reader.consume(pos);
+                return Ok(Some(byte));
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+fn read_array<R: BufRead>(reader: R, on_server: &mut dyn FnMut(Server)) -> Result<usize> {
+    struct ServerSeq<'a>(&'a mut dyn FnMut(Server));
+
+    impl<'de> Visitor<'de> for ServerSeq<'_> {
+        type Value = usize;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an array of servers")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+            let mut count = 0;
+            while let Some(server) = seq.next_element::<Server>()? {
+                (self.0)(server);
+                count += 1;
+            }
+            Ok(count)
+        }
+    }
+
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let count = (&mut de).deserialize_seq(ServerSeq(on_server))
+        .map_err(|e| anyhow::anyhow!("Failed to parse JSON: {}", e))?;
+    de.end().map_err(|e| anyhow::anyhow!("Failed to parse JSON: {}", e))?;
+    Ok(count)
+}
+
+fn read_lines<R: BufRead>(reader: R, on_server: &mut dyn FnMut(Server)) -> Result<usize> {
+    let mut count = 0;
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let server = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("Failed to parse JSON on line {}: {}", number + 1, e))?;
+        on_server(server);
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Write servers as JSON Lines, one object per line
+pub fn write_lines<W: Write>(mut writer: W, servers: &[Server]) -> Result<()> {
+    for server in servers {
+        serde_json::to_writer(&mut writer, server)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        read_servers(input.as_bytes(), |server| names.push(server.name))?;
+        Ok(names)
+    }
+
+    #[test]
+    fn test_read_array_and_lines() {
+        let array = r#" [{"name":"web","user":"root","ip":"10.0.0.1"},
+                         {"name":"db","user":"postgres","ip":"10.0.0.2"}] "#;
+        assert_eq!(read(array).unwrap(), vec!["web", "db"]);
+
+        let lines = "{\"name\":\"web\",\"user\":\"root\",\"ip\":\"10.0.0.1\"}\n\n{\"name\":\"db\",\"user\":\"postgres\",\"ip\":\"10.0.0.2\"}\n";
+        assert_eq!(read(lines).unwrap(), vec!["web", "db"]);
+
+        assert!(read("  \n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_reports_bad_line() {
+        let lines = "{\"name\":\"web\",\"user\":\"root\",\"ip\":\"10.0.0.1\"}\n{\"name\":\n";
+        let err = read(lines).unwrap_err().to_string();
+        assert!(err.contains("line 2"), "{}", err);
+
+        assert!(read("[{\"name\":\"web\",\"user\":\"root\",\"ip\":\"10.0.0.1\"}] trailing").is_err());
+    }
+
+    #[test]
+    fn test_write_lines_round_trips() {
+        let servers = vec![
+            Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string()),
+            Server::new("db".to_string(), "postgres".to_string(), "10.0.0.2".to_string()),
+        ];
+        let mut out = Vec::new();
+        write_lines(&mut out, &servers).unwrap();
+
+        assert_eq!(String::from_utf8(out.clone()).unwrap().lines().count(), 2);
+        assert_eq!(read(std::str::from_utf8(&out).unwrap()).unwrap(), vec!["web", "db"]);
+    }
+}
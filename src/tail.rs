@@ -0,0 +1,19 @@
+use crate::utils::shell_quote;
+
+/// Remote command printing the last `lines` lines of `path`, then (with
+/// `follow`) new lines as they are written, across log rotation
+pub fn tail_command(path: &str, lines: u32, follow: bool) -> String {
+    let follow = if follow { " -F" } else { "" };
+    format!("tail -n {}{} -- {}", lines, follow, shell_quote(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_command() {
+        assert_eq!(tail_command("/var/log/app/current", 10, true), "tail -n 10 -F -- '/var/log/app/current'");
+        assert_eq!(tail_command("/var/log/my app.log", 0, false), "tail -n 0 -- '/var/log/my app.log'");
+    }
+}
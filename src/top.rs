@@ -0,0 +1,89 @@
+use anyhow::Result;
+
+/// Remote probe: load average, CPU count, memory (total/available bytes) and root disk usage
+///
+/// Linux only; it relies on /proc, nproc, free and POSIX df.
+pub const PROBE_COMMAND: &str = "cat /proc/loadavg; nproc; free -b | awk '/^Mem:/ {print $2, $7}'; df -P / | awk 'NR==2 {print $5}'";
+
+/// Resource usage of one server
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resources {
+    pub load1: f64,
+    pub cpus: u32,
+    pub mem_total: u64,
+    pub mem_available: u64,
+    /// Percentage of the root filesystem in use
+    pub disk_percent: u8,
+}
+
+impl Resources {
+    /// 1-minute load divided by the number of CPUs, as a percentage
+    pub fn cpu_percent(&self) -> f64 {
+        self.load1 / f64::from(self.cpus.max(1)) * 100.0
+    }
+
+    pub fn mem_percent(&self) -> f64 {
+        if self.mem_total == 0 {
+            return 0.0;
+        }
+        (self.mem_total - self.mem_available.min(self.mem_total)) as f64 / self.mem_total as f64 * 100.0
+    }
+
+    /// The worst of CPU, memory and disk, used to sort the busiest servers first
+    pub fn pressure(&self) -> f64 {
+        self.cpu_percent().max(self.mem_percent()).max(f64::from(self.disk_percent))
+    }
+}
+
+/// Parse the output of `PROBE_COMMAND`
+pub fn parse(output: &str) -> Result<Resources> {
+    let invalid = || anyhow::anyhow!("Unexpected probe output (is this a Linux host?)");
+    let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+    
+    let load1 = lines.next().and_then(|l| l.split_whitespace().next()).and_then(|v| v.parse().ok()).ok_or_else(invalid)?;
+    let cpus = lines.next().and_then(|l| l.parse().ok()).ok_or_else(invalid)?;
+    let mut memory = lines.next().ok_or_else(invalid)?.split_whitespace().map(str::parse::<u64>);
+    let (mem_total, mem_available) = match (memory.next(), memory.next()) {
+        (Some(Ok(total)), Some(Ok(available))) => (total, available),
+        _ => return Err(invalid()),
+    };
+    let disk_percent = lines.next().and_then(|l| l.trim_end_matches('%').parse().ok()).ok_or_else(invalid)?;
+    
+    Ok(Resources { load1, cpus, mem_total, mem_available, disk_percent })
+}
+
+/// Format a byte count with a binary unit, e.g. "7.6G"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = "1.50 0.80 0.40 2/345 6789\n4\n8589934592 2147483648\n73%\n";
+        let resources = parse(output).unwrap();
+        assert_eq!(resources.cpus, 4);
+        assert_eq!(resources.disk_percent, 73);
+        assert!((resources.cpu_percent() - 37.5).abs() < 1e-9);
+        assert!((resources.mem_percent() - 75.0).abs() < 1e-9);
+        assert!((resources.pressure() - 75.0).abs() < 1e-9);
+        assert_eq!(format_bytes(resources.mem_total), "8.0G");
+        assert_eq!(format_bytes(512), "512B");
+
+        assert!(parse("Darwin\n").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use crate::background;
+use crate::utils::{ensure_dir_exists, write_private};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Longest wait between reconnect attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A connection that stayed up this long counts as healthy, resetting the backoff
+pub const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// State of a supervised tunnel, rewritten by its supervisor on every change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TunnelState {
+    pub name: String,
+    pub server: String,
+    /// ssh -L specifications, e.g. "5432:localhost:5432"
+    pub forwards: Vec<String>,
+    /// Process id of the hop process supervising the tunnel
+    pub pid: u32,
+    /// Process id of the current ssh process, if one is running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_pid: Option<u32>,
+    /// Unix timestamp (seconds) when the supervisor started
+    pub started_at: u64,
+    /// Unix timestamp (seconds) when the current ssh connection was started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connected_since: Option<u64>,
+    /// How many times the ssh process has died and been restarted
+    #[serde(default)]
+    pub flaps: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_flap: Option<u64>,
+}
+
+impl TunnelState {
+    pub fn is_running(&self) -> bool {
+        background::is_alive(self.pid)
+    }
+}
+
+/// Check an ssh -L specification: `[bind_address:]port:host:hostport`
+pub fn validate_forward(spec: &str) -> Result<()> {
+    let parts: Vec<&str> = spec.split(':').collect();
+    let is_port = |value: &str| value.parse::<u16>().is_ok_and(|port| port > 0);
+    let valid = match parts[..] {
+        [port, host, host_port] => is_port(port) && !host.is_empty() && is_port(host_port),
+        [bind, port, host, host_port] => !bind.is_empty() && is_port(port) && !host.is_empty() && is_port(host_port),
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Invalid forward '{}': expected [bind_address:]port:host:hostport", spec))
+    }
+}
+
+/// Wait before reconnect attempt `attempt` (0-based): 1s, 2s, 4s, ... up to a minute
+pub fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.min(6)).min(MAX_BACKOFF)
+}
+
+fn tunnels_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("tunnels"))
+}
+
+/// Get the path to a tunnel's state file
+pub fn get_state_file_path(name: &str) -> Result<PathBuf> {
+    Ok(tunnels_dir()?.join(format!("{}.json", name)))
+}
+
+/// Get the path to a detached tunnel's output
+pub fn get_log_file_path(name: &str) -> Result<PathBuf> {
+    Ok(tunnels_dir()?.join(format!("{}.log", name)))
+}
+
+pub fn load(name: &str) -> Result<Option<TunnelState>> {
+    let path = get_state_file_path(name)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read tunnel state: {}", path.display()))?;
+    let state = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse tunnel state: {}", path.display()))?;
+    Ok(Some(state))
+}
+
+pub fn save(state: &TunnelState) -> Result<()> {
+    let path = get_state_file_path(&state.name)?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    write_private(&path, &serde_json::to_string_pretty(state)?)
+        .with_context(|| format!("Failed to write tunnel state: {}", path.display()))
+}
+
+/// All tunnels with a state file, running or not, sorted by name
+pub fn list() -> Result<Vec<TunnelState>> {
+    let dir = tunnels_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut tunnels = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                tunnels.extend(load(name)?);
+            }
+        }
+    }
+    tunnels.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tunnels)
+}
+
+/// Stop a tunnel's supervisor and ssh process and forget it
+pub fn stop(state: &TunnelState) -> Result<()> {
+    if state.is_running() {
+        background::terminate(state.pid).with_context(|| format!("Failed to stop tunnel {}", state.name))?;
+    }
+    if let Some(ssh_pid) = state.ssh_pid.filter(|&pid| background::is_alive(pid)) {
+        background::terminate(ssh_pid).with_context(|| format!("Failed to stop ssh for tunnel {}", state.name))?;
+    }
+    forget(&state.name)
+}
+
+/// Remove a tunnel's state file, once its supervisor has stopped
+pub fn forget(name: &str) -> Result<()> {
+    let _ = fs::remove_file(get_state_file_path(name)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_forward() {
+        assert!(validate_forward("5432:localhost:5432").is_ok());
+        assert!(validate_forward("127.0.0.1:8080:10.0.0.5:80").is_ok());
+        assert!(validate_forward("5432").is_err());
+        assert!(validate_forward("0:localhost:5432").is_err());
+        assert!(validate_forward("5432::5432").is_err());
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(20), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::models::Server;
+use crate::utils::{ensure_dir_exists, server_file_name, write_private};
+use anyhow::{Context, Result};
+use log::debug;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Secrets engine mount used when a server sets no `vault_mount`
+pub const DEFAULT_MOUNT: &str = "ssh";
+
+/// Public keys offered for signing, in order of preference
+const PUBLIC_KEYS: &[&str] = &["id_ed25519.pub", "id_ecdsa.pub", "id_rsa.pub"];
+
+/// A short-lived credential issued by Vault's SSH secrets engine
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credential {
+    /// Signed certificate for the user's key, passed to ssh as CertificateFile
+    Certificate(PathBuf),
+    /// One-time password, fed to ssh through sshpass
+    Otp(String),
+}
+
+/// Request a fresh credential for `server`, or `None` when it has no Vault role
+///
+/// Talks to Vault through the `vault` CLI, so VAULT_ADDR, VAULT_TOKEN and the
+/// CLI's own login state apply as usual.
+pub fn credential_for(server: &Server) -> Result<Option<Credential>> {
+    let Some(role) = server.vault_role.as_deref() else {
+        return Ok(None);
+    };
+    let mount = server.vault_mount.as_deref().unwrap_or(DEFAULT_MOUNT);
+    
+    if server.vault_otp {
+        let otp = run_vault(&[
+            "write".to_string(),
+            "-field=key".to_string(),
+            format!("{}/creds/{}", mount, role),
+            format!("ip={}", server.ip),
+            format!("username={}", server.user),
+        ])?;
+        return Ok(Some(Credential::Otp(otp)));
+    }
+    
+    let public_key = find_public_key()?;
+    let certificate = run_vault(&[
+        "write".to_string(),
+        "-field=signed_key".to_string(),
+        format!("{}/sign/{}", mount, role),
+        format!("public_key=@{}", public_key.display()),
+        format!("valid_principals={}", server.user),
+    ])?;
+    
+    let path = get_certificate_path(&server.qualified_name())?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    write_private(&path, &format!("{}\n", certificate))
+        .with_context(|| format!("Failed to write certificate: {}", path.display()))?;
+    Ok(Some(Credential::Certificate(path)))
+}
+
+/// Where the signed certificate for a server is kept between requests
+pub fn get_certificate_path(name: &str) -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop").join("vault").join(format!("{}-cert.pub", server_file_name(name))))
+}
+
+fn find_public_key() -> Result<PathBuf> {
+    let ssh_dir = dirs::home_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+        .join(".ssh");
+    PUBLIC_KEYS
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .find(|path| path.exists())
+        .ok_or_else(|| anyhow::anyhow!(
+            "No SSH public key to sign in {} (looked for {})",
+            ssh_dir.display(),
+            PUBLIC_KEYS.join(", ")
+        ))
+}
+
+fn run_vault(args: &[String]) -> Result<String> {
+    debug!("argv: \"vault\" {:?}", args);
+    let output = Command::new("vault")
+        .args(args)
+        .output()
+        .context("Failed to run the Vault CLI (`vault`); is it installed?")?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("vault failed: {}", stderr.trim()));
+    }
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if value.is_empty() {
+        return Err(anyhow::anyhow!("vault returned an empty credential"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_role_means_no_credential() {
+        let server = Server::new("web".to_string(), "root".to_string(), "10.0.0.1".to_string());
+        assert_eq!(credential_for(&server).unwrap(), None);
+    }
+
+    #[test]
+    fn test_certificate_path() {
+        let path = get_certificate_path("web").unwrap();
+        assert!(path.ends_with("hop/vault/web-cert.pub"));
+    }
+}
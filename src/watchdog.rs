@@ -0,0 +1,129 @@
+use crate::background;
+use crate::utils::{ensure_dir_exists, write_private};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Last known reachability per server, kept across watchdog restarts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogState {
+    pub servers: BTreeMap<String, bool>,
+}
+
+/// A server that went down or came back since the previous round
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub server: String,
+    pub reachable: bool,
+}
+
+impl WatchdogState {
+    /// Record a round of results and return what changed
+    ///
+    /// A server seen for the first time only counts as a change when it is
+    /// down, so starting the watchdog does not announce every healthy host.
+    pub fn update(&mut self, results: &[(String, bool)]) -> Vec<Transition> {
+        let mut transitions = Vec::new();
+        for (server, reachable) in results {
+            let previous = self.servers.insert(server.clone(), *reachable);
+            let changed = match previous {
+                Some(previous) => previous != *reachable,
+                None => !*reachable,
+            };
+            if changed {
+                transitions.push(Transition { server: server.clone(), reachable: *reachable });
+            }
+        }
+        transitions
+    }
+}
+
+fn hop_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+
+    Ok(config_dir.join("hop"))
+}
+
+/// Get the path to the watchdog state file
+pub fn get_state_file_path() -> Result<PathBuf> {
+    Ok(hop_dir()?.join("watchdog.json"))
+}
+
+/// Get the path to the file holding the background watchdog's process id
+pub fn get_pid_file_path() -> Result<PathBuf> {
+    Ok(hop_dir()?.join("watchdog.pid"))
+}
+
+/// Get the path to the background watchdog's output
+pub fn get_log_file_path() -> Result<PathBuf> {
+    Ok(hop_dir()?.join("watchdog.log"))
+}
+
+pub fn load_state() -> Result<WatchdogState> {
+    let path = get_state_file_path()?;
+    if !path.exists() {
+        return Ok(WatchdogState::default());
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read watchdog state: {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse watchdog state: {}", path.display()))
+}
+
+pub fn save_state(state: &WatchdogState) -> Result<()> {
+    let path = get_state_file_path()?;
+    if let Some(parent) = path.parent() {
+        ensure_dir_exists(parent)?;
+    }
+    write_private(&path, &serde_json::to_string_pretty(state)?)
+        .with_context(|| format!("Failed to write watchdog state: {}", path.display()))
+}
+
+/// Process id of the running background watchdog, if any
+pub fn running_pid() -> Result<Option<u32>> {
+    let path = get_pid_file_path()?;
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    Ok(contents.trim().parse().ok().filter(|&pid| background::is_alive(pid)))
+}
+
+/// Start `hop` again with `args` in the background, detached from the terminal
+pub fn spawn_background(args: &[String]) -> Result<u32> {
+    let pid = background::spawn(args, &get_log_file_path()?).context("Failed to start the watchdog")?;
+    write_private(&get_pid_file_path()?, &pid.to_string())?;
+    Ok(pid)
+}
+
+/// Stop the background watchdog; returns whether one was running
+pub fn stop() -> Result<bool> {
+    let Some(pid) = running_pid()? else {
+        return Ok(false);
+    };
+    background::terminate(pid).context("Failed to stop the watchdog")?;
+    let _ = fs::remove_file(get_pid_file_path()?);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        let mut state = WatchdogState::default();
+        let round = |web: bool, db: bool| vec![("web".to_string(), web), ("db".to_string(), db)];
+
+        // First round: only the server that is already down is reported
+        assert_eq!(state.update(&round(true, false)), vec![Transition { server: "db".to_string(), reachable: false }]);
+        assert!(state.update(&round(true, false)).is_empty());
+
+        let changes = state.update(&round(false, true));
+        assert_eq!(changes.len(), 2);
+        assert!(!changes[0].reachable);
+        assert!(changes[1].reachable);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::config::Settings;
+#[cfg(feature = "webhooks")]
+use crate::http;
+use crate::utils::{local_actor, now_unix};
+use anyhow::Result;
+#[cfg(feature = "webhooks")]
+use log::debug;
+use serde::Serialize;
+#[cfg(feature = "webhooks")]
+use std::thread;
+#[cfg(feature = "webhooks")]
+use std::time::Duration;
+
+/// How long a webhook may take before hop gives up on it
+#[cfg(feature = "webhooks")]
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Attempts per event, including the first one
+#[cfg(feature = "webhooks")]
+const MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled for each further retry
+#[cfg(feature = "webhooks")]
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound for a server-requested Retry-After, so hop never stalls for long
+#[cfg(feature = "webhooks")]
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// Payload POSTed to the webhook after a config change
+#[derive(Debug, Serialize)]
+pub struct Event<'a> {
+    /// The hop command that made the change, e.g. "add" or "remove"
+    pub command: &'a str,
+    pub server: &'a str,
+    /// `user@host` of whoever ran hop
+    pub actor: String,
+    pub timestamp: u64,
+}
+
+impl<'a> Event<'a> {
+    pub fn new(command: &'a str, server: &'a str) -> Self {
+        Event {
+            command,
+            server,
+            actor: local_actor(),
+            timestamp: now_unix(),
+        }
+    }
+}
+
+/// POST an event to the webhook as JSON
+///
+/// Network errors and 5xx responses are retried with exponential backoff;
+/// a 429 waits for the server's Retry-After (capped) before retrying.
+#[cfg(feature = "webhooks")]
+pub fn send(url: &str, event: &Event, settings: &Settings) -> Result<()> {
+    let agent = http::agent_for(url, settings, TIMEOUT)?;
+    
+    let mut attempt = 1;
+    loop {
+        debug!("webhook: POST {} {:?} (attempt {})", url, event, attempt);
+        let retry_after = match agent.post(url).send_json(event) {
+            Ok(_) => return Ok(()),
+            Err(ureq::Error::Status(429, response)) => response.header("Retry-After").map(str::to_string),
+            Err(ureq::Error::Status(code, _)) if code >= 500 => None,
+            Err(ureq::Error::Transport(ref e)) if attempt < MAX_ATTEMPTS => {
+                debug!("webhook: {}", e);
+                None
+            }
+            Err(e) => return Err(anyhow::anyhow!("Webhook request to {} failed: {}", url, e)),
+        };
+        
+        if attempt == MAX_ATTEMPTS {
+            return Err(anyhow::anyhow!("Webhook request to {} failed after {} attempts", url, attempt));
+        }
+        let delay = retry_delay(attempt, retry_after.as_deref());
+        debug!("webhook: retrying in {:?}", delay);
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Without the `webhooks` feature there is no HTTP client to send with
+#[cfg(not(feature = "webhooks"))]
+pub fn send(url: &str, _event: &Event, _settings: &Settings) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Not sending the webhook to {}: hop was built without the 'webhooks' feature (cargo build --features webhooks)",
+        url
+    ))
+}
+
+/// Delay before retry number `attempt`, preferring the server's Retry-After seconds
+#[cfg(feature = "webhooks")]
+fn retry_delay(attempt: u32, retry_after: Option<&str>) -> Duration {
+    match retry_after.and_then(|value| value.trim().parse::<u64>().ok()) {
+        Some(seconds) => Duration::from_secs(seconds).min(MAX_RETRY_AFTER),
+        None => BASE_BACKOFF * 2u32.pow(attempt - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "webhooks")]
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1, None), Duration::from_millis(500));
+        assert_eq!(retry_delay(2, None), Duration::from_millis(1000));
+        assert_eq!(retry_delay(1, Some("3")), Duration::from_secs(3));
+        assert_eq!(retry_delay(1, Some("3600")), MAX_RETRY_AFTER);
+        // HTTP dates are not worth parsing for a best-effort webhook
+        assert_eq!(retry_delay(2, Some("Wed, 21 Oct 2026 07:28:00 GMT")), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_event_json() {
+        let event = Event {
+            command: "add",
+            server: "web",
+            actor: "alice@laptop".to_string(),
+            timestamp: 1_700_000_000,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"command":"add","server":"web","actor":"alice@laptop","timestamp":1700000000}"#
+        );
+    }
+}